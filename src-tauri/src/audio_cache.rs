@@ -0,0 +1,427 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+const AUDIO_CACHE_DIR_NAME: &str = "audio";
+const INDEX_FILE_NAME: &str = "index.json";
+// 完整的音频文件
+const AUDIO_EXTENSION: &str = "audio";
+// 只缓存了开头一部分的音频文件
+const PART_EXTENSION: &str = "part";
+// 默认最多占用 1 GB
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+// 缓存的音频，从文件开头连续缓存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioCacheEntry {
+    pub platform: String,
+    pub id: String,
+    pub content_type: Option<String>,
+    // 文件总大小，服务端没有返回时为空
+    pub total: Option<u64>,
+    // 服务端返回的 ETag 或 Last-Modified，续传时作为 If-Range 发送
+    pub validator: Option<String>,
+    // 已缓存的字节数
+    pub size: u64,
+    pub complete: bool,
+    // 最近一次使用的时间（Unix 秒），超出容量时先删除最久未使用的
+    pub last_access: i64,
+    // 离线歌单中的歌曲，超出容量时也不删除
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct AudioCacheIndex {
+    max_bytes: u64,
+    entries: HashMap<String, AudioCacheEntry>,
+}
+
+impl Default for AudioCacheIndex {
+    fn default() -> Self {
+        AudioCacheIndex {
+            max_bytes: DEFAULT_MAX_BYTES,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+// 缓存占用情况
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioCacheInfo {
+    pub entries: usize,
+    // 完整缓存的歌曲数
+    pub complete: usize,
+    pub size_bytes: u64,
+    pub max_bytes: u64,
+}
+
+// 写入缓存的凭据，释放时允许其他连接写入
+pub struct WriteGuard {
+    key: String,
+    writing: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        lock(&self.writing).remove(&self.key);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// 磁盘上的音频缓存，以 平台 + 歌曲 id 为键，按容量上限淘汰最久未使用的歌曲
+pub struct AudioCache {
+    dir: PathBuf,
+    index: Mutex<AudioCacheIndex>,
+    // 正在写入的条目，同一首歌同时只有一个连接写入
+    writing: Arc<Mutex<HashSet<String>>>,
+}
+
+impl AudioCache {
+    pub fn new(cache_dir: &Path) -> Self {
+        let dir = cache_dir.join(AUDIO_CACHE_DIR_NAME);
+        let mut index: AudioCacheIndex = fs::read_to_string(dir.join(INDEX_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let cache = AudioCache {
+            dir,
+            index: Mutex::new(AudioCacheIndex::default()),
+            writing: Arc::new(Mutex::new(HashSet::new())),
+        };
+        // 以磁盘上的文件为准，去掉已被删除的条目
+        index.entries.retain(|key, entry| {
+            let path = if entry.complete {
+                cache.audio_path(key)
+            } else {
+                cache.part_path(key)
+            };
+            match fs::metadata(path) {
+                Ok(metadata) if metadata.len() > 0 => {
+                    entry.size = metadata.len();
+                    true
+                }
+                _ => false,
+            }
+        });
+        *lock(&cache.index) = index;
+        cache
+    }
+
+    pub fn key_for(platform: &str, id: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(platform.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(id.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn audio_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, AUDIO_EXTENSION))
+    }
+
+    pub fn part_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, PART_EXTENSION))
+    }
+
+    fn save(&self, index: &AudioCacheIndex) {
+        let saved = fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("创建缓存目录失败: {}", e))
+            .and_then(|_| {
+                serde_json::to_string(index).map_err(|e| format!("序列化缓存失败: {}", e))
+            })
+            .and_then(|content| {
+                fs::write(self.dir.join(INDEX_FILE_NAME), content)
+                    .map_err(|e| format!("写入缓存失败: {}", e))
+            });
+        if let Err(e) = saved {
+            eprintln!("{}", e);
+        }
+    }
+
+    pub fn entry(&self, key: &str) -> Option<AudioCacheEntry> {
+        lock(&self.index).entries.get(key).cloned()
+    }
+
+    // 歌曲是否已完整缓存
+    pub fn is_complete(&self, platform: &str, id: &str) -> bool {
+        self.entry(&Self::key_for(platform, id))
+            .is_some_and(|entry| entry.complete)
+    }
+
+    // 更新最近使用时间
+    pub fn touch(&self, key: &str) {
+        let mut index = lock(&self.index);
+        if let Some(entry) = index.entries.get_mut(key) {
+            entry.last_access = now_secs();
+            self.save(&index);
+        }
+    }
+
+    // 其他连接正在写入时返回空
+    pub fn try_write(&self, key: &str) -> Option<WriteGuard> {
+        if !lock(&self.writing).insert(key.to_string()) {
+            return None;
+        }
+        Some(WriteGuard {
+            key: key.to_string(),
+            writing: self.writing.clone(),
+        })
+    }
+
+    // 开始写入缓存，entry.size 为已缓存的字节数
+    pub fn begin(&self, key: &str, entry: AudioCacheEntry) {
+        let mut index = lock(&self.index);
+        let pinned = index.entries.get(key).is_some_and(|entry| entry.pinned);
+        index.entries.insert(
+            key.to_string(),
+            AudioCacheEntry {
+                complete: false,
+                last_access: now_secs(),
+                pinned,
+                ..entry
+            },
+        );
+        self.save(&index);
+    }
+
+    // 登记已经下载到 audio_path 的完整音频
+    pub fn insert_complete(&self, key: &str, entry: AudioCacheEntry) {
+        let mut index = lock(&self.index);
+        let _ = fs::remove_file(self.part_path(key));
+        index.entries.insert(
+            key.to_string(),
+            AudioCacheEntry {
+                complete: true,
+                total: Some(entry.size),
+                last_access: now_secs(),
+                ..entry
+            },
+        );
+        self.evict(&mut index);
+        self.save(&index);
+    }
+
+    // 只保留 keys 中歌曲的固定标记
+    pub fn set_pinned(&self, keys: &HashSet<String>) {
+        let mut index = lock(&self.index);
+        for (key, entry) in index.entries.iter_mut() {
+            entry.pinned = keys.contains(key);
+        }
+        self.evict(&mut index);
+        self.save(&index);
+    }
+
+    // 写入结束，缓存完整时保存为完整的音频文件
+    pub fn finish(&self, key: &str, size: u64, complete: bool) {
+        let mut index = lock(&self.index);
+        let Some(entry) = index.entries.get_mut(key) else {
+            return;
+        };
+        entry.size = size;
+        if complete {
+            match fs::rename(self.part_path(key), self.audio_path(key)) {
+                Ok(_) => {
+                    entry.complete = true;
+                    entry.total = Some(size);
+                }
+                Err(e) => eprintln!("保存音频缓存失败: {}", e),
+            }
+        }
+        self.evict(&mut index);
+        self.save(&index);
+    }
+
+    fn remove_files(&self, key: &str) {
+        let _ = fs::remove_file(self.audio_path(key));
+        let _ = fs::remove_file(self.part_path(key));
+    }
+
+    // 删除最久未使用的歌曲，直到不超过容量上限；正在写入和固定的歌曲不删除
+    fn evict(&self, index: &mut AudioCacheIndex) {
+        let mut used: u64 = index.entries.values().map(|entry| entry.size).sum();
+        if used <= index.max_bytes {
+            return;
+        }
+        let writing = lock(&self.writing);
+        let mut entries: Vec<(String, i64, u64)> = index
+            .entries
+            .iter()
+            .filter(|(key, entry)| !entry.pinned && !writing.contains(*key))
+            .map(|(key, entry)| (key.clone(), entry.last_access, entry.size))
+            .collect();
+        entries.sort_by_key(|(_, last_access, _)| *last_access);
+        for (key, _, size) in entries {
+            if used <= index.max_bytes {
+                break;
+            }
+            self.remove_files(&key);
+            index.entries.remove(&key);
+            used = used.saturating_sub(size);
+        }
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        let mut index = lock(&self.index);
+        index.max_bytes = max_bytes;
+        self.evict(&mut index);
+        self.save(&index);
+    }
+
+    pub fn info(&self) -> AudioCacheInfo {
+        let index = lock(&self.index);
+        AudioCacheInfo {
+            entries: index.entries.len(),
+            complete: index
+                .entries
+                .values()
+                .filter(|entry| entry.complete)
+                .count(),
+            size_bytes: index.entries.values().map(|entry| entry.size).sum(),
+            max_bytes: index.max_bytes,
+        }
+    }
+
+    // 清除缓存，正在写入和离线歌单中的歌曲除外
+    pub fn clear(&self) {
+        let mut index = lock(&self.index);
+        let writing = lock(&self.writing);
+        let keys: Vec<String> = index
+            .entries
+            .iter()
+            .filter(|(key, entry)| !entry.pinned && !writing.contains(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.remove_files(&key);
+            index.entries.remove(&key);
+        }
+        drop(writing);
+        self.save(&index);
+    }
+}
+
+#[tauri::command]
+pub fn get_audio_cache_info(cache: State<'_, AudioCache>) -> AudioCacheInfo {
+    cache.info()
+}
+
+// 设置音频缓存的容量上限（字节），超出的部分立即删除
+#[tauri::command]
+pub fn set_audio_cache_limit(cache: State<'_, AudioCache>, max_bytes: u64) {
+    cache.set_max_bytes(max_bytes);
+}
+
+#[tauri::command]
+pub fn clear_audio_cache(cache: State<'_, AudioCache>) {
+    cache.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("audio_cache_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // 登记一首 100 字节的完整缓存，last_access 决定淘汰顺序
+    fn add(cache: &AudioCache, key: &str, last_access: i64, pinned: bool) {
+        fs::create_dir_all(&cache.dir).unwrap();
+        fs::write(cache.audio_path(key), [0u8; 100]).unwrap();
+        lock(&cache.index).entries.insert(
+            key.to_string(),
+            AudioCacheEntry {
+                size: 100,
+                complete: true,
+                last_access,
+                pinned,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn keys(cache: &AudioCache) -> Vec<String> {
+        let mut keys: Vec<String> = lock(&cache.index).entries.keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn evicts_least_recently_used_over_limit() {
+        let cache = AudioCache::new(&cache_dir("evict"));
+        add(&cache, "a", 1, true);
+        add(&cache, "b", 2, false);
+        add(&cache, "c", 3, false);
+        add(&cache, "d", 4, false);
+        let _guard = cache.try_write("b").unwrap();
+
+        // 固定的 a 和正在写入的 b 不删除，先删除最久未使用的 c
+        cache.set_max_bytes(300);
+        assert_eq!(keys(&cache), ["a", "b", "d"]);
+        assert!(!cache.audio_path("c").exists());
+        assert!(cache.audio_path("d").exists());
+
+        // 其余歌曲都不能删除时允许超出容量
+        cache.set_max_bytes(100);
+        assert_eq!(keys(&cache), ["a", "b"]);
+        assert_eq!(cache.info().size_bytes, 200);
+    }
+
+    #[test]
+    fn set_pinned_replaces_pins_and_evicts() {
+        let cache = AudioCache::new(&cache_dir("pinned"));
+        cache.set_max_bytes(300);
+        add(&cache, "a", 1, true);
+        add(&cache, "b", 2, false);
+        add(&cache, "c", 3, false);
+        add(&cache, "d", 4, false);
+
+        // a 不再固定，成为最久未使用的可删除歌曲
+        cache.set_pinned(&HashSet::from(["b".to_string()]));
+        assert_eq!(keys(&cache), ["b", "c", "d"]);
+        assert!(cache.entry("b").unwrap().pinned);
+        assert!(!cache.entry("c").unwrap().pinned);
+        assert!(!cache.audio_path("a").exists());
+    }
+
+    #[test]
+    fn clear_keeps_pinned_and_writing_entries() {
+        let dir = cache_dir("clear");
+        let cache = AudioCache::new(&dir);
+        add(&cache, "a", 1, true);
+        add(&cache, "b", 2, false);
+        add(&cache, "c", 3, false);
+        let guard = cache.try_write("b").unwrap();
+        assert!(cache.try_write("b").is_none());
+
+        cache.clear();
+        assert_eq!(keys(&cache), ["a", "b"]);
+        assert!(!cache.audio_path("c").exists());
+        assert!(cache.audio_path("a").exists());
+
+        // 写入结束后可以清除，重新打开时以磁盘上的文件为准
+        drop(guard);
+        cache.clear();
+        let reopened = AudioCache::new(&dir);
+        assert_eq!(keys(&reopened), ["a"]);
+    }
+}
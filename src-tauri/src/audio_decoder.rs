@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+// 解码得到的音频流基本信息
+#[derive(Debug, Clone, Copy)]
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    pub channels: usize,
+    // 容器中记录的总帧数，部分格式可能没有
+    pub total_frames: Option<u64>,
+}
+
+// 探测文件格式，返回格式读取器和第一条可解码的音轨
+pub fn open_format(path: &Path) -> Result<(Box<dyn FormatReader>, Track), String> {
+    let file = File::open(path).map_err(|e| format!("打开文件失败: {}", e))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("无法识别音频格式: {}", e))?;
+    let format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .cloned()
+        .ok_or_else(|| "没有找到可解码的音轨".to_string())?;
+
+    Ok((format, track))
+}
+
+// 解码本地音频文件，将交错排列的 f32 采样分块交给回调处理，回调返回 Break 时提前结束
+// cancel 被置位时中止解码并返回错误
+pub fn decode_file<F>(
+    path: &Path,
+    cancel: Option<&AtomicBool>,
+    mut on_samples: F,
+) -> Result<StreamInfo, String>
+where
+    F: FnMut(&StreamInfo, &[f32]) -> ControlFlow<()>,
+{
+    let (mut format, track) = open_format(path)?;
+    let track_id = track.id;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("不支持的音频编码: {}", e))?;
+
+    let mut info = StreamInfo {
+        sample_rate: track.codec_params.sample_rate.unwrap_or(44100),
+        channels: track
+            .codec_params
+            .channels
+            .map(|c| c.count())
+            .unwrap_or(2),
+        total_frames: track.codec_params.n_frames,
+    };
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Err("已取消".to_string());
+        }
+
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // 读到文件末尾
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(format!("读取音频数据失败: {}", e)),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 跳过损坏的数据包
+            Err(SymphoniaError::DecodeError(e)) => {
+                eprintln!("跳过无法解码的数据包 {:?}: {}", path, e);
+                continue;
+            }
+            Err(e) => return Err(format!("解码失败: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        info.sample_rate = spec.rate;
+        info.channels = spec.channels.count();
+
+        let buf = match sample_buf.as_mut() {
+            Some(buf) if buf.capacity() >= decoded.capacity() * info.channels => buf,
+            _ => sample_buf.insert(SampleBuffer::<f32>::new(decoded.capacity() as u64, spec)),
+        };
+        buf.copy_interleaved_ref(decoded);
+        if on_samples(&info, buf.samples()).is_break() {
+            break;
+        }
+    }
+
+    Ok(info)
+}
@@ -1,7 +1,11 @@
 use lofty::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use tauri::command;
+use tauri::{command, AppHandle, Manager};
+
+use crate::file_hash::cached_track_id;
+use crate::library::LibraryState;
+use crate::replay_gain::{read_replay_gain, ReplayGain};
 // 添加 base64 引擎导入
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 
@@ -22,7 +26,7 @@ fn safe_extract_string<T: AsRef<str>>(option_str: Option<T>, default: &str) -> S
 }
 
 // 从文件名中提取歌曲名和歌手信息
-fn extract_title_and_artist_from_filename(file_name: &str) -> (String, String) {
+pub(crate) fn extract_title_and_artist_from_filename(file_name: &str) -> (String, String) {
     // 移除文件扩展名
     let name_without_ext = match file_name.rfind('.') {
         Some(dot_pos) => &file_name[..dot_pos],
@@ -56,6 +60,8 @@ fn extract_title_and_artist_from_filename(file_name: &str) -> (String, String) {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AudioMetadata {
+    // 与标签无关的稳定标识，重新编辑标签后保持不变
+    pub track_id: String,
     pub title: String,
     pub artist: String,
     pub album: String,
@@ -63,10 +69,23 @@ pub struct AudioMetadata {
     pub full_path: String,
     pub cover_data: Option<String>,
     pub cover_mime_type: Option<String>,
+    pub replay_gain: ReplayGain,
+}
+
+// 首次读取时需要解码整个文件计算稳定标识，在阻塞线程中执行，避免扫描目录时卡住界面
+#[command(async)]
+pub async fn get_audio_metadata(
+    app_handle: AppHandle,
+    full_path: String,
+) -> Result<AudioMetadata, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        read_audio_metadata(&app_handle.state::<LibraryState>(), full_path)
+    })
+    .await
+    .map_err(|e| format!("读取音频元数据失败: {}", e))?
 }
 
-#[command]
-pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
+fn read_audio_metadata(library: &LibraryState, full_path: String) -> Result<AudioMetadata, String> {
     // 添加安全检查，确保路径不为空
     if full_path.trim().is_empty() {
         return Err("文件路径不能为空".to_string());
@@ -79,6 +98,8 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
         return Err(format!("文件不存在: {}", full_path));
     }
 
+    let track_id = cached_track_id(library, path);
+
     // 读取音频文件，添加更详细的错误处理
     let tagged_file = match lofty::read_from_path(path) {
         Ok(file) => file,
@@ -101,6 +122,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
                 let (title, artist) = extract_title_and_artist_from_filename(file_name);
                 
                 return Ok(AudioMetadata {
+                    track_id,
                     title,
                     artist,
                     album: "未知专辑".to_string(),
@@ -108,6 +130,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
                     full_path,
                     cover_data: None,
                     cover_mime_type: None,
+                    replay_gain: ReplayGain::default(),
                 });
             }
             
@@ -118,6 +141,9 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
     // 获取音频属性
     let properties = tagged_file.properties();
 
+    // 读取音量均衡信息（ReplayGain / R128 / iTunNORM）
+    let replay_gain = read_replay_gain(&tagged_file);
+
     // 安全地获取标签，避免 panic
     let tag = match tagged_file.primary_tag() {
         Some(primary_tag) => primary_tag,
@@ -153,6 +179,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
                     };
 
                     return Ok(AudioMetadata {
+                        track_id,
                         title,
                         artist,
                         album: "未知专辑".to_string(),
@@ -160,6 +187,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
                         full_path,
                         cover_data,
                         cover_mime_type,
+                        replay_gain,
                     });
                 }
             }
@@ -191,6 +219,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
     };
 
     Ok(AudioMetadata {
+        track_id,
         title,
         artist,
         album,
@@ -198,6 +227,7 @@ pub fn get_audio_metadata(full_path: String) -> Result<AudioMetadata, String> {
         full_path,
         cover_data,
         cover_mime_type,
+        replay_gain,
     })
 }
 
@@ -0,0 +1,239 @@
+use encoding_rs::{Encoding, BIG5, GB18030, UTF_8};
+
+// 只在响应开头查找 HTML meta / XML 声明中的编码
+const SNIFF_LEN: usize = 2048;
+
+// 常用简体字和繁体字，用于区分 GBK 与 Big5 的解码结果
+const COMMON_SIMPLIFIED: &str = "的一是不了在人有我他这个们中来上大为和国地到以说时要就出会可也你对生能而子那得于着下自之年过发后作里用道行所然家种事成方多经么去法学如都同现当没动面起看定天分还进好小部其些主样理心她本前开但因只从想实歌曲专辑热门排行榜单";
+const COMMON_TRADITIONAL: &str = "的一是不了在人有我他這個們中來上大為和國地到以說時要就出會可也你對生能而子那得於著下自之年過發後作裡用道行所然家種事成方多經麼去法學如都同現當沒動面起看定天分還進好小部其些主樣理心她本前開但因只從想實歌曲專輯熱門排行榜單";
+
+// 解码响应文本，编码的判断顺序：
+// 调用方指定 > BOM > Content-Type 中的 charset > HTML/XML 中声明的编码 > 按字节特征猜测
+pub fn decode_text(
+    bytes: &[u8],
+    header_charset: Option<&str>,
+    override_charset: Option<&str>,
+) -> String {
+    let (text, encoding, had_errors) = match override_charset.and_then(encoding_for_label) {
+        // 调用方指定的编码优先于 BOM，encoding_rs 的 decode 会按 BOM 改用其他编码
+        Some(encoding) => {
+            let (text, had_errors) = encoding.decode_without_bom_handling(bytes);
+            (text, encoding, had_errors)
+        }
+        None => Encoding::for_bom(bytes)
+            .map(|(encoding, _)| encoding)
+            .or_else(|| header_charset.and_then(encoding_for_label))
+            .or_else(|| sniff_declared_charset(bytes))
+            .unwrap_or_else(|| detect_encoding(bytes))
+            .decode(bytes),
+    };
+    if had_errors {
+        eprintln!("按 {} 解码响应时存在无效字节", encoding.name());
+    }
+    text.into_owned()
+}
+
+fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    let encoding = Encoding::for_label(label.trim().as_bytes())?;
+    // GB2312/GBK 按 GB18030 解码，兼容超出 GBK 范围的字符
+    if encoding == encoding_rs::GBK {
+        Some(GB18030)
+    } else {
+        Some(encoding)
+    }
+}
+
+// 查找 <meta charset="..."> 、<meta content="...; charset=..."> 或 <?xml encoding="..."?>
+fn sniff_declared_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+
+    let label_after = |pos: usize| -> Option<&'static Encoding> {
+        let rest = head[pos..]
+            .trim_start_matches(|c: char| c == '=' || c == '"' || c == '\'' || c.is_whitespace());
+        let label: String = rest
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        if label.is_empty() {
+            None
+        } else {
+            encoding_for_label(&label)
+        }
+    };
+
+    if head.trim_start().starts_with("<?xml") {
+        if let Some(pos) = head.find("encoding") {
+            if let Some(encoding) = label_after(pos + "encoding".len()) {
+                return Some(encoding);
+            }
+        }
+    }
+
+    let mut search_from = 0;
+    while let Some(meta) = head[search_from..].find("<meta") {
+        let start = search_from + meta;
+        let end = head[start..]
+            .find('>')
+            .map_or(head.len(), |end| start + end);
+        if let Some(pos) = head[start..end].find("charset") {
+            if let Some(encoding) = label_after(start + pos + "charset".len()) {
+                return Some(encoding);
+            }
+        }
+        search_from = end;
+    }
+    None
+}
+
+// 没有任何编码声明时按字节特征猜测：合法 UTF-8 优先，其次比较 GB18030 与 Big5
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+
+    let gb = GB18030.decode_without_bom_handling_and_without_replacement(bytes);
+    let big5 = BIG5.decode_without_bom_handling_and_without_replacement(bytes);
+    match (gb, big5) {
+        (Some(_), None) => GB18030,
+        (None, Some(_)) => BIG5,
+        (Some(gb), Some(big5)) => {
+            // 两者都能无错解码时，常用字更多的一方更可能正确
+            if common_char_score(&big5, COMMON_TRADITIONAL)
+                > common_char_score(&gb, COMMON_SIMPLIFIED)
+            {
+                BIG5
+            } else {
+                GB18030
+            }
+        }
+        // 都无法完整解码时，国内平台以 GBK 居多
+        (None, None) => GB18030,
+    }
+}
+
+fn common_char_score(text: &str, common: &str) -> usize {
+    text.chars().filter(|c| common.contains(*c)).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, UTF_16LE};
+
+    const SIMPLIFIED: &str = "热门歌曲排行榜，我们的歌";
+    const TRADITIONAL: &str = "熱門歌曲排行榜，我們的歌";
+
+    fn encode(encoding: &'static Encoding, text: &str) -> Vec<u8> {
+        encoding.encode(text).0.into_owned()
+    }
+
+    fn html(meta: &str, encoding: &'static Encoding, text: &str) -> Vec<u8> {
+        let page = format!("<html><head>{}</head><body>{}</body></html>", meta, text);
+        encode(encoding, &page)
+    }
+
+    #[test]
+    fn maps_gbk_labels_to_gb18030() {
+        for label in ["gbk", "GB2312", " gb_2312-80 ", "gb18030", "x-gbk"] {
+            assert_eq!(encoding_for_label(label), Some(GB18030), "{}", label);
+        }
+        assert_eq!(encoding_for_label("big5"), Some(BIG5));
+        assert_eq!(encoding_for_label("unknown"), None);
+    }
+
+    #[test]
+    fn decodes_with_bom() {
+        let mut utf8 = b"\xef\xbb\xbf".to_vec();
+        utf8.extend_from_slice(SIMPLIFIED.as_bytes());
+        // BOM 优先于响应头
+        assert_eq!(decode_text(&utf8, Some("gbk"), None), SIMPLIFIED);
+
+        let mut utf16 = b"\xff\xfe".to_vec();
+        utf16.extend(SIMPLIFIED.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_text(&utf16, None, None), SIMPLIFIED);
+        assert_eq!(Encoding::for_bom(&utf16).map(|(e, _)| e), Some(UTF_16LE));
+    }
+
+    #[test]
+    fn decodes_with_header_charset() {
+        let gbk = encode(GBK, SIMPLIFIED);
+        assert_eq!(decode_text(&gbk, Some("GBK"), None), SIMPLIFIED);
+        assert_eq!(decode_text(&gbk, Some("gb2312"), None), SIMPLIFIED);
+        let big5 = encode(BIG5, TRADITIONAL);
+        assert_eq!(decode_text(&big5, Some("big5"), None), TRADITIONAL);
+        // 响应头优先于页面中声明的编码
+        let page = html("<meta charset=\"big5\">", GBK, SIMPLIFIED);
+        assert!(decode_text(&page, Some("gbk"), None).contains(SIMPLIFIED));
+        // 无法识别的 charset 被忽略
+        assert_eq!(decode_text(&gbk, Some("bogus"), None), SIMPLIFIED);
+    }
+
+    #[test]
+    fn decodes_with_declared_charset() {
+        let cases = [
+            ("<meta charset=\"gbk\">", GBK, SIMPLIFIED),
+            ("<META CHARSET=GB2312>", GBK, SIMPLIFIED),
+            (
+                "<meta http-equiv=\"Content-Type\" content=\"text/html; charset=big5\">",
+                BIG5,
+                TRADITIONAL,
+            ),
+            // 没有 charset 的 meta 被跳过
+            (
+                "<meta name=\"keywords\" content=\"x\"><meta charset='big5'>",
+                BIG5,
+                TRADITIONAL,
+            ),
+        ];
+        for (meta, encoding, text) in cases {
+            let page = html(meta, encoding, text);
+            assert!(decode_text(&page, None, None).contains(text), "{}", meta);
+        }
+
+        let xml = encode(
+            GBK,
+            &format!(
+                "<?xml version=\"1.0\" encoding=\"GB2312\"?><song>{}</song>",
+                SIMPLIFIED
+            ),
+        );
+        assert!(decode_text(&xml, None, None).contains(SIMPLIFIED));
+        assert_eq!(
+            sniff_declared_charset(b"<html><body>no meta</body></html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_undeclared_encoding() {
+        assert_eq!(detect_encoding(SIMPLIFIED.as_bytes()), UTF_8);
+        assert_eq!(detect_encoding(&encode(GBK, SIMPLIFIED)), GB18030);
+        assert_eq!(detect_encoding(&encode(BIG5, TRADITIONAL)), BIG5);
+        assert_eq!(
+            decode_text(&encode(GBK, SIMPLIFIED), None, None),
+            SIMPLIFIED
+        );
+        assert_eq!(
+            decode_text(&encode(BIG5, TRADITIONAL), None, None),
+            TRADITIONAL
+        );
+        // 都无法完整解码时按 GB18030
+        assert_eq!(detect_encoding(b"\xff\xff\xff"), GB18030);
+    }
+
+    #[test]
+    fn override_takes_precedence() {
+        let big5 = encode(BIG5, TRADITIONAL);
+        assert_eq!(decode_text(&big5, Some("gbk"), Some("big5")), TRADITIONAL);
+        let page = html("<meta charset=\"gbk\">", BIG5, TRADITIONAL);
+        assert!(decode_text(&page, None, Some("big5")).contains(TRADITIONAL));
+        // 指定编码时 BOM 按普通字节解码
+        assert_eq!(
+            decode_text(b"\xef\xbb\xbfabc", None, Some("windows-1252")),
+            "\u{ef}\u{bb}\u{bf}abc"
+        );
+        // 无法识别的指定编码被忽略
+        assert_eq!(decode_text(&big5, Some("big5"), Some("bogus")), TRADITIONAL);
+    }
+}
@@ -0,0 +1,799 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::download_tagger::{tag_downloaded, DEFAULT_FILENAME_TEMPLATE};
+use crate::http_client::HttpClientState;
+use crate::http_download::{
+    download_root, download_to, remove_partial, resolve_dest, DownloadResult,
+};
+
+const DOWNLOADS_FILE_NAME: &str = "downloads.json";
+const DEFAULT_MAX_CONCURRENT: usize = 3;
+// 下载任务在取消登记表中的 id 前缀，避免与前端的请求 id 冲突
+const CANCEL_ID_PREFIX: &str = "download:";
+// 带登录凭据的请求头只保存在内存中，不写入 downloads.json，也不发送给前端；
+// 重启后继续下载时由 Cookie 存储提供 Cookie
+const CREDENTIAL_HEADERS: &[&str] = &["cookie", "authorization", "proxy-authorization"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamedItem {
+    pub name: String,
+}
+
+// 下载的曲目信息，字段与前端的 Track 一致
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadTrack {
+    pub id: String,
+    pub platform: String,
+    pub title: String,
+    pub artist: Vec<NamedItem>,
+    pub album: NamedItem,
+    pub cover: Option<String>,
+    // LRC 格式的歌词
+    pub lyric: Option<String>,
+    pub track_no: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Paused,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadTask {
+    pub id: String,
+    pub url: String,
+    pub dest: String,
+    #[serde(default, serialize_with = "serialize_headers")]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub track: Option<DownloadTrack>,
+    pub status: DownloadStatus,
+    #[serde(default)]
+    pub downloaded: u64,
+    #[serde(default)]
+    pub total: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub created_at: i64,
+    #[serde(default)]
+    pub finished_at: Option<i64>,
+    // 每次开始下载时递增，用于忽略已被暂停的旧下载的结果
+    #[serde(skip)]
+    run: u64,
+}
+
+fn serialize_headers<S: serde::Serializer>(
+    headers: &HashMap<String, String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(headers.iter().filter(|(name, _)| {
+        !CREDENTIAL_HEADERS
+            .iter()
+            .any(|credential| name.eq_ignore_ascii_case(credential))
+    }))
+}
+
+// 下载队列，已完成和失败的任务作为历史记录保留
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadQueue {
+    // 同时下载的任务数
+    pub max_concurrent: usize,
+    // 下载完成后写入曲目标签并按模板重命名
+    pub auto_tag: bool,
+    pub filename_template: String,
+    pub tasks: Vec<DownloadTask>,
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        DownloadQueue {
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            auto_tag: true,
+            filename_template: DEFAULT_FILENAME_TEMPLATE.to_string(),
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl DownloadQueue {
+    fn task_mut(&mut self, id: &str) -> Result<&mut DownloadTask, String> {
+        self.tasks
+            .iter_mut()
+            .find(|task| task.id == id)
+            .ok_or_else(|| format!("下载任务不存在: {}", id))
+    }
+}
+
+// 正在写入文件的任务的凭据，释放后才能重新开始下载或删除未完成的文件
+struct WriteGuard {
+    id: String,
+    writing: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        lock(&self.writing).remove(&self.id);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// 下载队列状态，持久化到应用数据目录
+pub struct DownloadManager {
+    file: PathBuf,
+    queue: Mutex<DownloadQueue>,
+    // 正在写入文件的任务，暂停或删除后旧的下载停止前仍在其中
+    writing: Arc<Mutex<HashSet<String>>>,
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn new_task_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("{:x}{:04x}", millis, rand::thread_rng().gen::<u16>())
+}
+
+fn cancel_id(task_id: &str) -> String {
+    format!("{}{}", CANCEL_ID_PREFIX, task_id)
+}
+
+impl DownloadManager {
+    // 加载下载队列，上次退出时正在下载的任务重新排队
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(DOWNLOADS_FILE_NAME);
+        let mut queue = match fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str::<DownloadQueue>(&content).unwrap_or_else(|e| {
+                eprintln!("解析下载队列失败 {:?}: {}", file, e);
+                DownloadQueue::default()
+            }),
+            Err(_) => DownloadQueue::default(),
+        };
+        for task in queue.tasks.iter_mut() {
+            if task.status == DownloadStatus::Downloading {
+                task.status = DownloadStatus::Queued;
+            }
+        }
+        DownloadManager {
+            file,
+            queue: Mutex::new(queue),
+            writing: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    // 旧的下载还没有停止时返回空
+    fn try_write(&self, id: &str) -> Option<WriteGuard> {
+        if !lock(&self.writing).insert(id.to_string()) {
+            return None;
+        }
+        Some(WriteGuard {
+            id: id.to_string(),
+            writing: self.writing.clone(),
+        })
+    }
+
+    fn is_writing(&self, id: &str) -> bool {
+        lock(&self.writing).contains(id)
+    }
+
+    fn save(&self, queue: &DownloadQueue) -> Result<(), String> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        }
+        let content =
+            serde_json::to_string(queue).map_err(|e| format!("序列化下载队列失败: {}", e))?;
+        let tmp_file = self.file.with_extension("json.tmp");
+        fs::write(&tmp_file, content).map_err(|e| format!("保存下载队列失败: {}", e))?;
+        fs::rename(&tmp_file, &self.file).map_err(|e| format!("保存下载队列失败: {}", e))
+    }
+
+    // 修改队列并保存
+    fn update<R>(
+        &self,
+        f: impl FnOnce(&mut DownloadQueue) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut queue = self.queue.lock().map_err(|e| e.to_string())?;
+        let result = f(&mut queue)?;
+        self.save(&queue)?;
+        Ok(result)
+    }
+
+    pub fn snapshot(&self) -> DownloadQueue {
+        match self.queue.lock() {
+            Ok(queue) => queue.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    // 是否自动写入标签，以及文件名模板
+    fn tagging(&self) -> (bool, String) {
+        match self.queue.lock() {
+            Ok(queue) => (queue.auto_tag, queue.filename_template.clone()),
+            Err(_) => (false, String::new()),
+        }
+    }
+
+    // 更新下载进度，只保存在内存中
+    fn set_progress(&self, id: &str, downloaded: u64, total: Option<u64>) {
+        if let Ok(mut queue) = self.queue.lock() {
+            if let Ok(task) = queue.task_mut(id) {
+                task.downloaded = downloaded;
+                task.total = total;
+            }
+        }
+    }
+
+    // 按并发数把排队中的任务标记为下载中，暂停后旧的下载还没有停止的任务等它结束后再启动
+    fn start_queued(&self) -> Result<Vec<(DownloadTask, WriteGuard)>, String> {
+        self.update(|queue| {
+            let running = queue
+                .tasks
+                .iter()
+                .filter(|task| task.status == DownloadStatus::Downloading)
+                .count();
+            let slots = queue.max_concurrent.max(1).saturating_sub(running);
+            Ok(queue
+                .tasks
+                .iter_mut()
+                .filter(|task| task.status == DownloadStatus::Queued)
+                .filter(|task| !self.is_writing(&task.id))
+                .take(slots)
+                .filter_map(|task| {
+                    let guard = self.try_write(&task.id)?;
+                    task.status = DownloadStatus::Downloading;
+                    task.error = None;
+                    task.run += 1;
+                    Some((task.clone(), guard))
+                })
+                .collect())
+        })
+    }
+
+    // 记录下载结果，tagged 为写入标签后的文件路径
+    fn finish(
+        &self,
+        task: &DownloadTask,
+        guard: WriteGuard,
+        result: Result<DownloadResult, String>,
+        tagged: Option<Result<PathBuf, String>>,
+    ) -> Result<(), String> {
+        self.update(|queue| {
+            // 在队列锁内释放写入凭据，删除任务时据此判断由谁删除未完成的文件
+            let _guard = guard;
+            // 任务在下载过程中被删除，未完成的文件由这里删除
+            let current = match queue.task_mut(&task.id) {
+                Ok(current) => current,
+                Err(_) => {
+                    if result.is_err() {
+                        remove_partial(Path::new(&task.dest));
+                    }
+                    return Ok(());
+                }
+            };
+            // 暂停后已经重新开始
+            if current.run != task.run {
+                return Ok(());
+            }
+            match result {
+                Ok(result) => {
+                    current.status = DownloadStatus::Completed;
+                    current.downloaded = result.size;
+                    current.total = Some(result.size);
+                    current.finished_at = Some(now_secs());
+                    match tagged {
+                        Some(Ok(path)) => current.dest = path.to_string_lossy().into_owned(),
+                        Some(Err(e)) => {
+                            eprintln!("写入标签失败 {}: {}", current.dest, e);
+                            current.error = Some(e);
+                        }
+                        None => {}
+                    }
+                }
+                // 暂停时任务状态已经改变，取消导致的错误不记录
+                Err(e) if current.status == DownloadStatus::Downloading => {
+                    eprintln!("下载失败 {}: {}", task.url, e);
+                    current.status = DownloadStatus::Failed;
+                    current.error = Some(e);
+                    current.finished_at = Some(now_secs());
+                }
+                Err(_) => {}
+            }
+            Ok(())
+        })
+    }
+
+    // 暂停任务，返回任务是否正在下载
+    fn pause(&self, id: &str) -> Result<bool, String> {
+        self.update(|queue| {
+            let task = queue.task_mut(id)?;
+            let was_running = task.status == DownloadStatus::Downloading;
+            if matches!(
+                task.status,
+                DownloadStatus::Queued | DownloadStatus::Downloading
+            ) {
+                task.status = DownloadStatus::Paused;
+            }
+            Ok(was_running)
+        })
+    }
+
+    // 状态为 from 的任务重新排队
+    fn requeue(&self, id: &str, from: DownloadStatus) -> Result<(), String> {
+        self.update(|queue| {
+            let task = queue.task_mut(id)?;
+            if task.status == from {
+                task.status = DownloadStatus::Queued;
+                task.error = None;
+                task.finished_at = None;
+            }
+            Ok(())
+        })
+    }
+
+    // 从队列中删除任务
+    fn remove(&self, id: &str) -> Result<DownloadTask, String> {
+        self.update(|queue| {
+            let index = queue
+                .tasks
+                .iter()
+                .position(|task| task.id == id)
+                .ok_or_else(|| format!("下载任务不存在: {}", id))?;
+            let task = queue.tasks.remove(index);
+            // 下载还在写入时由它停止后删除未完成的文件，避免删除后又被写入
+            if task.status != DownloadStatus::Completed && !self.is_writing(id) {
+                remove_partial(Path::new(&task.dest));
+            }
+            Ok(task)
+        })
+    }
+}
+
+// 下载进度事件
+#[derive(Clone, Serialize)]
+struct DownloadQueueProgress {
+    id: String,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+// 队列发生变化时发送完整的队列
+fn emit_queue(app_handle: &AppHandle) {
+    let queue = app_handle.state::<DownloadManager>().snapshot();
+    let _ = app_handle.emit("download-queue-changed", queue);
+}
+
+// 应用启动时继续未完成的下载
+pub fn start(app_handle: &AppHandle) {
+    schedule(app_handle);
+}
+
+// 按并发数启动排队中的任务，暂停后旧的下载还没有停止的任务等它结束后再启动
+fn schedule(app_handle: &AppHandle) {
+    let started = app_handle.state::<DownloadManager>().start_queued();
+    let started = match started {
+        Ok(started) => started,
+        Err(e) => {
+            eprintln!("启动下载任务失败: {}", e);
+            return;
+        }
+    };
+    if started.is_empty() {
+        return;
+    }
+    emit_queue(app_handle);
+    for (task, guard) in started {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move { run_task(app_handle, task, guard).await });
+    }
+}
+
+async fn run_task(app_handle: AppHandle, task: DownloadTask, guard: WriteGuard) {
+    let http = app_handle.state::<HttpClientState>();
+    let manager = app_handle.state::<DownloadManager>();
+
+    let on_progress = |downloaded, total| {
+        manager.set_progress(&task.id, downloaded, total);
+        let _ = app_handle.emit(
+            "download-queue-progress",
+            DownloadQueueProgress {
+                id: task.id.clone(),
+                downloaded,
+                total,
+            },
+        );
+    };
+    let cancel = http.cancels().register(cancel_id(&task.id));
+    let result = cancel
+        .run(download_to(
+            &http,
+            &task.url,
+            Path::new(&task.dest),
+            &task.headers,
+            on_progress,
+        ))
+        .await;
+    drop(cancel);
+
+    // 写入标签失败不影响下载结果，错误记录在任务中
+    let (auto_tag, filename_template) = manager.tagging();
+    let tagged = match (&result, &task.track) {
+        (Ok(result), Some(track)) if auto_tag => {
+            Some(tag_downloaded(&http, Path::new(&result.dest), track, &filename_template).await)
+        }
+        _ => None,
+    };
+
+    if let Err(e) = manager.finish(&task, guard, result, tagged) {
+        eprintln!("{}", e);
+    }
+    emit_queue(&app_handle);
+    schedule(&app_handle);
+}
+
+// 添加下载任务，dest 必须在下载目录内
+#[tauri::command]
+pub fn enqueue_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    http: State<'_, HttpClientState>,
+    url: String,
+    dest: String,
+    headers: Option<HashMap<String, String>>,
+    track: Option<DownloadTrack>,
+) -> Result<DownloadTask, String> {
+    let dest = resolve_dest(&download_root(&app_handle, &http)?, &dest)?;
+    let task = DownloadTask {
+        id: new_task_id(),
+        url,
+        dest: dest.to_string_lossy().into_owned(),
+        headers: headers.unwrap_or_default(),
+        track,
+        status: DownloadStatus::Queued,
+        downloaded: 0,
+        total: None,
+        error: None,
+        created_at: now_secs(),
+        finished_at: None,
+        run: 0,
+    };
+    manager.update(|queue| {
+        let duplicated = queue.tasks.iter().any(|existing| {
+            existing.dest == task.dest
+                && matches!(
+                    existing.status,
+                    DownloadStatus::Queued | DownloadStatus::Downloading | DownloadStatus::Paused
+                )
+        });
+        if duplicated {
+            return Err(format!("已有保存到 {} 的下载任务", task.dest));
+        }
+        queue.tasks.push(task.clone());
+        Ok(())
+    })?;
+    emit_queue(&app_handle);
+    schedule(&app_handle);
+    Ok(task)
+}
+
+#[tauri::command]
+pub fn list_downloads(manager: State<'_, DownloadManager>) -> DownloadQueue {
+    manager.snapshot()
+}
+
+// 暂停任务，已下载的部分保留，继续时从中断的位置下载
+#[tauri::command]
+pub fn pause_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    http: State<'_, HttpClientState>,
+    id: String,
+) -> Result<(), String> {
+    let was_running = manager.pause(&id)?;
+    if was_running {
+        http.cancels().cancel(&cancel_id(&id));
+    }
+    emit_queue(&app_handle);
+    schedule(&app_handle);
+    Ok(())
+}
+
+// 重新排队：继续已暂停的任务，或重试失败的任务
+fn requeue(
+    app_handle: &AppHandle,
+    manager: &DownloadManager,
+    id: &str,
+    from: DownloadStatus,
+) -> Result<(), String> {
+    manager.requeue(id, from)?;
+    emit_queue(app_handle);
+    schedule(app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn resume_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    requeue(&app_handle, &manager, &id, DownloadStatus::Paused)
+}
+
+#[tauri::command]
+pub fn retry_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    requeue(&app_handle, &manager, &id, DownloadStatus::Failed)
+}
+
+// 删除任务和未完成的文件，delete_file 为 true 时同时删除已下载完成的文件
+#[tauri::command]
+pub fn remove_download(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    http: State<'_, HttpClientState>,
+    id: String,
+    delete_file: bool,
+) -> Result<(), String> {
+    let task = manager.remove(&id)?;
+    if manager.is_writing(&id) {
+        http.cancels().cancel(&cancel_id(&id));
+    }
+    if task.status == DownloadStatus::Completed && delete_file {
+        fs::remove_file(&task.dest).map_err(|e| format!("删除文件失败: {}", e))?;
+    }
+    emit_queue(&app_handle);
+    schedule(&app_handle);
+    Ok(())
+}
+
+// 清除已完成任务的历史记录，不删除文件
+#[tauri::command]
+pub fn clear_download_history(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+) -> Result<(), String> {
+    manager.update(|queue| {
+        queue
+            .tasks
+            .retain(|task| task.status != DownloadStatus::Completed);
+        Ok(())
+    })?;
+    emit_queue(&app_handle);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_max_concurrent_downloads(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    max_concurrent: usize,
+) -> Result<(), String> {
+    if max_concurrent == 0 {
+        return Err("同时下载数至少为 1".to_string());
+    }
+    manager.update(|queue| {
+        queue.max_concurrent = max_concurrent;
+        Ok(())
+    })?;
+    emit_queue(&app_handle);
+    schedule(&app_handle);
+    Ok(())
+}
+
+// 设置下载完成后是否写入标签，以及重命名使用的文件名模板
+#[tauri::command]
+pub fn set_download_tagging(
+    app_handle: AppHandle,
+    manager: State<'_, DownloadManager>,
+    auto_tag: bool,
+    filename_template: String,
+) -> Result<(), String> {
+    if filename_template.trim().is_empty() {
+        return Err("文件名模板不能为空".to_string());
+    }
+    manager.update(|queue| {
+        queue.auto_tag = auto_tag;
+        queue.filename_template = filename_template;
+        Ok(())
+    })?;
+    emit_queue(&app_handle);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_download::part_path;
+
+    fn manager(name: &str) -> (DownloadManager, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("download_queue_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        (DownloadManager::load(&dir), dir)
+    }
+
+    fn task(dir: &Path, id: &str) -> DownloadTask {
+        DownloadTask {
+            id: id.to_string(),
+            url: format!("https://m701.music.126.net/{}.mp3", id),
+            dest: dir
+                .join(format!("{}.mp3", id))
+                .to_string_lossy()
+                .into_owned(),
+            headers: HashMap::new(),
+            track: None,
+            status: DownloadStatus::Queued,
+            downloaded: 0,
+            total: None,
+            error: None,
+            created_at: 0,
+            finished_at: None,
+            run: 0,
+        }
+    }
+
+    fn add(manager: &DownloadManager, task: DownloadTask) {
+        lock(&manager.queue).tasks.push(task);
+    }
+
+    fn status(manager: &DownloadManager, id: &str) -> DownloadStatus {
+        lock(&manager.queue).task_mut(id).unwrap().status
+    }
+
+    fn downloaded(task: &DownloadTask) -> Result<DownloadResult, String> {
+        Ok(DownloadResult {
+            dest: task.dest.clone(),
+            size: 100,
+            resumed: false,
+        })
+    }
+
+    fn started_ids(started: &[(DownloadTask, WriteGuard)]) -> Vec<&str> {
+        started.iter().map(|(task, _)| task.id.as_str()).collect()
+    }
+
+    #[test]
+    fn schedules_up_to_max_concurrent() {
+        let (manager, dir) = manager("schedule");
+        lock(&manager.queue).max_concurrent = 2;
+        for id in ["a", "b", "c", "d"] {
+            add(&manager, task(&dir, id));
+        }
+
+        let mut started = manager.start_queued().unwrap();
+        assert_eq!(started_ids(&started), ["a", "b"]);
+        assert_eq!(status(&manager, "b"), DownloadStatus::Downloading);
+        assert_eq!(status(&manager, "c"), DownloadStatus::Queued);
+        assert!(manager.start_queued().unwrap().is_empty());
+
+        // 一个任务完成后空出一个位置
+        let (task_a, guard) = started.remove(0);
+        let result = downloaded(&task_a);
+        manager.finish(&task_a, guard, result, None).unwrap();
+        assert_eq!(status(&manager, "a"), DownloadStatus::Completed);
+        assert_eq!(started_ids(&manager.start_queued().unwrap()), ["c"]);
+    }
+
+    #[test]
+    fn resume_waits_for_paused_download_to_stop() {
+        let (manager, dir) = manager("pause");
+        add(&manager, task(&dir, "a"));
+        let (first, guard) = manager.start_queued().unwrap().remove(0);
+
+        assert!(manager.pause("a").unwrap());
+        assert_eq!(status(&manager, "a"), DownloadStatus::Paused);
+        manager.requeue("a", DownloadStatus::Paused).unwrap();
+        assert_eq!(status(&manager, "a"), DownloadStatus::Queued);
+        // 旧的下载还在写入
+        assert!(manager.start_queued().unwrap().is_empty());
+
+        // 旧的下载因取消而结束，不记录为失败
+        manager
+            .finish(&first, guard, Err("请求已取消".to_string()), None)
+            .unwrap();
+        assert_eq!(status(&manager, "a"), DownloadStatus::Queued);
+        let (second, _guard) = manager.start_queued().unwrap().remove(0);
+        assert_eq!(second.run, first.run + 1);
+    }
+
+    #[test]
+    fn ignores_results_of_stale_runs() {
+        let (manager, dir) = manager("stale");
+        add(&manager, task(&dir, "a"));
+        let (current, _guard) = manager.start_queued().unwrap().remove(0);
+        let stale = DownloadTask {
+            run: current.run - 1,
+            ..current.clone()
+        };
+        let stale_guard = WriteGuard {
+            id: "a".to_string(),
+            writing: Arc::new(Mutex::new(HashSet::new())),
+        };
+        manager
+            .finish(&stale, stale_guard, Err("连接中断".to_string()), None)
+            .unwrap();
+        let task = lock(&manager.queue).task_mut("a").unwrap().clone();
+        assert_eq!(task.status, DownloadStatus::Downloading);
+        assert!(task.error.is_none());
+    }
+
+    #[test]
+    fn removing_writing_task_leaves_partial_to_the_download() {
+        let (manager, dir) = manager("remove");
+        add(&manager, task(&dir, "a"));
+        add(&manager, task(&dir, "b"));
+        lock(&manager.queue).max_concurrent = 1;
+        let (task_a, guard) = manager.start_queued().unwrap().remove(0);
+        let part_a = part_path(Path::new(&task_a.dest));
+        let part_b = part_path(Path::new(&task(&dir, "b").dest));
+        fs::write(&part_a, b"partial").unwrap();
+        fs::write(&part_b, b"partial").unwrap();
+
+        // 没有在写入的任务直接删除未完成的文件
+        manager.remove("b").unwrap();
+        assert!(!part_b.exists());
+
+        // 正在写入的任务由下载结束时删除
+        manager.remove("a").unwrap();
+        assert!(part_a.exists());
+        manager
+            .finish(&task_a, guard, Err("请求已取消".to_string()), None)
+            .unwrap();
+        assert!(!part_a.exists());
+        assert!(lock(&manager.queue).tasks.is_empty());
+    }
+
+    #[test]
+    fn load_requeues_downloading_tasks_without_saving_credentials() {
+        let (manager, dir) = manager("load");
+        let mut downloading = task(&dir, "a");
+        downloading.status = DownloadStatus::Downloading;
+        downloading.headers = HashMap::from([
+            ("Cookie".to_string(), "MUSIC_U=secret".to_string()),
+            ("Referer".to_string(), "https://music.163.com/".to_string()),
+        ]);
+        let mut paused = task(&dir, "b");
+        paused.status = DownloadStatus::Paused;
+        add(&manager, downloading);
+        add(&manager, paused);
+        manager.update(|_| Ok(())).unwrap();
+
+        let saved = fs::read_to_string(dir.join(DOWNLOADS_FILE_NAME)).unwrap();
+        assert!(!saved.contains("secret"));
+        let reloaded = DownloadManager::load(&dir);
+        assert_eq!(status(&reloaded, "a"), DownloadStatus::Queued);
+        assert_eq!(status(&reloaded, "b"), DownloadStatus::Paused);
+        let headers = lock(&reloaded.queue).task_mut("a").unwrap().headers.clone();
+        assert_eq!(
+            headers,
+            HashMap::from([("Referer".to_string(), "https://music.163.com/".to_string())])
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use lofty::config::WriteOptions;
+use lofty::picture::{Picture, PictureType};
+use lofty::prelude::*;
+use lofty::tag::{ItemKey, Tag};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use crate::download_queue::DownloadTrack;
+use crate::http_client::{HttpClientState, HttpRequest, ResponseType};
+
+// 默认的文件名模板
+pub const DEFAULT_FILENAME_TEMPLATE: &str = "{artist} - {title}";
+// 多位歌手之间的分隔符
+const ARTIST_SEPARATOR: &str = ", ";
+// Windows 保留的设备名，带扩展名时同样不能作为文件名
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+impl DownloadTrack {
+    fn artist_names(&self) -> String {
+        self.artist
+            .iter()
+            .map(|artist| artist.name.trim())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>()
+            .join(ARTIST_SEPARATOR)
+    }
+}
+
+// 替换文件名中不允许出现的字符
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '\\' | '/' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows 不允许文件名以点或空格结尾
+    let name = name
+        .trim_start()
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        return format!("{}_{}", stem, &name[stem.len()..]);
+    }
+    name.to_string()
+}
+
+// 按模板生成相对路径（不含扩展名），支持 {title}、{artist}、{album}、{track}、{platform}，
+// 模板中的 / 表示子目录
+pub fn render_file_name(template: &str, track: &DownloadTrack) -> Option<PathBuf> {
+    let track_no = track
+        .track_no
+        .map(|no| format!("{:02}", no))
+        .unwrap_or_default();
+    let mut path = PathBuf::new();
+    for segment in template.split(['/', '\\']) {
+        let rendered = segment
+            .replace("{title}", &track.title)
+            .replace("{artist}", &track.artist_names())
+            .replace("{album}", &track.album.name)
+            .replace("{track}", &track_no)
+            .replace("{platform}", &track.platform);
+        // 去掉结尾的点之后 . 和 .. 都为空，不会跳出下载目录
+        let rendered = sanitize_file_name(&rendered);
+        if !rendered.is_empty() {
+            path.push(rendered);
+        }
+    }
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+// 目标文件已存在时在文件名后加序号
+fn available_path(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|index| path.with_file_name(format!("{} ({}){}", stem, index, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(path)
+}
+
+// 将曲目信息写入音频文件的标签
+fn write_track_tags(
+    path: &Path,
+    track: &DownloadTrack,
+    cover: Option<&[u8]>,
+) -> Result<(), String> {
+    let mut tagged_file =
+        lofty::read_from_path(path).map_err(|e| format!("无法读取音频文件: {}", e))?;
+
+    // 没有主标签时按文件格式创建一个
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| "无法创建标签".to_string())?;
+
+    if !track.title.trim().is_empty() {
+        tag.set_title(track.title.trim().to_string());
+    }
+    let artists = track.artist_names();
+    if !artists.is_empty() {
+        tag.set_artist(artists.clone());
+        // 在线平台没有单独的专辑艺人，使用第一位歌手
+        if let Some(first) = track.artist.first() {
+            tag.insert_text(ItemKey::AlbumArtist, first.name.trim().to_string());
+        }
+    }
+    if !track.album.name.trim().is_empty() {
+        tag.set_album(track.album.name.trim().to_string());
+    }
+    if let Some(track_no) = track.track_no {
+        tag.set_track(track_no);
+    }
+    if let Some(lyric) = track
+        .lyric
+        .as_deref()
+        .filter(|lyric| !lyric.trim().is_empty())
+    {
+        tag.insert_text(ItemKey::Lyrics, lyric.to_string());
+    }
+    if let Some(cover) = cover {
+        match Picture::from_reader(&mut Cursor::new(cover)) {
+            Ok(mut picture) => {
+                picture.set_pic_type(PictureType::CoverFront);
+                tag.remove_picture_type(PictureType::CoverFront);
+                tag.push_picture(picture);
+            }
+            Err(e) => eprintln!("无法识别封面图片: {}", e),
+        }
+    }
+
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| format!("保存标签失败: {}", e))
+}
+
+// 通过共享的 HTTP 客户端下载封面
+async fn fetch_cover(http: &HttpClientState, url: &str) -> Result<Vec<u8>, String> {
+    let mut request = HttpRequest::new("GET", url);
+    request.response_type = ResponseType::Bytes;
+    let response = http.execute(request).await?;
+    STANDARD
+        .decode(response.body)
+        .map_err(|e| format!("读取封面失败: {}", e))
+}
+
+// 为下载完成的曲目写入标签并按模板重命名，返回最终的文件路径
+pub async fn tag_downloaded(
+    http: &HttpClientState,
+    path: &Path,
+    track: &DownloadTrack,
+    filename_template: &str,
+) -> Result<PathBuf, String> {
+    let cover = match track.cover.as_deref().filter(|url| !url.trim().is_empty()) {
+        Some(url) => fetch_cover(http, url)
+            .await
+            .map_err(|e| eprintln!("下载封面失败 {}: {}", url, e))
+            .ok(),
+        None => None,
+    };
+
+    let source = path.to_path_buf();
+    let track = track.clone();
+    let template = filename_template.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_track_tags(&source, &track, cover.as_deref())?;
+
+        let dir = source.parent().unwrap_or(Path::new(""));
+        let mut target = match render_file_name(&template, &track) {
+            Some(relative) => dir.join(relative),
+            None => return Ok(source),
+        };
+        if let Some(extension) = source.extension() {
+            let mut file_name = target.file_name().unwrap_or_default().to_os_string();
+            file_name.push(".");
+            file_name.push(extension);
+            target.set_file_name(file_name);
+        }
+        if target == source {
+            return Ok(source);
+        }
+        if let Some(target_dir) = target.parent() {
+            std::fs::create_dir_all(target_dir).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        let target = available_path(target);
+        std::fs::rename(&source, &target).map_err(|e| format!("重命名文件失败: {}", e))?;
+        Ok(target)
+    })
+    .await
+    .map_err(|e| format!("写入标签失败: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download_queue::NamedItem;
+
+    fn track(title: &str, artists: &[&str], album: &str) -> DownloadTrack {
+        DownloadTrack {
+            id: "1".to_string(),
+            platform: "netease".to_string(),
+            title: title.to_string(),
+            artist: artists
+                .iter()
+                .map(|name| NamedItem {
+                    name: name.to_string(),
+                })
+                .collect(),
+            album: NamedItem {
+                name: album.to_string(),
+            },
+            track_no: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sanitizes_file_names() {
+        let cases = [
+            ("a/b\\c:d*e?f\"g<h>i|j", "a_b_c_d_e_f_g_h_i_j"),
+            ("tab\there", "tab_here"),
+            ("  name. . ", "name"),
+            ("..", ""),
+            ("   ", ""),
+            ("CON", "CON_"),
+            ("nul.txt", "nul_.txt"),
+            ("Com1 .flac", "Com1_ .flac"),
+            ("LPT9", "LPT9_"),
+            ("CONSOLE", "CONSOLE"),
+            ("COM10", "COM10"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(sanitize_file_name(name), expected, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn renders_templates() {
+        let song = track("晴天", &["周杰伦", " ", "Lara"], "叶惠美");
+        let cases = [
+            (DEFAULT_FILENAME_TEMPLATE, Some("周杰伦, Lara - 晴天")),
+            ("{album}/{track} {title}", Some("叶惠美/03 晴天")),
+            (
+                "{platform}\\{artist}/{title}",
+                Some("netease/周杰伦, Lara/晴天"),
+            ),
+            // 不能跳出下载目录
+            ("../../{title}", Some("晴天")),
+            ("./{album}/../{title}", Some("叶惠美/晴天")),
+            // 空的目录被忽略
+            ("{album}//{title}", Some("叶惠美/晴天")),
+            ("/", None),
+            ("..", None),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                render_file_name(template, &song),
+                expected.map(PathBuf::from),
+                "{}",
+                template
+            );
+        }
+        // 标签为空时结果为空
+        let empty = track("", &[], "");
+        assert_eq!(render_file_name("{album}/{title}", &empty), None);
+        // 标签中的路径分隔符和保留名不会生成目录或设备名
+        let tricky = track("../../evil", &["a/b"], "con");
+        assert_eq!(
+            render_file_name("{album}/{artist} - {title}", &tricky),
+            Some(PathBuf::from("con_/a_b - .._.._evil"))
+        );
+    }
+}
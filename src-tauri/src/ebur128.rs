@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// ReplayGain 2.0 的参考响度
+pub const REPLAY_GAIN_REFERENCE_LUFS: f64 = -18.0;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+// 100ms 为一个子块；瞬时响度窗口 400ms，短期响度窗口 3s
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+// 短期响度窗口每 1s 取一次（重叠 2s），与 EBU Tech 3342 一致
+const SHORT_TERM_STEP_SUB_BLOCKS: usize = 10;
+
+// 真峰值检测使用 4 倍过采样
+const OVERSAMPLE_FACTOR: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+// 单曲或专辑的响度测量结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessResult {
+    // 综合响度（LUFS），静音时为空
+    pub integrated: Option<f64>,
+    // 响度范围（LU）
+    pub loudness_range: f64,
+    // 真峰值，线性振幅
+    pub true_peak: f64,
+    // 真峰值（dBTP）
+    pub true_peak_db: f64,
+    // 对应 ReplayGain 2.0 参考电平的增益（dB）
+    pub replay_gain: Option<f64>,
+}
+
+// 一首曲目测量得到的门限块能量，用于计算单曲和专辑结果
+#[derive(Debug, Default, Clone)]
+pub struct LoudnessBlocks {
+    momentary: Vec<f64>,
+    short_term: Vec<f64>,
+    true_peak: f64,
+}
+
+impl LoudnessBlocks {
+    pub fn result(&self) -> LoudnessResult {
+        Self::combine(std::slice::from_ref(self))
+    }
+
+    // 合并多首曲目的块能量，得到专辑的整体响度
+    pub fn combine(tracks: &[LoudnessBlocks]) -> LoudnessResult {
+        let momentary: Vec<f64> = tracks
+            .iter()
+            .flat_map(|t| t.momentary.iter().copied())
+            .collect();
+        let short_term: Vec<f64> = tracks
+            .iter()
+            .flat_map(|t| t.short_term.iter().copied())
+            .collect();
+        let true_peak = tracks.iter().map(|t| t.true_peak).fold(0.0, f64::max);
+
+        let integrated = integrated_loudness(&momentary);
+        LoudnessResult {
+            integrated,
+            loudness_range: loudness_range(&short_term),
+            true_peak,
+            true_peak_db: 20.0 * true_peak.max(1e-10).log10(),
+            replay_gain: integrated.map(|lufs| REPLAY_GAIN_REFERENCE_LUFS - lufs),
+        }
+    }
+}
+
+// 二阶 IIR 滤波器（直接 II 型）
+#[derive(Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let w = x - self.a[1] * self.z[0] - self.a[2] * self.z[1];
+        let y = self.b[0] * w + self.b[1] * self.z[0] + self.b[2] * self.z[1];
+        self.z[1] = self.z[0];
+        self.z[0] = w;
+        y
+    }
+}
+
+// 按采样率计算 K 计权滤波器系数（高架滤波 + RLB 高通），公式来自 libebur128
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let f0 = 1681.974450955533;
+    let g = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(g / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        z: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+// 声道权重：5.1 布局中 LFE 不计入，环绕声道加权 1.41
+fn channel_weights(channels: usize) -> Vec<f64> {
+    (0..channels)
+        .map(|ch| match (channels, ch) {
+            (6, 3) => 0.0,
+            (6, 4) | (6, 5) => 1.41,
+            _ => 1.0,
+        })
+        .collect()
+}
+
+// 真峰值检测的多相插值滤波器
+struct TruePeak {
+    phases: Vec<[f64; TAPS_PER_PHASE]>,
+    history: Vec<[f64; TAPS_PER_PHASE]>,
+    peak: f64,
+}
+
+impl TruePeak {
+    fn new(channels: usize) -> Self {
+        let taps = OVERSAMPLE_FACTOR * TAPS_PER_PHASE;
+        let center = (taps - 1) as f64 / 2.0;
+        let mut phases = vec![[0.0; TAPS_PER_PHASE]; OVERSAMPLE_FACTOR];
+        for n in 0..taps {
+            let t = (n as f64 - center) / OVERSAMPLE_FACTOR as f64;
+            let sinc = if t == 0.0 {
+                1.0
+            } else {
+                (PI * t).sin() / (PI * t)
+            };
+            // Hann 窗
+            let window = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / taps as f64).cos();
+            phases[n % OVERSAMPLE_FACTOR][n / OVERSAMPLE_FACTOR] = sinc * window;
+        }
+        // 归一化每个相位的直流增益
+        for phase in phases.iter_mut() {
+            let sum: f64 = phase.iter().sum();
+            if sum.abs() > f64::EPSILON {
+                phase.iter_mut().for_each(|tap| *tap /= sum);
+            }
+        }
+
+        TruePeak {
+            phases,
+            history: vec![[0.0; TAPS_PER_PHASE]; channels],
+            peak: 0.0,
+        }
+    }
+
+    fn process(&mut self, channel: usize, x: f64) {
+        let history = &mut self.history[channel];
+        history.copy_within(0..TAPS_PER_PHASE - 1, 1);
+        history[0] = x;
+
+        let mut peak = self.peak.max(x.abs());
+        for phase in &self.phases {
+            let y: f64 = phase.iter().zip(history.iter()).map(|(h, s)| h * s).sum();
+            peak = peak.max(y.abs());
+        }
+        self.peak = peak;
+    }
+}
+
+// EBU R128 响度计，逐块输入交错排列的采样
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+    true_peak: TruePeak,
+    sub_block_frames: usize,
+    frame_in_sub_block: usize,
+    sub_block_sum: f64,
+    sub_blocks: Vec<f64>,
+    blocks: LoudnessBlocks,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+        let filter = k_weighting(sample_rate as f64);
+        LoudnessMeter {
+            channels,
+            filters: vec![filter; channels],
+            weights: channel_weights(channels),
+            true_peak: TruePeak::new(channels),
+            sub_block_frames: (sample_rate as usize / 10).max(1),
+            frame_in_sub_block: 0,
+            sub_block_sum: 0.0,
+            sub_blocks: Vec::new(),
+            blocks: LoudnessBlocks::default(),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (ch, &sample) in frame.iter().enumerate() {
+                let x = sample as f64;
+                self.true_peak.process(ch, x);
+
+                let [shelf, high_pass] = &mut self.filters[ch];
+                let y = high_pass.process(shelf.process(x));
+                self.sub_block_sum += self.weights[ch] * y * y;
+            }
+
+            self.frame_in_sub_block += 1;
+            if self.frame_in_sub_block == self.sub_block_frames {
+                self.push_sub_block();
+            }
+        }
+    }
+
+    fn push_sub_block(&mut self) {
+        self.sub_blocks
+            .push(self.sub_block_sum / self.sub_block_frames as f64);
+        self.sub_block_sum = 0.0;
+        self.frame_in_sub_block = 0;
+
+        let count = self.sub_blocks.len();
+        if count >= MOMENTARY_SUB_BLOCKS {
+            let window = &self.sub_blocks[count - MOMENTARY_SUB_BLOCKS..];
+            self.blocks
+                .momentary
+                .push(window.iter().sum::<f64>() / MOMENTARY_SUB_BLOCKS as f64);
+        }
+        if count >= SHORT_TERM_SUB_BLOCKS
+            && (count - SHORT_TERM_SUB_BLOCKS).is_multiple_of(SHORT_TERM_STEP_SUB_BLOCKS)
+        {
+            let window = &self.sub_blocks[count - SHORT_TERM_SUB_BLOCKS..];
+            self.blocks
+                .short_term
+                .push(window.iter().sum::<f64>() / SHORT_TERM_SUB_BLOCKS as f64);
+        }
+    }
+
+    pub fn finish(mut self) -> LoudnessBlocks {
+        self.blocks.true_peak = self.true_peak.peak;
+        self.blocks
+    }
+}
+
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.max(1e-20).log10()
+}
+
+fn loudness_to_energy(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+// 在绝对门限之上再按相对门限过滤后的块
+fn gated_blocks(blocks: &[f64], relative_gate: f64) -> Vec<f64> {
+    let absolute_threshold = loudness_to_energy(ABSOLUTE_GATE_LUFS);
+    let above_absolute: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&energy| energy > absolute_threshold)
+        .collect();
+    if above_absolute.is_empty() {
+        return above_absolute;
+    }
+
+    let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+    let relative_threshold = loudness_to_energy(energy_to_loudness(mean) + relative_gate);
+    above_absolute
+        .into_iter()
+        .filter(|&energy| energy > relative_threshold)
+        .collect()
+}
+
+fn integrated_loudness(blocks: &[f64]) -> Option<f64> {
+    let gated = gated_blocks(blocks, INTEGRATED_RELATIVE_GATE_LU);
+    if gated.is_empty() {
+        return None;
+    }
+    Some(energy_to_loudness(
+        gated.iter().sum::<f64>() / gated.len() as f64,
+    ))
+}
+
+// 响度范围：门限后短期响度分布的 10% 到 95% 分位之差
+fn loudness_range(short_term: &[f64]) -> f64 {
+    let mut loudness: Vec<f64> = gated_blocks(short_term, RANGE_RELATIVE_GATE_LU)
+        .into_iter()
+        .map(energy_to_loudness)
+        .collect();
+    if loudness.len() < 2 {
+        return 0.0;
+    }
+
+    loudness.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+    percentile(0.95) - percentile(0.10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    // 按顺序生成各段双声道 1 kHz 正弦波，每段为 (电平 dBFS, 秒)，返回测量结果
+    fn measure(segments: &[(f64, f64)]) -> LoudnessResult {
+        measure_sine(1000.0, 0.0, segments)
+    }
+
+    fn measure_sine(frequency: f64, phase: f64, segments: &[(f64, f64)]) -> LoudnessResult {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        let mut n: u64 = 0;
+        for &(level, seconds) in segments {
+            let amplitude = 10f64.powf(level / 20.0);
+            let frames = (seconds * SAMPLE_RATE as f64).round() as usize;
+            let mut samples = Vec::with_capacity(frames * 2);
+            for _ in 0..frames {
+                let t = n as f64 / SAMPLE_RATE as f64;
+                let sample = (amplitude * (2.0 * PI * frequency * t + phase).sin()) as f32;
+                samples.extend([sample, sample]);
+                n += 1;
+            }
+            meter.process(&samples);
+        }
+        meter.finish().result()
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "期望 {} ± {}，实际 {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    // 以下用例参照 EBU Tech 3341/3342 的最低要求测试，为缩短测试时间缩短了各段时长，期望值不变
+
+    // Tech 3341 测试 1、2：双声道 1 kHz 正弦波，综合响度与电平相同
+    #[test]
+    fn tech_3341_steady_sine() {
+        let result = measure(&[(-23.0, 5.0)]);
+        assert_near(result.integrated.unwrap(), -23.0, 0.1);
+        assert_near(result.replay_gain.unwrap(), 5.0, 0.1);
+        assert_near(measure(&[(-33.0, 5.0)]).integrated.unwrap(), -33.0, 0.1);
+    }
+
+    // Tech 3341 测试 3、4：相对门限和绝对门限排除安静的片段
+    #[test]
+    fn tech_3341_gating() {
+        let result = measure(&[(-36.0, 3.0), (-23.0, 20.0), (-36.0, 3.0)]);
+        assert_near(result.integrated.unwrap(), -23.0, 0.1);
+        let result = measure(&[
+            (-72.0, 3.0),
+            (-36.0, 3.0),
+            (-23.0, 20.0),
+            (-36.0, 3.0),
+            (-72.0, 3.0),
+        ]);
+        assert_near(result.integrated.unwrap(), -23.0, 0.1);
+    }
+
+    // Tech 3341 测试 5：-26、-20、-26 dBFS 三段时长相同，能量平均后为 -23 LUFS
+    #[test]
+    fn tech_3341_varying_level() {
+        let result = measure(&[(-26.0, 5.0), (-20.0, 5.0), (-26.0, 5.0)]);
+        assert_near(result.integrated.unwrap(), -23.0, 0.1);
+    }
+
+    // Tech 3342 测试 1～3：两段不同电平的正弦波，响度范围为两者之差
+    #[test]
+    fn tech_3342_loudness_range() {
+        for (first, second, expected) in [
+            (-20.0, -30.0, 10.0),
+            (-20.0, -15.0, 5.0),
+            (-40.0, -20.0, 20.0),
+        ] {
+            let result = measure(&[(first, 10.0), (second, 10.0)]);
+            assert_near(result.loudness_range, expected, 1.0);
+        }
+    }
+
+    // 采样点落在波峰之间时，真峰值仍接近实际振幅（Tech 3341 要求误差在 +0.2/-0.4 dB 内）
+    #[test]
+    fn true_peak_between_samples() {
+        let result = measure_sine(SAMPLE_RATE as f64 / 4.0, PI / 4.0, &[(-6.0, 1.0)]);
+        assert!(result.true_peak_db <= -6.0 + 0.2, "{}", result.true_peak_db);
+        assert!(result.true_peak_db >= -6.0 - 0.4, "{}", result.true_peak_db);
+    }
+
+    #[test]
+    fn silence_has_no_integrated_loudness() {
+        let result = measure(&[(-120.0, 2.0)]);
+        assert!(result.integrated.is_none());
+        assert!(result.replay_gain.is_none());
+        assert_eq!(result.loudness_range, 0.0);
+    }
+
+    // 专辑响度按全部曲目的块能量计算，不是各曲目响度的平均值
+    #[test]
+    fn album_combines_blocks() {
+        let track = |level: f64, seconds: f64| {
+            let mut meter = LoudnessMeter::new(SAMPLE_RATE, 1);
+            let amplitude = 10f64.powf(level / 20.0);
+            let step = 2.0 * PI * 1000.0 / SAMPLE_RATE as f64;
+            let samples: Vec<f32> = (0..(seconds * SAMPLE_RATE as f64) as usize)
+                .map(|n| (amplitude * (step * n as f64).sin()) as f32)
+                .collect();
+            meter.process(&samples);
+            meter.finish()
+        };
+        let album = LoudnessBlocks::combine(&[track(-20.0, 2.0), track(-20.0, 6.0)]);
+        // 单声道正弦波比双声道低约 3 dB
+        assert_near(album.integrated.unwrap(), -23.0, 0.1);
+    }
+}
@@ -0,0 +1,429 @@
+use lofty::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio_metadata::extract_title_and_artist_from_filename;
+use crate::file_hash::cached_track_id;
+use crate::http_client::HttpClientState;
+use crate::library::{modified_secs, LibraryState, LibraryTags};
+use crate::music_provider::{NamedRef, ProviderRegistry, Track};
+use crate::search_aggregator::{normalize, search_providers};
+
+// 本地歌曲的平台编码，与前端 LocalMusic.CODE 一致
+const LOCAL_PLATFORM: &str = "local";
+// 默认的最低可信度，低于该值的候选不会被使用
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.75;
+// 每个平台取前几条搜索结果作为候选
+const SEARCH_LIMIT: u32 = 10;
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(8);
+// 最多向平台请求几次播放地址
+const MAX_PLAY_ATTEMPTS: usize = 5;
+// 时长相差不超过 DURATION_EXACT_MS 时完全一致，超过 DURATION_MAX_DIFF_MS 时不得分
+const DURATION_EXACT_MS: f64 = 2000.0;
+const DURATION_MAX_DIFF_MS: f64 = 15000.0;
+// 本地文件标签中多位歌手的分隔符
+const ARTIST_SEPARATORS: [char; 4] = ['/', '、', ';', '&'];
+
+// 可播放的替代来源，track.url 为播放地址
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayableSource {
+    pub track: Track,
+    // 与原歌曲的匹配程度，0 ~ 1
+    pub confidence: f64,
+}
+
+// 字符二元组的 Dice 系数
+fn text_similarity(a: &str, b: &str) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let pairs = |text: &str| {
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<Vec<_>>()
+    };
+    let a_pairs = pairs(a);
+    let mut b_pairs = pairs(b);
+    let total = a_pairs.len() + b_pairs.len();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut common = 0;
+    for pair in a_pairs {
+        if let Some(index) = b_pairs.iter().position(|other| *other == pair) {
+            b_pairs.swap_remove(index);
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / total as f64
+}
+
+// 任意两位歌手的最高相似度，缺少歌手时为空
+fn artist_similarity(a: &Track, b: &Track) -> Option<f64> {
+    let names = |track: &Track| {
+        track
+            .artist
+            .iter()
+            .map(|artist| normalize(&artist.name))
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (names(a), names(b));
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| text_similarity(x, y)))
+        .reduce(f64::max)
+}
+
+fn duration_similarity(a: u64, b: u64) -> Option<f64> {
+    if a == 0 || b == 0 {
+        return None;
+    }
+    let diff = a.abs_diff(b) as f64;
+    Some(
+        ((DURATION_MAX_DIFF_MS - diff) / (DURATION_MAX_DIFF_MS - DURATION_EXACT_MS))
+            .clamp(0.0, 1.0),
+    )
+}
+
+// 按标题、歌手、时长加权计算可信度；缺少歌手或时长时该项不得分，只有标题相同达不到默认的最低可信度
+pub fn match_confidence(target: &Track, candidate: &Track) -> f64 {
+    let title = text_similarity(&normalize(&target.title), &normalize(&candidate.title));
+    let artist = artist_similarity(target, candidate).unwrap_or(0.0);
+    let duration = duration_similarity(target.duration, candidate.duration).unwrap_or(0.0);
+    0.5 * title + 0.3 * artist + 0.2 * duration
+}
+
+// 读取本地文件的标签，没有标签时从文件名中提取
+fn read_local_tags(path: &Path, modified: u64) -> LibraryTags {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (file_title, file_artist) = extract_title_and_artist_from_filename(&file_name);
+    let tagged_file = match lofty::read_from_path(path) {
+        Ok(tagged_file) => tagged_file,
+        Err(_) => {
+            return LibraryTags {
+                title: file_title,
+                artist: file_artist,
+                duration: 0,
+                modified,
+            }
+        }
+    };
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag());
+    let title = tag
+        .and_then(|tag| tag.title().map(|title| title.trim().to_string()))
+        .filter(|title| !title.is_empty())
+        .unwrap_or(file_title);
+    let artist = tag
+        .and_then(|tag| tag.artist().map(|artist| artist.trim().to_string()))
+        .filter(|artist| !artist.is_empty())
+        .unwrap_or(file_artist);
+    LibraryTags {
+        title,
+        artist,
+        duration: tagged_file.properties().duration().as_millis() as u64,
+        modified,
+    }
+}
+
+fn local_track(full_path: &str, tags: &LibraryTags) -> Track {
+    let mut track = Track::new("", LOCAL_PLATFORM);
+    track.title = tags.title.clone();
+    track.artist = tags
+        .artist
+        .split(ARTIST_SEPARATORS)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| NamedRef {
+            id: String::new(),
+            name: name.to_string(),
+        })
+        .collect();
+    track.duration = tags.duration;
+    track.url = Some(full_path.to_string());
+    track
+}
+
+// 曲库中仍然存在的本地文件，标签按修改时间缓存在曲库中
+async fn library_tracks(app_handle: &AppHandle) -> Vec<Track> {
+    let cached: Vec<(String, Option<LibraryTags>)> = match app_handle.state::<LibraryState>().lock()
+    {
+        Ok(library) => library
+            .entries()
+            .map(|entry| (entry.full_path.clone(), entry.tags.clone()))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+
+    let scanned = tauri::async_runtime::spawn_blocking(move || {
+        cached
+            .into_iter()
+            .filter_map(|(full_path, tags)| {
+                let path = Path::new(&full_path);
+                // 文件已删除时跳过
+                let modified = modified_secs(path)?;
+                match tags.filter(|tags| tags.modified == modified) {
+                    Some(tags) => Some((full_path, tags, false)),
+                    None => {
+                        let tags = read_local_tags(path, modified);
+                        Some((full_path, tags, true))
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+
+    if scanned.iter().any(|(_, _, updated)| *updated) {
+        if let Ok(mut library) = app_handle.state::<LibraryState>().lock() {
+            for (full_path, tags, updated) in &scanned {
+                if *updated {
+                    library.entry_mut(full_path).tags = Some(tags.clone());
+                }
+            }
+            if let Err(e) = library.save() {
+                eprintln!("{}", e);
+            }
+        }
+    }
+    scanned
+        .iter()
+        .map(|(full_path, tags, _)| local_track(full_path, tags))
+        .collect()
+}
+
+// 本地歌曲候选：前端的本地歌曲列表，加上曲库中不在列表里的文件；文件已删除的跳过
+async fn local_candidates(app_handle: &AppHandle, listed: Vec<Track>) -> Vec<Track> {
+    let mut tracks: Vec<Track> = listed
+        .into_iter()
+        .filter(|track| track.platform == LOCAL_PLATFORM)
+        .filter(|track| {
+            track
+                .url
+                .as_deref()
+                .is_some_and(|url| Path::new(url).is_file())
+        })
+        .collect();
+    for track in library_tracks(app_handle).await {
+        if !tracks.iter().any(|listed| listed.url == track.url) {
+            tracks.push(track);
+        }
+    }
+    tracks
+}
+
+// 歌曲无法播放（如 VIP、地区限制）时，在其他平台和本地歌曲中查找同一首歌，返回可信度最高的可播放来源；
+// local_tracks 为前端的本地歌曲列表，没有找到时返回空
+#[tauri::command]
+pub async fn resolve_playable_source(
+    app_handle: AppHandle,
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    track: Track,
+    platforms: Option<Vec<String>>,
+    include_local: Option<bool>,
+    local_tracks: Option<Vec<Track>>,
+    min_confidence: Option<f64>,
+) -> Result<Option<PlayableSource>, String> {
+    let title = track.title.trim();
+    if title.is_empty() {
+        return Err("缺少歌曲名".to_string());
+    }
+    let min_confidence = min_confidence
+        .unwrap_or(DEFAULT_MIN_CONFIDENCE)
+        .clamp(0.0, 1.0);
+    let is_same = |candidate: &Track| {
+        if candidate.platform == LOCAL_PLATFORM {
+            candidate.url.is_some() && candidate.url == track.url
+        } else {
+            candidate.platform == track.platform && candidate.id == track.id
+        }
+    };
+
+    let mut candidates: Vec<(f64, Track)> = Vec::new();
+    if include_local.unwrap_or(true) {
+        let listed = local_tracks.unwrap_or_default();
+        for local in local_candidates(&app_handle, listed).await {
+            let confidence = match_confidence(&track, &local);
+            if confidence >= min_confidence && !is_same(&local) {
+                candidates.push((confidence, local));
+            }
+        }
+    }
+
+    let providers = match platforms {
+        Some(platforms) => platforms
+            .iter()
+            .map(|code| registry.get(code))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => registry.all().to_vec(),
+    };
+    let keyword = match track.artist.first() {
+        Some(artist) => format!("{} {}", title, artist.name.trim()),
+        None => title.to_string(),
+    };
+    let mut receiver = search_providers(
+        &app_handle,
+        &providers,
+        &keyword,
+        SEARCH_LIMIT,
+        SEARCH_TIMEOUT,
+    );
+    while let Some((index, result)) = receiver.recv().await {
+        match result {
+            Ok(result) => {
+                for candidate in result.data {
+                    let confidence = match_confidence(&track, &candidate);
+                    if confidence >= min_confidence && !is_same(&candidate) {
+                        candidates.push((confidence, candidate));
+                    }
+                }
+            }
+            Err(e) => eprintln!("搜索 {} 失败: {}", providers[index].code(), e),
+        }
+    }
+
+    // 可信度相同时优先本地文件，其次是非 VIP 歌曲
+    candidates.sort_by(|(a_confidence, a), (b_confidence, b)| {
+        b_confidence
+            .total_cmp(a_confidence)
+            .then_with(|| (b.platform == LOCAL_PLATFORM).cmp(&(a.platform == LOCAL_PLATFORM)))
+            .then_with(|| a.pay_play.cmp(&b.pay_play))
+    });
+
+    let mut attempts = 0;
+    for (confidence, mut candidate) in candidates {
+        if candidate.platform == LOCAL_PLATFORM {
+            // 曲库中的文件没有 id，与前端本地歌曲的 id 一致
+            if candidate.id.is_empty() {
+                let full_path = candidate.url.clone().unwrap_or_default();
+                let handle = app_handle.clone();
+                candidate.id = tauri::async_runtime::spawn_blocking(move || {
+                    let library = handle.state::<LibraryState>();
+                    let id = cached_track_id(&library, Path::new(&full_path));
+                    if let Ok(mut library) = library.lock() {
+                        if let Err(e) = library.save_if_dirty() {
+                            eprintln!("{}", e);
+                        }
+                    }
+                    id
+                })
+                .await
+                .map_err(|e| format!("读取本地文件失败: {}", e))?;
+            }
+            return Ok(Some(PlayableSource {
+                track: candidate,
+                confidence,
+            }));
+        }
+        if attempts >= MAX_PLAY_ATTEMPTS {
+            continue;
+        }
+        attempts += 1;
+        let detail = match registry
+            .get(&candidate.platform)?
+            .play_detail(&http, &candidate)
+            .await
+        {
+            Ok(detail) => detail,
+            Err(e) => {
+                eprintln!(
+                    "获取播放地址失败 {} {}: {}",
+                    candidate.platform, candidate.id, e
+                );
+                continue;
+            }
+        };
+        if detail.url.is_none() {
+            continue;
+        }
+        candidate.url = detail.url;
+        if detail.cover.is_some() {
+            candidate.cover = detail.cover;
+        }
+        if detail.lyric.is_some() {
+            candidate.lyric = detail.lyric;
+        }
+        return Ok(Some(PlayableSource {
+            track: candidate,
+            confidence,
+        }));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(title: &str, artists: &[&str], duration: u64) -> Track {
+        let mut track = Track::new("1", "qq");
+        track.title = title.to_string();
+        track.artist = artists
+            .iter()
+            .map(|name| NamedRef {
+                id: String::new(),
+                name: name.to_string(),
+            })
+            .collect();
+        track.duration = duration;
+        track
+    }
+
+    #[test]
+    fn same_song_is_confident() {
+        let target = track("晴天", &["周杰伦"], 269_000);
+        let confidence = match_confidence(&target, &track("晴天", &["周杰伦"], 270_000));
+        assert!(confidence > 0.99, "{}", confidence);
+        let featured = match_confidence(&target, &track("晴天", &["A", "周杰伦"], 268_000));
+        assert!(featured >= DEFAULT_MIN_CONFIDENCE, "{}", featured);
+    }
+
+    #[test]
+    fn title_alone_is_not_enough() {
+        let target = track("晴天", &["周杰伦"], 269_000);
+        for candidate in [
+            track("晴天", &[], 0),
+            track("晴天", &["某人"], 0),
+            track("晴天", &["某人"], 200_000),
+        ] {
+            let confidence = match_confidence(&target, &candidate);
+            assert!(confidence < DEFAULT_MIN_CONFIDENCE, "{}", confidence);
+        }
+        let untagged = track("晴天", &[], 269_000);
+        assert!(
+            match_confidence(&untagged, &track("晴天", &["周杰伦"], 0)) < DEFAULT_MIN_CONFIDENCE
+        );
+    }
+
+    #[test]
+    fn different_versions_score_lower() {
+        let target = track("晴天", &["周杰伦"], 269_000);
+        let live = match_confidence(&target, &track("晴天 (Live)", &["周杰伦"], 290_000));
+        let other = match_confidence(&target, &track("七里香", &["周杰伦"], 299_000));
+        assert!(live < DEFAULT_MIN_CONFIDENCE, "{}", live);
+        assert!(other < live, "{} {}", other, live);
+    }
+
+    #[test]
+    fn duration_tolerance() {
+        assert_eq!(duration_similarity(200_000, 201_000), Some(1.0));
+        assert_eq!(duration_similarity(200_000, 230_000), Some(0.0));
+        assert_eq!(duration_similarity(0, 200_000), None);
+    }
+}
@@ -0,0 +1,126 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use symphonia::core::errors::Error as SymphoniaError;
+
+use crate::audio_decoder::open_format;
+use crate::library::{modified_secs, LibraryState, LibraryTrackId};
+
+// 曲目标识的前缀，区分标识来源
+const AUDIO_ID_PREFIX: &str = "audio-";
+const PATH_ID_PREFIX: &str = "path-";
+// 标识只保留前 32 位十六进制字符（128 位）
+const ID_HEX_LEN: usize = 32;
+
+// 只对音频数据包计算 SHA-256，不包含标签和封面，修改标签后结果不变
+pub fn sha256_audio_payload(path: &Path) -> Result<String, String> {
+    let (mut format, track) = open_format(path)?;
+    let mut hasher = Sha256::new();
+    let mut packets = 0;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(format!("读取音频数据失败: {}", e)),
+        };
+        if packet.track_id() != track.id {
+            continue;
+        }
+        hasher.update(&packet.data);
+        packets += 1;
+    }
+
+    if packets == 0 {
+        return Err("没有读取到音频数据".to_string());
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// 本地文件的稳定标识：优先使用音频数据的哈希，无法解析音频时退回到文件路径的哈希
+pub fn stable_track_id(path: &Path) -> String {
+    match sha256_audio_payload(path) {
+        Ok(hash) => format!("{}{}", AUDIO_ID_PREFIX, &hash[..ID_HEX_LEN]),
+        Err(e) => {
+            eprintln!("计算音频数据哈希失败 {:?}: {}，使用路径作为标识", path, e);
+            let hash = format!("{:x}", Sha256::digest(path.to_string_lossy().as_bytes()));
+            format!("{}{}", PATH_ID_PREFIX, &hash[..ID_HEX_LEN])
+        }
+    }
+}
+
+// 带缓存的稳定标识：文件修改时间不变时直接使用曲库中保存的结果，避免每次读取元数据都解码整个文件
+// 新计算的结果只标记为未保存，由调用方在整批处理完成后保存曲库
+pub fn cached_track_id(library: &LibraryState, path: &Path) -> String {
+    let full_path = path.to_string_lossy().into_owned();
+    let modified = match modified_secs(path) {
+        Some(modified) => modified,
+        None => return stable_track_id(path),
+    };
+    if let Ok(library) = library.lock() {
+        let cached = library
+            .get(&full_path)
+            .and_then(|entry| entry.track_id.as_ref())
+            .filter(|track_id| track_id.modified == modified);
+        if let Some(track_id) = cached {
+            return track_id.id.clone();
+        }
+    }
+
+    // 计算期间不持有曲库锁
+    let id = stable_track_id(path);
+    if let Ok(mut library) = library.lock() {
+        library.entry_mut(&full_path).track_id = Some(LibraryTrackId {
+            id: id.clone(),
+            modified,
+        });
+        library.mark_dirty();
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use std::sync::Mutex;
+
+    #[test]
+    fn track_id_is_cached_until_file_changes() {
+        let dir = std::env::temp_dir().join("file_hash_cached_track_id");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.mp3");
+        std::fs::write(&path, b"not audio").unwrap();
+        let library: LibraryState = Mutex::new(Library::load(&dir));
+
+        let id = cached_track_id(&library, &path);
+        assert_eq!(id, stable_track_id(&path));
+        let full_path = path.to_string_lossy().into_owned();
+        let saved = library.lock().unwrap().get(&full_path).cloned().unwrap();
+        assert_eq!(saved.track_id.unwrap().id, id);
+        // 计算结果只标记为未保存，保存后重新加载曲库仍然可用
+        assert!(Library::load(&dir).get(&full_path).is_none());
+        library.lock().unwrap().save_if_dirty().unwrap();
+        let reloaded: LibraryState = Mutex::new(Library::load(&dir));
+        assert_eq!(cached_track_id(&reloaded, &path), id);
+
+        // 修改时间不变时直接使用缓存
+        {
+            let mut library = library.lock().unwrap();
+            let saved = library.entry_mut(&full_path).track_id.as_mut().unwrap();
+            saved.id = "cached".to_string();
+        }
+        assert_eq!(cached_track_id(&library, &path), "cached");
+
+        // 修改时间变化后重新计算
+        {
+            let mut library = library.lock().unwrap();
+            let saved = library.entry_mut(&full_path).track_id.as_mut().unwrap();
+            saved.modified -= 1;
+        }
+        assert_eq!(cached_track_id(&library, &path), id);
+    }
+}
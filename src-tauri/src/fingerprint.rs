@@ -0,0 +1,391 @@
+use rusty_chromaprint::{Configuration, Fingerprinter};
+use serde::{Deserialize, Serialize};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::audio_decoder::decode_file;
+use crate::library::LibraryState;
+
+// 与 fpcalc 默认值一致，只取前 120 秒计算指纹
+const MAX_FINGERPRINT_SECONDS: u64 = 120;
+// 比较时允许的最大错位（指纹项数，约 ±10 秒）
+const MAX_ALIGN_OFFSET: usize = 80;
+// 重叠部分太少时不认为可比
+const MIN_OVERLAP_ITEMS: usize = 40;
+// 默认相似度阈值，1.0 表示完全一致
+const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.85;
+// 时长相差超过该值（毫秒）的曲目不做比较
+const MAX_DURATION_DIFF_MS: f64 = 10_000.0;
+
+// 保存在曲库中的声学指纹（Chromaprint 兼容，test2 预设）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcousticFingerprint {
+    pub data: Vec<u32>,
+    // 音频时长（毫秒）
+    pub duration: f64,
+}
+
+// 每计算完一个文件发送的进度事件
+#[derive(Clone, Serialize)]
+struct FingerprintProgress {
+    current: usize,
+    total: usize,
+    full_path: String,
+    error: Option<String>,
+}
+
+// 重复曲目分组中的一项
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateTrack {
+    pub full_path: String,
+    pub duration: f64,
+    // 与分组中第一首曲目的相似度，分组是传递的，可能低于阈值
+    pub similarity: f64,
+}
+
+// 计算声学指纹
+pub fn compute_fingerprint(
+    path: &Path,
+    cancel: Option<&AtomicBool>,
+) -> Result<AcousticFingerprint, String> {
+    let config = Configuration::preset_test2();
+    let mut printer: Option<Fingerprinter> = None;
+    let mut frames: u64 = 0;
+    let mut sample_rate: u32 = 0;
+    let mut pcm: Vec<i16> = Vec::new();
+    // 采样率或声道数不受支持时停止解码，并返回该错误
+    let mut start_error: Option<String> = None;
+
+    let info = decode_file(path, cancel, |info, samples| {
+        if start_error.is_some() {
+            return ControlFlow::Break(());
+        }
+        if printer.is_none() {
+            let mut created = Fingerprinter::new(&config);
+            if let Err(e) = created.start(info.sample_rate, info.channels as u32) {
+                start_error = Some(format!("初始化指纹计算失败: {:?}", e));
+                return ControlFlow::Break(());
+            }
+            sample_rate = info.sample_rate;
+            printer = Some(created);
+        }
+        let Some(printer) = printer.as_mut() else {
+            return ControlFlow::Break(());
+        };
+        frames += (samples.len() / info.channels.max(1)) as u64;
+
+        pcm.clear();
+        pcm.extend(
+            samples
+                .iter()
+                .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+        printer.consume(&pcm);
+        // 达到最大时长后不再解码剩余部分
+        if frames >= MAX_FINGERPRINT_SECONDS * sample_rate as u64 {
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    })?;
+
+    if let Some(e) = start_error {
+        return Err(e);
+    }
+    let mut printer = printer.ok_or_else(|| "没有解码到音频数据".to_string())?;
+    printer.finish();
+
+    let duration = match info.total_frames {
+        Some(total) if info.sample_rate > 0 => total as f64 * 1000.0 / info.sample_rate as f64,
+        _ if sample_rate > 0 => frames as f64 * 1000.0 / sample_rate as f64,
+        _ => 0.0,
+    };
+
+    Ok(AcousticFingerprint {
+        data: printer.fingerprint().to_vec(),
+        duration,
+    })
+}
+
+// 计算两个指纹的相似度（0.0 ~ 1.0），在允许的错位范围内取最好的对齐结果
+pub fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let mut best = 0.0;
+    for offset in 0..=MAX_ALIGN_OFFSET {
+        best = f64::max(best, aligned_similarity(&a[offset.min(a.len())..], b));
+        if offset > 0 {
+            best = f64::max(best, aligned_similarity(a, &b[offset.min(b.len())..]));
+        }
+    }
+    best
+}
+
+fn aligned_similarity(a: &[u32], b: &[u32]) -> f64 {
+    let overlap = a.len().min(b.len());
+    if overlap < MIN_OVERLAP_ITEMS {
+        return 0.0;
+    }
+    let bit_errors: u32 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x ^ y).count_ones())
+        .sum();
+    1.0 - bit_errors as f64 / (overlap as f64 * 32.0)
+}
+
+#[tauri::command]
+pub async fn compute_fingerprints(
+    app_handle: AppHandle,
+    full_paths: Vec<String>,
+    force: bool,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let library = app_handle.state::<LibraryState>();
+        let total = full_paths.len();
+        let mut computed = 0;
+
+        for (index, full_path) in full_paths.iter().enumerate() {
+            let exists = library
+                .lock()
+                .map(|library| {
+                    library
+                        .get(full_path)
+                        .is_some_and(|entry| entry.fingerprint.is_some())
+                })
+                .unwrap_or(false);
+
+            let error = if exists && !force {
+                None
+            } else {
+                match compute_fingerprint(Path::new(full_path), None) {
+                    Ok(fingerprint) => {
+                        if let Ok(mut library) = library.lock() {
+                            library.entry_mut(full_path).fingerprint = Some(fingerprint);
+                        }
+                        computed += 1;
+                        None
+                    }
+                    Err(e) => {
+                        eprintln!("计算声学指纹失败 {}: {}", full_path, e);
+                        Some(e)
+                    }
+                }
+            };
+
+            let _ = app_handle.emit(
+                "fingerprint-progress",
+                FingerprintProgress {
+                    current: index + 1,
+                    total,
+                    full_path: full_path.clone(),
+                    error,
+                },
+            );
+        }
+
+        let mut library = library.lock().map_err(|e| format!("更新曲库失败: {}", e))?;
+        library.save()?;
+        Ok(computed)
+    })
+    .await
+    .map_err(|e| format!("计算声学指纹失败: {}", e))?
+}
+
+// 按声学相似度对曲库中已有指纹的曲目分组，只返回包含两首及以上曲目的分组
+#[tauri::command]
+pub async fn find_duplicates(
+    app_handle: AppHandle,
+    threshold: Option<f64>,
+) -> Result<Vec<Vec<DuplicateTrack>>, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+    let tracks: Vec<(String, AcousticFingerprint)> = {
+        let library = app_handle.state::<LibraryState>();
+        let library = library.lock().map_err(|e| format!("读取曲库失败: {}", e))?;
+        library
+            .entries()
+            .filter_map(|entry| {
+                entry
+                    .fingerprint
+                    .clone()
+                    .map(|fingerprint| (entry.full_path.clone(), fingerprint))
+            })
+            .collect()
+    };
+
+    tauri::async_runtime::spawn_blocking(move || group_duplicates(&tracks, threshold))
+        .await
+        .map_err(|e| format!("查找重复曲目失败: {}", e))
+}
+
+// 相似度达到阈值的两首曲目归为一组，分组是传递的：A 与 B、B 与 C 相似时三首在同一组，
+// 即使 A 与 C 的相似度低于阈值（如同一首歌的不同剪辑版本）
+fn group_duplicates(
+    tracks: &[(String, AcousticFingerprint)],
+    threshold: f64,
+) -> Vec<Vec<DuplicateTrack>> {
+    // 按时长排序后只需比较时长相近的曲目
+    let mut order: Vec<usize> = (0..tracks.len()).collect();
+    order.sort_by(|&a, &b| tracks[a].1.duration.total_cmp(&tracks[b].1.duration));
+
+    // 并查集
+    let mut parent: Vec<usize> = (0..tracks.len()).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        let mut node = i;
+        while parent[node] != root {
+            let next = parent[node];
+            parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    for (pos, &i) in order.iter().enumerate() {
+        for &j in &order[pos + 1..] {
+            if tracks[j].1.duration - tracks[i].1.duration > MAX_DURATION_DIFF_MS {
+                break;
+            }
+            if similarity(&tracks[i].1.data, &tracks[j].1.data) >= threshold {
+                let (root_i, root_j) = (find(&mut parent, i), find(&mut parent, j));
+                if root_i != root_j {
+                    parent[root_j] = root_i;
+                }
+            }
+        }
+    }
+
+    let mut groups: std::collections::HashMap<usize, Vec<usize>> = Default::default();
+    for i in 0..tracks.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut result: Vec<Vec<DuplicateTrack>> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let first = &tracks[members[0]].1.data;
+            members
+                .iter()
+                .map(|&i| DuplicateTrack {
+                    full_path: tracks[i].0.clone(),
+                    duration: tracks[i].1.duration,
+                    similarity: similarity(first, &tracks[i].1.data),
+                })
+                .collect()
+        })
+        .collect();
+    result.sort_by(|a, b| a[0].full_path.cmp(&b[0].full_path));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 固定种子的伪随机指纹
+    fn fingerprint(seed: u32, len: usize) -> Vec<u32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state
+            })
+            .collect()
+    }
+
+    // 把每一项的前 bits 位取反
+    fn flip_bits(data: &[u32], bits: u32) -> Vec<u32> {
+        let mask = if bits >= 32 {
+            u32::MAX
+        } else {
+            (1 << bits) - 1
+        };
+        data.iter().map(|value| value ^ mask).collect()
+    }
+
+    fn track(name: &str, data: Vec<u32>, duration: f64) -> (String, AcousticFingerprint) {
+        (name.to_string(), AcousticFingerprint { data, duration })
+    }
+
+    fn paths(groups: &[Vec<DuplicateTrack>]) -> Vec<Vec<&str>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|track| track.full_path.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn aligned_similarity_counts_bit_errors() {
+        let a = fingerprint(1, 100);
+        assert_eq!(aligned_similarity(&a, &a), 1.0);
+        assert_eq!(aligned_similarity(&a, &flip_bits(&a, 8)), 0.75);
+        assert_eq!(aligned_similarity(&a, &flip_bits(&a, 32)), 0.0);
+        // 只比较重叠部分
+        assert_eq!(aligned_similarity(&a, &a[..60]), 1.0);
+        // 重叠部分太少
+        assert_eq!(
+            aligned_similarity(&a[..MIN_OVERLAP_ITEMS - 1], &a[..MIN_OVERLAP_ITEMS - 1]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn similarity_finds_best_alignment() {
+        let a = fingerprint(1, 300);
+        assert_eq!(similarity(&a, &a), 1.0);
+        // 任一方向错位都能对齐
+        assert_eq!(similarity(&a[20..], &a), 1.0);
+        assert_eq!(similarity(&a, &a[MAX_ALIGN_OFFSET..]), 1.0);
+        // 超出允许的错位范围
+        assert!(similarity(&a, &a[MAX_ALIGN_OFFSET + 20..]) < 0.7);
+        // 不相关的指纹约有一半的位相同
+        let unrelated = similarity(&a, &fingerprint(2, 300));
+        assert!(unrelated < 0.7, "{}", unrelated);
+        assert_eq!(similarity(&a[..10], &a[..10]), 0.0);
+    }
+
+    #[test]
+    fn groups_similar_tracks_within_duration() {
+        let song = fingerprint(1, 200);
+        let tracks = [
+            track("a.flac", song.clone(), 200_000.0),
+            track("b.mp3", flip_bits(&song, 2), 201_000.0),
+            track("c.mp3", fingerprint(2, 200), 200_500.0),
+            // 指纹相同但时长相差超过 10 秒
+            track("d.mp3", song.clone(), 215_000.0),
+            track("e.mp3", song[30..].to_vec(), 195_000.0),
+        ];
+        let groups = group_duplicates(&tracks, DEFAULT_SIMILARITY_THRESHOLD);
+        let mut members = paths(&groups);
+        for group in members.iter_mut() {
+            group.sort();
+        }
+        assert_eq!(members, [vec!["a.flac", "b.mp3", "e.mp3"]]);
+        let similarities: Vec<f64> = groups[0].iter().map(|track| track.similarity).collect();
+        assert!(similarities
+            .iter()
+            .all(|s| *s >= DEFAULT_SIMILARITY_THRESHOLD));
+    }
+
+    #[test]
+    fn grouping_is_transitive() {
+        // b 与 a、c 都相似，a 与 c 低于阈值，三首仍在同一组
+        let a = fingerprint(1, 200);
+        let b = flip_bits(&a, 4);
+        let c = flip_bits(&a, 8);
+        assert!(similarity(&a, &b) >= 0.85 && similarity(&b, &c) >= 0.85);
+        assert!(similarity(&a, &c) < 0.85);
+        let tracks = [
+            track("a.mp3", a, 100_000.0),
+            track("b.mp3", b, 100_000.0),
+            track("c.mp3", c, 100_000.0),
+        ];
+        let groups = group_duplicates(&tracks, 0.85);
+        assert_eq!(paths(&groups), [vec!["a.mp3", "b.mp3", "c.mp3"]]);
+        assert!(groups[0][2].similarity < 0.85);
+    }
+}
@@ -0,0 +1,427 @@
+use reqwest::header::COOKIE;
+use reqwest::Request;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HTTP_CACHE_DIR_NAME: &str = "http";
+const META_EXTENSION: &str = "json";
+const BODY_EXTENSION: &str = "body";
+// 缓存容量上限，超过后删除最早保存的条目
+const DEFAULT_MAX_BYTES: u64 = 200 * 1024 * 1024;
+
+// 未经解码的完整响应
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: u16,
+    pub url: String,
+    pub headers: HashMap<String, Vec<String>>,
+    pub bytes: Vec<u8>,
+}
+
+// 缓存条目的元数据，响应体单独保存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheMeta {
+    method: String,
+    url: String,
+    status: u16,
+    headers: HashMap<String, Vec<String>>,
+    stored_at: i64,
+    // 在此之前无需重新验证（Unix 秒）
+    fresh_until: i64,
+}
+
+// 命中的缓存
+pub struct CachedResponse {
+    pub response: RawResponse,
+    pub fresh: bool,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CachedResponse {
+    // 是否可以发送条件请求进行重新验证
+    pub fn can_revalidate(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+// 缓存占用情况
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HttpCacheInfo {
+    pub entries: usize,
+    pub size_bytes: u64,
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn first_header<'a>(headers: &'a HashMap<String, Vec<String>>, name: &str) -> Option<&'a str> {
+    headers.get(name)?.first().map(String::as_str)
+}
+
+// Set-Cookie 已经写入 Cookie 存储，不随缓存保存
+fn cacheable_headers(headers: &HashMap<String, Vec<String>>) -> HashMap<String, Vec<String>> {
+    headers
+        .iter()
+        .filter(|(name, _)| name.as_str() != "set-cookie")
+        .map(|(name, values)| (name.clone(), values.clone()))
+        .collect()
+}
+
+// 按响应头计算缓存策略：返回 None 表示不能缓存，否则返回新鲜期（秒）
+fn freshness_from_headers(headers: &HashMap<String, Vec<String>>) -> Option<i64> {
+    let has_validator = headers.contains_key("etag") || headers.contains_key("last-modified");
+
+    let mut max_age = None;
+    let mut no_cache = false;
+    if let Some(values) = headers.get("cache-control") {
+        for directive in values.iter().flat_map(|value| value.split(',')) {
+            let directive = directive.trim().to_ascii_lowercase();
+            if directive == "no-store" {
+                return None;
+            } else if directive == "no-cache" {
+                no_cache = true;
+            } else if let Some(seconds) = directive.strip_prefix("max-age=") {
+                max_age = seconds.trim_matches('"').parse::<i64>().ok();
+            }
+        }
+    }
+
+    let freshness = if no_cache {
+        0
+    } else if let Some(max_age) = max_age {
+        max_age.max(0)
+    } else if let Some(expires) = first_header(headers, "expires") {
+        httpdate::parse_http_date(expires)
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| (d.as_secs() as i64 - now_secs()).max(0))
+            .unwrap_or(0)
+    } else {
+        0
+    };
+
+    // 既没有新鲜期也无法重新验证的响应不缓存
+    if freshness == 0 && !has_validator {
+        None
+    } else {
+        Some(freshness)
+    }
+}
+
+// 磁盘上的 HTTP 响应缓存，以 方法 + 地址 + 请求体 的哈希为键
+pub struct HttpCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    // 已占用的字节数，第一次保存时统计目录得到
+    used: Mutex<Option<u64>>,
+}
+
+impl HttpCache {
+    pub fn new(cache_dir: &Path) -> Self {
+        Self::with_max_bytes(cache_dir, DEFAULT_MAX_BYTES)
+    }
+
+    pub fn with_max_bytes(cache_dir: &Path, max_bytes: u64) -> Self {
+        HttpCache {
+            dir: cache_dir.join(HTTP_CACHE_DIR_NAME),
+            max_bytes,
+            used: Mutex::new(None),
+        }
+    }
+
+    // 计算缓存键，custom_key 用于请求体每次都不同（如带随机加密参数）的请求。
+    // 带 Cookie 的请求按 Cookie 区分，切换账号或退出登录后不会读到其他账号的响应
+    pub fn key_for(request: &Request, custom_key: Option<&str>) -> String {
+        let mut hasher = Sha256::new();
+        match custom_key {
+            Some(key) => hasher.update(key.as_bytes()),
+            None => {
+                hasher.update(request.method().as_str().as_bytes());
+                hasher.update(b" ");
+                hasher.update(request.url().as_str().as_bytes());
+                hasher.update(b"\n");
+                if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+                    hasher.update(body);
+                }
+            }
+        }
+        if let Some(cookie) = request.headers().get(COOKIE) {
+            hasher.update(b"\ncookie: ");
+            hasher.update(cookie.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, META_EXTENSION))
+    }
+
+    fn body_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, BODY_EXTENSION))
+    }
+
+    // 条目占用的字节数（元数据 + 响应体）
+    fn entry_size(&self, key: &str) -> u64 {
+        [self.meta_path(key), self.body_path(key)]
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    fn read_meta(&self, key: &str) -> Option<CacheMeta> {
+        let content = fs::read_to_string(self.meta_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write_meta(&self, key: &str, meta: &CacheMeta) -> Result<(), String> {
+        let content = serde_json::to_string(meta).map_err(|e| format!("序列化缓存失败: {}", e))?;
+        fs::write(self.meta_path(key), content).map_err(|e| format!("写入缓存失败: {}", e))
+    }
+
+    pub fn lookup(&self, key: &str) -> Option<CachedResponse> {
+        let meta = self.read_meta(key)?;
+        let bytes = fs::read(self.body_path(key)).ok()?;
+        let etag = first_header(&meta.headers, "etag").map(str::to_string);
+        let last_modified = first_header(&meta.headers, "last-modified").map(str::to_string);
+        Some(CachedResponse {
+            fresh: now_secs() < meta.fresh_until,
+            etag,
+            last_modified,
+            response: RawResponse {
+                status: meta.status,
+                url: meta.url,
+                headers: meta.headers,
+                bytes,
+            },
+        })
+    }
+
+    // 保存 2xx 响应，ttl 不为空时忽略响应头直接使用该新鲜期；
+    // keep 为 true 时响应头不允许缓存也保存（立即过期），供离线模式使用
+    pub fn store(
+        &self,
+        key: &str,
+        method: &str,
+        response: &RawResponse,
+        ttl: Option<u64>,
+        keep: bool,
+    ) {
+        if !(200..300).contains(&response.status) {
+            return;
+        }
+        let freshness = match ttl {
+            Some(ttl) => ttl as i64,
+            None => match freshness_from_headers(&response.headers) {
+                Some(freshness) => freshness,
+                None if keep => 0,
+                None => return,
+            },
+        };
+
+        let old_size = self.entry_size(key);
+        let now = now_secs();
+        let meta = CacheMeta {
+            method: method.to_string(),
+            url: response.url.clone(),
+            status: response.status,
+            headers: cacheable_headers(&response.headers),
+            stored_at: now,
+            fresh_until: now + freshness,
+        };
+        let saved = fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("创建缓存目录失败: {}", e))
+            .and_then(|_| {
+                fs::write(self.body_path(key), &response.bytes)
+                    .map_err(|e| format!("写入缓存失败: {}", e))
+            })
+            .and_then(|_| self.write_meta(key, &meta));
+        if let Err(e) = saved {
+            eprintln!("{}", e);
+        }
+        self.update_usage(key, old_size, self.entry_size(key));
+    }
+
+    // 记录条目大小的变化，超过容量上限时删除最早保存（或重新验证）的条目
+    fn update_usage(&self, key: &str, old_size: u64, new_size: u64) {
+        let mut used = match self.used.lock() {
+            Ok(used) => used,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let total = match *used {
+            Some(total) => (total + new_size).saturating_sub(old_size),
+            None => self.info().size_bytes,
+        };
+        *used = Some(if total > self.max_bytes {
+            self.evict(key, total)
+        } else {
+            total
+        });
+    }
+
+    // 按元数据文件的修改时间从旧到新删除，保留刚保存的条目，返回删除后的占用
+    fn evict(&self, keep_key: &str, mut used: u64) -> u64 {
+        let mut entries: Vec<(SystemTime, String)> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    if path.extension().and_then(|ext| ext.to_str()) != Some(META_EXTENSION) {
+                        return None;
+                    }
+                    let key = path.file_stem()?.to_str()?.to_string();
+                    let modified = entry.metadata().ok()?.modified().ok()?;
+                    Some((modified, key))
+                })
+                .filter(|(_, key)| key != keep_key)
+                .collect(),
+            Err(_) => return used,
+        };
+        entries.sort();
+        for (_, key) in entries {
+            if used <= self.max_bytes {
+                break;
+            }
+            let size = self.entry_size(&key);
+            let _ = fs::remove_file(self.meta_path(&key));
+            let _ = fs::remove_file(self.body_path(&key));
+            used = used.saturating_sub(size);
+        }
+        used
+    }
+
+    // 收到 304 后更新新鲜期，合并新的响应头
+    pub fn refresh(&self, key: &str, headers: &HashMap<String, Vec<String>>, ttl: Option<u64>) {
+        let mut meta = match self.read_meta(key) {
+            Some(meta) => meta,
+            None => return,
+        };
+        for (name, values) in cacheable_headers(headers) {
+            if name != "content-length" && name != "content-type" {
+                meta.headers.insert(name, values);
+            }
+        }
+        let freshness = ttl
+            .map(|ttl| ttl as i64)
+            .or_else(|| freshness_from_headers(&meta.headers))
+            .unwrap_or(0);
+        meta.stored_at = now_secs();
+        meta.fresh_until = meta.stored_at + freshness;
+        if let Err(e) = self.write_meta(key, &meta) {
+            eprintln!("{}", e);
+        }
+    }
+
+    pub fn info(&self) -> HttpCacheInfo {
+        let mut info = HttpCacheInfo::default();
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|ext| ext.to_str()) == Some(META_EXTENSION) {
+                    info.entries += 1;
+                }
+                info.size_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+        info
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        if let Ok(mut used) = self.used.lock() {
+            *used = None;
+        }
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).map_err(|e| format!("清除缓存失败: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(url: &str) -> RawResponse {
+        RawResponse {
+            status: 200,
+            url: url.to_string(),
+            headers: HashMap::new(),
+            bytes: vec![b'x'; 1000],
+        }
+    }
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("http_cache_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn evicts_oldest_entries_over_limit() {
+        let dir = cache_dir("evict");
+        let probe = HttpCache::new(&dir);
+        probe.store("a", "GET", &response("https://a.test/1"), None, true);
+        let entry_size = probe.info().size_bytes;
+        probe.clear().unwrap();
+
+        // 只能容纳两个条目
+        let cache = HttpCache::with_max_bytes(&dir, entry_size * 2 + entry_size / 2);
+        for key in ["a", "b", "c"] {
+            cache.store(key, "GET", &response("https://a.test/1"), None, true);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(cache.lookup("a").is_none());
+        assert!(cache.lookup("b").is_some());
+        assert!(cache.lookup("c").is_some());
+        assert!(cache.info().size_bytes <= entry_size * 2 + entry_size / 2);
+
+        // 覆盖已有条目不重复计算占用
+        cache.store("c", "GET", &response("https://a.test/1"), None, true);
+        assert!(cache.lookup("b").is_some());
+    }
+
+    #[test]
+    fn offline_keep_stores_uncacheable_response() {
+        let cache = HttpCache::new(&cache_dir("keep"));
+        cache.store("a", "POST", &response("https://a.test/1"), None, false);
+        assert!(cache.lookup("a").is_none());
+        cache.store("a", "POST", &response("https://a.test/1"), None, true);
+        assert!(!cache.lookup("a").unwrap().fresh);
+    }
+
+    #[test]
+    fn key_depends_on_cookie() {
+        let client = reqwest::Client::new();
+        let request = |cookie: Option<&str>| {
+            let mut builder = client.get("https://music.163.com/api/playlist");
+            if let Some(cookie) = cookie {
+                builder = builder.header(COOKIE, cookie);
+            }
+            builder.build().unwrap()
+        };
+        let anonymous = HttpCache::key_for(&request(None), None);
+        let user_a = HttpCache::key_for(&request(Some("MUSIC_U=a")), None);
+        let user_b = HttpCache::key_for(&request(Some("MUSIC_U=b")), None);
+        assert_ne!(anonymous, user_a);
+        assert_ne!(user_a, user_b);
+        assert_eq!(
+            user_a,
+            HttpCache::key_for(&request(Some("MUSIC_U=a")), None)
+        );
+        // 自定义缓存键同样按 Cookie 区分
+        assert_ne!(
+            HttpCache::key_for(&request(Some("MUSIC_U=a")), Some("search")),
+            HttpCache::key_for(&request(Some("MUSIC_U=b")), Some("search"))
+        );
+    }
+}
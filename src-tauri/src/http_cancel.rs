@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// 请求被取消时返回的错误，前端据此区分取消和真正的失败
+pub const CANCELLED_ERROR: &str = "请求已取消";
+
+// 进行中的可取消请求，按前端传入的请求 id 登记
+#[derive(Default)]
+pub struct CancelRegistry {
+    requests: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+impl CancelRegistry {
+    // 登记请求，同一 id 的旧请求不再能被取消
+    pub fn register(&self, id: String) -> CancelGuard<'_> {
+        let notify = Arc::new(Notify::new());
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.insert(id.clone(), notify.clone());
+        }
+        CancelGuard {
+            registry: self,
+            id,
+            notify,
+        }
+    }
+
+    // 取消请求，返回是否找到了对应的请求
+    pub fn cancel(&self, id: &str) -> bool {
+        let notify = match self.requests.lock() {
+            Ok(mut requests) => requests.remove(id),
+            Err(_) => None,
+        };
+        match notify {
+            Some(notify) => {
+                // notify_one 在还没有等待者时也会保留通知
+                notify.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// 请求结束时自动从登记表中移除
+pub struct CancelGuard<'a> {
+    registry: &'a CancelRegistry,
+    id: String,
+    notify: Arc<Notify>,
+}
+
+impl CancelGuard<'_> {
+    // 运行请求，收到取消通知时丢弃请求的 future，连接随之中断
+    pub async fn run<T, F>(&self, future: F) -> Result<T, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        tokio::select! {
+            result = future => result,
+            _ = self.notify.notified() => Err(CANCELLED_ERROR.to_string()),
+        }
+    }
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut requests) = self.registry.requests.lock() {
+            // 同一 id 已被新请求占用时保留新请求
+            if requests
+                .get(&self.id)
+                .is_some_and(|notify| Arc::ptr_eq(notify, &self.notify))
+            {
+                requests.remove(&self.id);
+            }
+        }
+    }
+}
@@ -1,106 +1,906 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::header::{
+    HeaderMap, CONTENT_TYPE, COOKIE, IF_MODIFIED_SINCE, IF_NONE_MATCH, SET_COOKIE,
+};
+use reqwest::redirect::Policy as RedirectPolicy;
+use reqwest::{Client, ClientBuilder, Method, Request, RequestBuilder, Response, Url};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::sync::{Arc, RwLock};
+use tauri::State;
 
-#[tauri::command]
-pub async fn http_get_text(
-    url: &str,
-    header: HashMap<String, String>,
-    req_body: Option<HashMap<String, Value>>,
-) -> Result<String, String> {
-    // 创建HTTP客户端
-    let client = reqwest::Client::new();
-    // 创建请求
-    let mut request = client.get(url);
+use crate::charset::decode_text;
+use crate::http_cache::{HttpCache, HttpCacheInfo, RawResponse};
+use crate::http_cancel::CancelRegistry;
+use crate::http_cookies::{parse_cookie_file, CookieJar, StoredCookie};
+use crate::http_fixtures::{FixtureMode, FixtureStore};
+use crate::http_limiter::HostLimiter;
+use crate::http_policy::{sanitize_headers, PolicyResolver, RequestPolicy};
+use crate::http_proxy::ProxySettings;
+use crate::http_retry::{is_idempotent, is_retryable_status, retry_after};
+use crate::http_settings::HttpSettings;
+use crate::platform::platform_for_host;
 
-    // 添加查询参数 - 只有当req_body存在时才处理
-    if let Some(body) = req_body {
-        for (key, value) in &body {
-            // 将任何类型的值转换为字符串
-            let value_str = match value {
-                Value::String(s) => s.to_string(),
-                Value::Number(n) => n.to_string(),
-                Value::Bool(b) => b.to_string(),
-                _ => value.to_string(), // 对于其他类型，使用JSON字符串表示
+const HTTP_SETTINGS_FILE_NAME: &str = "http_settings.json";
+// 与 reqwest 默认的重定向次数上限一致
+const MAX_REDIRECTS: usize = 10;
+
+// 请求体，前端传入 { kind, data }
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "lowercase")]
+pub enum HttpBody {
+    // JSON 对象或数组
+    Json(Value),
+    // application/x-www-form-urlencoded 表单
+    Form(HashMap<String, Value>),
+    // 纯文本
+    Text(String),
+    // 二进制数据，使用 base64 编码传入
+    Bytes(String),
+}
+
+// 响应体的返回形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseType {
+    // 按字符集解码后的文本
+    #[default]
+    Text,
+    // base64 编码的原始字节
+    Bytes,
+}
+
+// 通用请求参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // 查询参数
+    #[serde(default)]
+    pub query: Option<HashMap<String, Value>>,
+    #[serde(default)]
+    pub body: Option<HttpBody>,
+    #[serde(default)]
+    pub response_type: ResponseType,
+    // 指定响应文本的编码（如 gbk、big5），为空时自动检测
+    #[serde(default)]
+    pub charset: Option<String>,
+    // 为 true 时非 2xx 响应也正常返回，由调用方自行处理
+    #[serde(default)]
+    pub allow_error_status: bool,
+    // 覆盖设置中的最多重试次数
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    // 声明请求可以安全重试；默认只有 GET、PUT、DELETE 等幂等方法会重试
+    #[serde(default)]
+    pub idempotent: Option<bool>,
+    // 缓存有效期（秒），忽略响应头的缓存策略；设置后非 GET 请求也会缓存，为 0 时不使用缓存
+    #[serde(default)]
+    pub cache_ttl: Option<u64>,
+    // 自定义缓存键，用于请求体每次都不同（如带随机加密参数）但结果相同的请求
+    #[serde(default)]
+    pub cache_key: Option<String>,
+    // 请求 id，用于通过 cancel_request 取消进行中的请求
+    #[serde(default)]
+    pub request_id: Option<String>,
+    // 为 true 时即使响应不允许缓存也保存，离线模式下使用
+    #[serde(default)]
+    pub offline_cache: bool,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl HttpRequest {
+    pub(crate) fn new(method: &str, url: &str) -> Self {
+        HttpRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+            query: None,
+            body: None,
+            response_type: ResponseType::Text,
+            charset: None,
+            allow_error_status: false,
+            max_retries: None,
+            idempotent: None,
+            cache_ttl: None,
+            cache_key: None,
+            request_id: None,
+            offline_cache: false,
+        }
+    }
+
+    // 默认只缓存 GET 请求，指定缓存有效期或需要离线使用时任意方法都可以缓存
+    fn cacheable(&self) -> bool {
+        match self.cache_ttl {
+            Some(ttl) => ttl > 0,
+            None => self.offline_cache || self.method.trim().eq_ignore_ascii_case("GET"),
+        }
+    }
+}
+
+// 通用请求的响应
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    // 状态码是否为 2xx
+    pub ok: bool,
+    // 跟随重定向后的最终地址
+    pub url: String,
+    // 同名响应头（如 Set-Cookie）可能有多个值
+    pub headers: HashMap<String, Vec<String>>,
+    pub body: String,
+    pub response_type: ResponseType,
+}
+
+// 全局共享的 HTTP 客户端，复用连接池、TLS 会话和 DNS 缓存
+pub struct HttpClientState {
+    settings_file: PathBuf,
+    settings: RwLock<HttpSettings>,
+    client: RwLock<Client>,
+    limiter: RwLock<Arc<HostLimiter>>,
+    data_dir: PathBuf,
+    fixtures: RwLock<Arc<FixtureStore>>,
+    cookies: CookieJar,
+    cache: HttpCache,
+    cancels: CancelRegistry,
+}
+
+impl HttpClientState {
+    pub fn new(config_dir: &Path, data_dir: &Path, cache_dir: &Path) -> Self {
+        let settings_file = config_dir.join(HTTP_SETTINGS_FILE_NAME);
+        let settings = HttpSettings::load(&settings_file);
+        let client = build_client(&settings).unwrap_or_else(|e| {
+            eprintln!("{}，使用默认配置", e);
+            Client::new()
+        });
+        let limiter = HostLimiter::new(settings.rate_limit.clone());
+        let fixtures = FixtureStore::new(&settings.fixtures, data_dir);
+        HttpClientState {
+            settings_file,
+            settings: RwLock::new(settings),
+            client: RwLock::new(client),
+            limiter: RwLock::new(Arc::new(limiter)),
+            data_dir: data_dir.to_path_buf(),
+            fixtures: RwLock::new(Arc::new(fixtures)),
+            cookies: CookieJar::load(data_dir),
+            cache: HttpCache::new(cache_dir),
+            cancels: CancelRegistry::default(),
+        }
+    }
+
+    // Client 内部是引用计数，克隆开销很小
+    pub fn client(&self) -> Client {
+        match self.client.read() {
+            Ok(client) => client.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    fn limiter(&self) -> Arc<HostLimiter> {
+        match self.limiter.read() {
+            Ok(limiter) => limiter.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    fn fixtures(&self) -> Arc<FixtureStore> {
+        match self.fixtures.read() {
+            Ok(fixtures) => fixtures.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    // 替换录制/回放设置，只在测试中使用，设置更新后恢复为按设置创建
+    #[cfg(test)]
+    pub(crate) fn set_fixtures(&self, fixtures: FixtureStore) {
+        match self.fixtures.write() {
+            Ok(mut current) => *current = Arc::new(fixtures),
+            Err(e) => *e.into_inner() = Arc::new(fixtures),
+        }
+    }
+
+    pub fn settings(&self) -> HttpSettings {
+        match self.settings.read() {
+            Ok(settings) => settings.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    // 更新设置：重新创建客户端并保存到配置文件
+    pub fn update_settings(&self, settings: HttpSettings) -> Result<(), String> {
+        let client = build_client(&settings)?;
+        settings.save(&self.settings_file)?;
+        *self.client.write().map_err(|e| e.to_string())? = client;
+        *self.limiter.write().map_err(|e| e.to_string())? =
+            Arc::new(HostLimiter::new(settings.rate_limit.clone()));
+        *self.fixtures.write().map_err(|e| e.to_string())? =
+            Arc::new(FixtureStore::new(&settings.fixtures, &self.data_dir));
+        *self.settings.write().map_err(|e| e.to_string())? = settings;
+        Ok(())
+    }
+
+    // 按请求参数构造 reqwest 请求
+    fn build_request(&self, request: &mut HttpRequest) -> Result<RequestBuilder, String> {
+        let method = Method::from_bytes(request.method.trim().to_uppercase().as_bytes())
+            .map_err(|_| format!("不支持的请求方法: {}", request.method))?;
+        let url = Url::parse(&request.url).map_err(|e| format!("无效的请求地址: {}", e))?;
+        self.settings().policy.check_url(&url)?;
+        sanitize_headers(&mut request.headers)?;
+        let mut builder = self.client().request(method, url.clone());
+
+        let has_content_type = request
+            .headers
+            .keys()
+            .any(|key| key.eq_ignore_ascii_case(CONTENT_TYPE.as_str()));
+        // 添加请求头，Cookie 单独处理
+        let mut caller_cookie = None;
+        for (key, value) in &request.headers {
+            if key.eq_ignore_ascii_case(COOKIE.as_str()) {
+                caller_cookie = Some(value.clone());
+            } else {
+                builder = builder.header(key, value);
+            }
+        }
+        // 调用方传入的 Cookie 在前，Cookie 存储中匹配的在后
+        let cookie = match (caller_cookie, self.cookies.header_for(&url)) {
+            (Some(caller), Some(stored)) => Some(format!("{}; {}", caller, stored)),
+            (caller, stored) => caller.or(stored),
+        };
+        if let Some(cookie) = cookie {
+            builder = builder.header(COOKIE, cookie);
+        }
+        // 添加查询参数
+        builder = builder.query(&to_query_pairs(request.query.take()));
+        // 添加请求体
+        if let Some(body) = request.body.take() {
+            builder = apply_body(builder, body, has_content_type)?;
+        }
+        Ok(builder)
+    }
+
+    // 构造下载请求，应用安全策略、请求头和 Cookie；下载时间不固定，不设置总超时
+    pub(crate) async fn download_request(
+        &self,
+        url: &str,
+        headers: HashMap<String, String>,
+    ) -> Result<RequestBuilder, String> {
+        if self.settings().offline {
+            return Err("离线模式下无法下载".to_string());
+        }
+        let mut request = HttpRequest::new("GET", url);
+        request.headers = headers;
+        let builder = self.build_request(&mut request)?;
+        if let Ok(url) = Url::parse(url) {
+            self.settings().policy.check_resolved(&url).await?;
+        }
+        Ok(builder)
+    }
+
+    pub(crate) fn cancels(&self) -> &CancelRegistry {
+        &self.cancels
+    }
+
+    // 发送请求并读取完整响应，带请求 id 的请求可以被取消
+    pub async fn execute(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        match request.request_id.clone() {
+            Some(id) => {
+                let guard = self.cancels.register(id);
+                guard.run(self.execute_cached(request)).await
+            }
+            None => self.execute_cached(request).await,
+        }
+    }
+
+    // 可缓存的请求优先使用磁盘缓存，过期的缓存通过条件请求重新验证
+    async fn execute_cached(&self, mut request: HttpRequest) -> Result<HttpResponse, String> {
+        let mut builder = self.build_request(&mut request)?;
+        if let Some(total) = self.settings().timeouts.total() {
+            builder = builder.timeout(total);
+        }
+        let fixtures = self.fixtures();
+        // 录制和回放时不使用缓存，保证每个请求都有对应的录制文件
+        if fixtures.mode() == FixtureMode::Replay {
+            return self.replay(builder, &request, &fixtures);
+        }
+
+        // 离线模式下任何请求都只查找缓存，过期的缓存也直接使用
+        let offline = self.settings().offline;
+        let cache_key = if request.cacheable() || offline {
+            built_request(&builder)
+                .map(|built| HttpCache::key_for(&built, request.cache_key.as_deref()))
+        } else {
+            None
+        };
+        let cached = cache_key.as_deref().and_then(|key| self.cache.lookup(key));
+        if offline {
+            return match cached {
+                Some(cached) => finish_response(cached.response, &request),
+                None => Err(format!("离线模式下没有缓存: {}", request.url)),
             };
-            request = request.query(&[(key, value_str)]);
         }
+
+        if let Ok(url) = Url::parse(&request.url) {
+            self.settings().policy.check_resolved(&url).await?;
+        }
+        if fixtures.mode() == FixtureMode::Record {
+            return self.record(builder, &request, &fixtures).await;
+        }
+
+        if let Some(cached) = &cached {
+            if cached.fresh {
+                return finish_response(cached.response.clone(), &request);
+            }
+            if let Some(etag) = &cached.etag {
+                builder = builder.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                builder = builder.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let result = self.send_with_retry(builder, &request).await;
+        let raw = match (result, cached, cache_key) {
+            // 服务端确认缓存仍然有效
+            (Ok(raw), Some(cached), Some(key)) if raw.status == 304 && cached.can_revalidate() => {
+                self.cache.refresh(&key, &raw.headers, request.cache_ttl);
+                cached.response
+            }
+            (Ok(raw), _, key) => {
+                if let Some(key) = key {
+                    self.cache.store(
+                        &key,
+                        &request.method,
+                        &raw,
+                        request.cache_ttl,
+                        request.offline_cache,
+                    );
+                }
+                raw
+            }
+            // 网络不可用时返回过期的缓存
+            (Err(e), Some(cached), _) => {
+                eprintln!("{}，使用过期的缓存", e);
+                cached.response
+            }
+            (Err(e), None, _) => return Err(e),
+        };
+        finish_response(raw, &request)
     }
 
-    // 添加请求头
-    for (key, value) in header {
-        request = request.header(key, value);
+    // 使用录制的响应代替网络请求
+    fn replay(
+        &self,
+        builder: RequestBuilder,
+        request: &HttpRequest,
+        fixtures: &FixtureStore,
+    ) -> Result<HttpResponse, String> {
+        let built = built_request(&builder).ok_or("无法读取请求内容")?;
+        let key = HttpCache::key_for(&built, request.cache_key.as_deref());
+        let raw = fixtures.replay(built.method().as_str(), built.url().as_str(), &key)?;
+        finish_response(raw, request)
     }
 
-    // 发送请求
-    match request.send().await {
-        Ok(response) => {
-            // 检查响应状态
-            if response.status().is_success() {
-                // 使用text_with_charset确保中文正确显示
-                match response.text_with_charset("utf-8").await {
-                    Ok(text) => Ok(text), // 直接返回文本
-                    Err(e) => {
-                        eprintln!("读取响应失败: {}", e);
-                        Err(format!("读取响应失败: {}", e))
+    // 正常发送请求，并保存请求和响应
+    async fn record(
+        &self,
+        builder: RequestBuilder,
+        request: &HttpRequest,
+        fixtures: &FixtureStore,
+    ) -> Result<HttpResponse, String> {
+        let built = built_request(&builder).ok_or("无法读取请求内容")?;
+        let raw = self.send_with_retry(builder, request).await?;
+        fixtures.record(
+            built.method().as_str(),
+            built.url().as_str(),
+            built.body().and_then(|body| body.as_bytes()),
+            &HttpCache::key_for(&built, request.cache_key.as_deref()),
+            &raw,
+        );
+        finish_response(raw, request)
+    }
+
+    // 发送请求，失败时按重试策略重试，按设置应用总超时和读取超时
+    async fn send_with_retry(
+        &self,
+        builder: RequestBuilder,
+        request: &HttpRequest,
+    ) -> Result<RawResponse, String> {
+        let settings = self.settings();
+        let retry = settings.retry.clone();
+        let max_retries = request.max_retries.unwrap_or(retry.max_retries);
+        let can_retry = request.idempotent.unwrap_or_else(|| {
+            Method::from_bytes(request.method.trim().to_uppercase().as_bytes())
+                .is_ok_and(|method| is_idempotent(&method))
+        });
+        let host = Url::parse(&request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+
+        let mut pending = Some(builder);
+        let mut attempt = 0;
+        loop {
+            let current = pending.take().ok_or("无法重试请求")?;
+            // 保留一份请求用于重试，请求体无法复制时不重试
+            pending = current.try_clone();
+            let retries_left = can_retry && pending.is_some() && attempt < max_retries;
+
+            let permit = self.limiter().acquire(&host).await;
+            let result = self.send_once(current, &settings).await;
+            drop(permit);
+
+            let delay = match &result {
+                Ok(response) if retries_left && is_retryable_status(response.status) => {
+                    match retry_after(&response.headers) {
+                        // 服务端要求等待的时间太长时直接返回
+                        Some(wait) if wait > retry.max_delay() => None,
+                        Some(wait) => Some(wait),
+                        None => Some(retry.backoff(attempt)),
                     }
                 }
+                Err(_) if retries_left => Some(retry.backoff(attempt)),
+                _ => None,
+            };
+
+            match delay {
+                Some(delay) => {
+                    eprintln!(
+                        "请求 {} 失败，{} 毫秒后第 {} 次重试",
+                        request.url,
+                        delay.as_millis(),
+                        attempt + 1
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return result,
+            }
+        }
+    }
+
+    // 发送一次请求并读取完整响应，不检查状态码
+    async fn send_once(
+        &self,
+        builder: RequestBuilder,
+        settings: &HttpSettings,
+    ) -> Result<RawResponse, String> {
+        // 发送请求
+        let response = match builder.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("发送请求失败: {}", e);
+                return Err(format!("发送请求失败: {}", e));
+            }
+        };
+
+        let status = response.status();
+        let url = response.url().to_string();
+        let headers = collect_headers(response.headers());
+        // 非 2xx 响应中的 Set-Cookie 同样需要保存
+        if let Some(set_cookies) = headers.get(SET_COOKIE.as_str()) {
+            self.cookies
+                .store_response_cookies(response.url(), set_cookies);
+        }
+
+        let bytes = read_body(response, settings.timeouts.read())
+            .await
+            .map_err(|e| {
+                eprintln!("读取响应失败: {}", e);
+                format!("读取响应失败: {}", e)
+            })?;
+
+        Ok(RawResponse {
+            status: status.as_u16(),
+            url,
+            headers,
+            bytes,
+        })
+    }
+}
+
+// 复制出完整的请求，用于计算缓存键和录制；请求体为流时返回空
+fn built_request(builder: &RequestBuilder) -> Option<Request> {
+    builder.try_clone().and_then(|builder| builder.build().ok())
+}
+
+// 按请求的返回形式解码响应体，并检查响应状态
+fn finish_response(raw: RawResponse, request: &HttpRequest) -> Result<HttpResponse, String> {
+    let ok = (200..300).contains(&raw.status);
+    if !ok && !request.allow_error_status {
+        return Err(format!("请求失败，状态码: {}", raw.status));
+    }
+
+    let body = match request.response_type {
+        ResponseType::Text => {
+            let charset = response_charset(&raw.headers);
+            decode_text(&raw.bytes, charset.as_deref(), request.charset.as_deref())
+        }
+        ResponseType::Bytes => STANDARD.encode(&raw.bytes),
+    };
+    Ok(HttpResponse {
+        status: raw.status,
+        ok,
+        url: raw.url,
+        headers: raw.headers,
+        body,
+        response_type: request.response_type,
+    })
+}
+
+fn build_client(settings: &HttpSettings) -> Result<Client, String> {
+    let mut builder = Client::builder();
+    if let Some(connect) = settings.timeouts.connect() {
+        builder = builder.connect_timeout(connect);
+    }
+    if let Some(proxy) = settings.proxy.build()? {
+        builder = builder.proxy(proxy);
+    }
+    let trusted_hosts = if settings.proxy.enabled {
+        settings.proxy.host().into_iter().collect()
+    } else {
+        Vec::new()
+    };
+    apply_policy(builder, &settings.policy, trusted_hosts)
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))
+}
+
+// 重定向的目标地址同样需要符合安全策略，域名解析到内网地址时拒绝连接
+fn apply_policy(
+    builder: ClientBuilder,
+    policy: &RequestPolicy,
+    trusted_hosts: Vec<String>,
+) -> ClientBuilder {
+    let resolver = PolicyResolver::new(policy, trusted_hosts);
+    let policy = policy.clone();
+    builder
+        .dns_resolver(Arc::new(resolver))
+        .redirect(RedirectPolicy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("重定向次数过多")
+            } else if let Err(e) = policy.check_url(attempt.url()) {
+                attempt.error(e)
             } else {
-                Err(format!("请求失败，状态码: {}", response.status()))
+                attempt.follow()
             }
+        }))
+}
+
+// 逐块读取响应体，两次收到数据的间隔超过 read_timeout 时中止
+async fn read_body(
+    mut response: Response,
+    read_timeout: Option<std::time::Duration>,
+) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let chunk = match read_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+                .await
+                .map_err(|_| "读取响应超时".to_string())?,
+            None => response.chunk().await,
+        };
+        match chunk.map_err(|e| e.to_string())? {
+            Some(chunk) => body.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    Ok(body)
+}
+
+// 响应头转换为 名称 -> 值列表，名称为小写
+fn collect_headers(headers: &HeaderMap) -> HashMap<String, Vec<String>> {
+    let mut result: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        result
+            .entry(name.as_str().to_string())
+            .or_default()
+            .push(value);
+    }
+    result
+}
+
+// 从 Content-Type 中取出 charset
+fn response_charset(headers: &HashMap<String, Vec<String>>) -> Option<String> {
+    let content_type = headers.get(CONTENT_TYPE.as_str())?.first()?;
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+// 将任何类型的值转换为字符串
+fn value_to_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => value.to_string(), // 对于其他类型，使用JSON字符串表示
+    }
+}
+
+// 将请求参数转换为查询字符串参数，按名称排序，相同的参数总是得到相同的地址和缓存键
+fn to_query_pairs(req_body: Option<HashMap<String, Value>>) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = req_body
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(key, value)| (key, value_to_string(value)))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+// 按请求体类型编码，调用方没有指定 Content-Type 时设置对应的默认值
+fn apply_body(
+    request: RequestBuilder,
+    body: HttpBody,
+    has_content_type: bool,
+) -> Result<RequestBuilder, String> {
+    let with_default_type = |request: RequestBuilder, content_type: &str| {
+        if has_content_type {
+            request
+        } else {
+            request.header(CONTENT_TYPE, content_type)
+        }
+    };
+
+    match body {
+        HttpBody::Json(value) => {
+            let json =
+                serde_json::to_vec(&value).map_err(|e| format!("序列化请求体失败: {}", e))?;
+            Ok(with_default_type(request, "application/json").body(json))
+        }
+        HttpBody::Form(fields) => {
+            let pairs = to_query_pairs(Some(fields));
+            let form =
+                serde_urlencoded::to_string(&pairs).map_err(|e| format!("编码表单失败: {}", e))?;
+            Ok(with_default_type(request, "application/x-www-form-urlencoded").body(form))
         }
-        Err(e) => {
-            eprintln!("发送请求失败: {}", e);
-            Err(format!("发送请求失败: {}", e))
+        HttpBody::Text(text) => {
+            Ok(with_default_type(request, "text/plain; charset=utf-8").body(text))
+        }
+        HttpBody::Bytes(data) => {
+            let bytes = STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("请求体不是有效的 base64: {}", e))?;
+            Ok(with_default_type(request, "application/octet-stream").body(bytes))
         }
     }
 }
 
+#[tauri::command]
+pub async fn http_get_text(
+    http: State<'_, HttpClientState>,
+    url: &str,
+    header: HashMap<String, String>,
+    req_body: Option<HashMap<String, Value>>,
+    charset: Option<String>,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    let mut request = HttpRequest::new("GET", url);
+    request.headers = header;
+    // 添加查询参数 - 只有当req_body存在时才处理
+    request.query = req_body;
+    request.charset = charset;
+    request.request_id = request_id;
+
+    Ok(http.execute(request).await?.body)
+}
+
+// req_body 为查询参数，请求体通过 body 传入
 #[tauri::command]
 pub async fn http_post_text(
+    http: State<'_, HttpClientState>,
     url: &str,
     header: HashMap<String, String>,
     req_body: Option<HashMap<String, Value>>,
+    body: Option<HttpBody>,
+    charset: Option<String>,
+    request_id: Option<String>,
 ) -> Result<String, String> {
-    let client = reqwest::Client::new();
-    let mut request = client.post(url);
-    // 添加请求头
-    for (key, value) in header {
-        request = request.header(key, value);
+    let mut request = HttpRequest::new("POST", url);
+    request.headers = header;
+    request.query = req_body;
+    request.body = body;
+    request.charset = charset;
+    request.request_id = request_id;
+
+    Ok(http.execute(request).await?.body)
+}
+
+// 通用请求：支持任意方法，返回状态码、最终地址、响应头和响应体
+#[tauri::command]
+pub async fn http_request(
+    http: State<'_, HttpClientState>,
+    request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    http.execute(request).await
+}
+
+// 取消进行中的请求，被取消的请求返回 CANCELLED_ERROR；返回是否找到了该请求
+#[tauri::command]
+pub fn cancel_request(http: State<'_, HttpClientState>, id: String) -> bool {
+    http.cancels.cancel(&id)
+}
+
+#[tauri::command]
+pub fn get_http_settings(http: State<'_, HttpClientState>) -> HttpSettings {
+    http.settings()
+}
+
+#[tauri::command]
+pub fn set_http_settings(
+    http: State<'_, HttpClientState>,
+    settings: HttpSettings,
+) -> Result<(), String> {
+    http.update_settings(settings)
+}
+
+// 代理测试结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyTestResult {
+    pub status: u16,
+    // 从发出请求到收到响应头的耗时
+    pub latency_ms: u64,
+}
+
+const PROXY_TEST_URL: &str = "https://music.163.com/";
+
+// 通过指定的代理访问测试地址（忽略代理规则），检查代理是否可用
+#[tauri::command]
+pub async fn test_proxy(
+    http: State<'_, HttpClientState>,
+    proxy: ProxySettings,
+    url: Option<String>,
+) -> Result<ProxyTestResult, String> {
+    let settings = http.settings();
+    let timeouts = settings.timeouts;
+    let mut builder = Client::builder().proxy(proxy.build_unconditional()?);
+    if let Some(connect) = timeouts.connect() {
+        builder = builder.connect_timeout(connect);
     }
-    // 添加查询参数 - 只有当req_body存在时才处理
-    if let Some(body) = req_body {
-        for (key, value) in &body {
-            // 将任何类型的值转换为字符串
-            let value_str = match value {
-                Value::String(s) => s.to_string(),
-                Value::Number(n) => n.to_string(),
-                Value::Bool(b) => b.to_string(),
-                _ => value.to_string(), // 对于其他类型，使用JSON字符串表示
-            };
-            request = request.query(&[(key, value_str)]);
-        }
-    }
-    // 发送请求
-    match request.send().await {
-        Ok(response) => {
-            // 检查响应状态
-            if response.status().is_success() {
-                // 使用text_with_charset确保中文正确显示
-                match response.text_with_charset("utf-8").await {
-                    Ok(text) => Ok(text), // 直接返回文本
-                    Err(e) => {
-                        eprintln!("读取响应失败: {}", e);
-                        Err(format!("读取响应失败: {}", e))
-                    }
-                }
-            } else {
-                Err(format!("请求失败，状态码: {}", response.status()))
-            }
-        }
-        Err(e) => {
-            eprintln!("发送请求失败: {}", e);
-            Err(format!("发送请求失败: {}", e))
-        }
+    if let Some(total) = timeouts.total() {
+        builder = builder.timeout(total);
+    }
+    let client = apply_policy(
+        builder,
+        &settings.policy,
+        proxy.host().into_iter().collect(),
+    )
+    .build()
+    .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+
+    let url = Url::parse(url.as_deref().unwrap_or(PROXY_TEST_URL))
+        .map_err(|e| format!("无效的测试地址: {}", e))?;
+    settings.policy.check_url(&url)?;
+    settings.policy.check_resolved(&url).await?;
+
+    let started = std::time::Instant::now();
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("代理连接失败: {}", e))?;
+    Ok(ProxyTestResult {
+        status: response.status().as_u16(),
+        latency_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+// 平台名称同时作为文件名，只允许字母、数字、下划线和连字符
+fn check_platform(platform: &str) -> Result<(), String> {
+    let valid = !platform.is_empty()
+        && platform
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("无效的平台名称: {}", platform))
+    }
+}
+
+#[tauri::command]
+pub fn list_cookies(
+    http: State<'_, HttpClientState>,
+    platform: String,
+) -> Result<Vec<StoredCookie>, String> {
+    check_platform(&platform)?;
+    Ok(http.cookies.list(&platform))
+}
+
+// 导入浏览器导出的 Cookie 文件，只保留属于该平台域名的 Cookie
+#[tauri::command]
+pub fn import_cookies(
+    http: State<'_, HttpClientState>,
+    platform: String,
+    file_path: String,
+) -> Result<usize, String> {
+    check_platform(&platform)?;
+    let content =
+        std::fs::read_to_string(&file_path).map_err(|e| format!("读取 Cookie 文件失败: {}", e))?;
+    let cookies: Vec<StoredCookie> = parse_cookie_file(&content)?
+        .into_iter()
+        .filter(|cookie| platform_for_host(&cookie.domain) == platform)
+        .collect();
+    if cookies.is_empty() {
+        return Err("文件中没有该平台的 Cookie".to_string());
+    }
+    http.cookies.insert(&platform, cookies)
+}
+
+#[tauri::command]
+pub fn get_http_cache_info(http: State<'_, HttpClientState>) -> HttpCacheInfo {
+    http.cache.info()
+}
+
+#[tauri::command]
+pub fn clear_http_cache(http: State<'_, HttpClientState>) -> Result<(), String> {
+    http.cache.clear()
+}
+
+#[tauri::command]
+pub fn clear_cookies(http: State<'_, HttpClientState>, platform: String) -> Result<(), String> {
+    check_platform(&platform)?;
+    http.cookies.clear(&platform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params() -> HashMap<String, Value> {
+        [
+            ("keyword", json!("晴天")),
+            ("page", json!(1)),
+            ("pagesize", json!(30)),
+            ("format", json!("json")),
+            ("showtype", json!(true)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+    }
+
+    #[test]
+    fn query_pairs_are_sorted() {
+        let pairs = to_query_pairs(Some(params()));
+        let keys: Vec<&str> = pairs.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["format", "keyword", "page", "pagesize", "showtype"]);
+        assert_eq!(pairs[2], ("page".to_string(), "1".to_string()));
+    }
+
+    #[test]
+    fn same_params_give_same_cache_key() {
+        let client = Client::new();
+        let build = || {
+            client
+                .post("https://www.kugou.com/search")
+                .query(&to_query_pairs(Some(params())))
+        };
+        let keys: Vec<String> = (0..8)
+            .map(|_| {
+                let request = apply_body(build(), HttpBody::Form(params()), false)
+                    .unwrap()
+                    .build()
+                    .unwrap();
+                HttpCache::key_for(&request, None)
+            })
+            .collect();
+        assert!(keys.iter().all(|key| *key == keys[0]));
     }
 }
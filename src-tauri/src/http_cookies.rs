@@ -0,0 +1,422 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::platform::{domain_match, platform_for_host};
+
+const COOKIES_DIR_NAME: &str = "cookies";
+// 常见的多级公共后缀，不允许通过 Domain 为这些域名设置 Cookie；单级域名（如 com）同样视为公共后缀
+const PUBLIC_SUFFIXES: [&str; 24] = [
+    "com.cn", "net.cn", "org.cn", "gov.cn", "edu.cn", "ac.cn", "com.hk", "net.hk", "org.hk",
+    "com.tw", "net.tw", "org.tw", "com.mo", "co.jp", "ne.jp", "or.jp", "co.kr", "or.kr", "co.uk",
+    "org.uk", "com.au", "net.au", "com.sg", "com.my",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    // 不带前导点的域名
+    pub domain: String,
+    // 为 true 时只发送给完全相同的主机，不包括子域名
+    #[serde(default)]
+    pub host_only: bool,
+    #[serde(default = "default_path")]
+    pub path: String,
+    // 过期时间（Unix 秒），为空表示会话 Cookie
+    #[serde(default)]
+    pub expires: Option<i64>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    // 用户导入的 Cookie，没有过期时间时也持久化，否则重启后需要重新导入
+    #[serde(default)]
+    pub imported: bool,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+        let path = url.path();
+        let path_matches = path == self.path
+            || (path.starts_with(&self.path)
+                && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+        domain_matches && path_matches && (!self.secure || url.scheme() == "https")
+    }
+
+    fn same_identity(&self, other: &StoredCookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+// 按平台持久化的 Cookie 存储，保存在应用数据目录的 cookies 目录下
+pub struct CookieJar {
+    dir: PathBuf,
+    platforms: Mutex<HashMap<String, Vec<StoredCookie>>>,
+}
+
+impl CookieJar {
+    pub fn load(data_dir: &Path) -> Self {
+        let dir = data_dir.join(COOKIES_DIR_NAME);
+        let mut platforms = HashMap::new();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+                let platform = match path.file_stem().and_then(|stem| stem.to_str()) {
+                    Some(stem) => stem.to_string(),
+                    None => continue,
+                };
+                match fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| {
+                        serde_json::from_str::<Vec<StoredCookie>>(&content)
+                            .map_err(|e| e.to_string())
+                    }) {
+                    Ok(cookies) => {
+                        platforms.insert(platform, cookies);
+                    }
+                    Err(e) => eprintln!("读取 Cookie 文件失败 {:?}: {}", path, e),
+                }
+            }
+        }
+        CookieJar {
+            dir,
+            platforms: Mutex::new(platforms),
+        }
+    }
+
+    fn save_platform(&self, platform: &str, cookies: &[StoredCookie]) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| format!("创建 Cookie 目录失败: {}", e))?;
+        // 响应设置的会话 Cookie 不持久化
+        let persistent: Vec<&StoredCookie> = cookies
+            .iter()
+            .filter(|cookie| cookie.expires.is_some() || cookie.imported)
+            .collect();
+        let content = serde_json::to_string_pretty(&persistent)
+            .map_err(|e| format!("序列化 Cookie 失败: {}", e))?;
+        fs::write(self.dir.join(format!("{}.json", platform)), content)
+            .map_err(|e| format!("保存 Cookie 失败: {}", e))
+    }
+
+    // 生成请求应携带的 Cookie 请求头，没有匹配的 Cookie 时返回空
+    pub fn header_for(&self, url: &Url) -> Option<String> {
+        let now = now_secs();
+        let platforms = self.platforms.lock().ok()?;
+        let mut matched: Vec<&StoredCookie> = platforms
+            .values()
+            .flatten()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .collect();
+        if matched.is_empty() {
+            return None;
+        }
+        // 路径更长的 Cookie 排在前面
+        matched.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        Some(
+            matched
+                .iter()
+                .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+                .collect::<Vec<_>>()
+                .join("; "),
+        )
+    }
+
+    // 保存响应中的 Set-Cookie
+    pub fn store_response_cookies(&self, url: &Url, set_cookies: &[String]) {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return,
+        };
+        let cookies: Vec<StoredCookie> = set_cookies
+            .iter()
+            .filter_map(|header| parse_set_cookie(header, &host, url.path()))
+            .collect();
+        if cookies.is_empty() {
+            return;
+        }
+        if let Err(e) = self.insert(platform_for_host(&host), cookies) {
+            eprintln!("{}", e);
+        }
+    }
+
+    // 写入 Cookie，同名同域同路径的旧值被替换，已过期的被删除
+    pub fn insert(&self, platform: &str, cookies: Vec<StoredCookie>) -> Result<usize, String> {
+        let now = now_secs();
+        let mut platforms = self
+            .platforms
+            .lock()
+            .map_err(|e| format!("写入 Cookie 失败: {}", e))?;
+        let jar = platforms.entry(platform.to_string()).or_default();
+        let count = cookies.len();
+        for cookie in cookies {
+            jar.retain(|existing| !existing.same_identity(&cookie));
+            if !cookie.is_expired(now) {
+                jar.push(cookie);
+            }
+        }
+        jar.retain(|cookie| !cookie.is_expired(now));
+        self.save_platform(platform, jar)?;
+        Ok(count)
+    }
+
+    pub fn list(&self, platform: &str) -> Vec<StoredCookie> {
+        let now = now_secs();
+        self.platforms
+            .lock()
+            .ok()
+            .and_then(|platforms| platforms.get(platform).cloned())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .collect()
+    }
+
+    pub fn clear(&self, platform: &str) -> Result<(), String> {
+        let mut platforms = self
+            .platforms
+            .lock()
+            .map_err(|e| format!("清除 Cookie 失败: {}", e))?;
+        platforms.remove(platform);
+        let file = self.dir.join(format!("{}.json", platform));
+        if file.exists() {
+            fs::remove_file(&file).map_err(|e| format!("删除 Cookie 文件失败: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+// 解析 Set-Cookie 响应头
+fn parse_set_cookie(header: &str, host: &str, request_path: &str) -> Option<StoredCookie> {
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = StoredCookie {
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+        domain: host.to_string(),
+        host_only: true,
+        path: default_cookie_path(request_path),
+        expires: None,
+        secure: false,
+        http_only: false,
+        imported: false,
+    };
+    let mut max_age: Option<i64> = None;
+
+    for attribute in parts {
+        let (key, value) = match attribute.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (attribute.trim(), ""),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "domain" => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                // 不允许为其他站点设置 Cookie
+                if domain.is_empty() || !domain_match(host, &domain) {
+                    return None;
+                }
+                // Domain 为公共后缀时，只有与请求主机相同才作为仅限该主机的 Cookie 保存
+                if is_public_suffix(&domain) {
+                    if domain != host {
+                        return None;
+                    }
+                    continue;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "expires" => {
+                if let Ok(time) = httpdate::parse_http_date(value) {
+                    cookie.expires = time
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs() as i64)
+                        .ok()
+                        .or(Some(0));
+                }
+            }
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            _ => {}
+        }
+    }
+
+    // Max-Age 优先于 Expires
+    if let Some(max_age) = max_age {
+        cookie.expires = Some(now_secs() + max_age.max(0));
+    }
+    Some(cookie)
+}
+
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || PUBLIC_SUFFIXES.contains(&domain)
+}
+
+// 未指定 Path 时取请求路径的目录部分
+fn default_cookie_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(pos) => request_path[..pos].to_string(),
+    }
+}
+
+// 解析浏览器导出的 Cookie 文件，支持 Netscape cookies.txt 和 JSON 数组两种格式
+pub fn parse_cookie_file(content: &str) -> Result<Vec<StoredCookie>, String> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') {
+        parse_json_cookies(trimmed)
+    } else {
+        Ok(parse_netscape_cookies(content))
+    }
+}
+
+// 浏览器扩展导出的 JSON 格式
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedCookie {
+    name: String,
+    value: String,
+    domain: String,
+    #[serde(default)]
+    host_only: bool,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    expiration_date: Option<f64>,
+    #[serde(default)]
+    secure: bool,
+    #[serde(default)]
+    http_only: bool,
+}
+
+fn parse_json_cookies(content: &str) -> Result<Vec<StoredCookie>, String> {
+    let exported: Vec<ExportedCookie> =
+        serde_json::from_str(content).map_err(|e| format!("Cookie 文件格式错误: {}", e))?;
+    Ok(exported
+        .into_iter()
+        .map(|cookie| StoredCookie {
+            name: cookie.name,
+            value: cookie.value,
+            domain: cookie.domain.trim_start_matches('.').to_ascii_lowercase(),
+            host_only: cookie.host_only,
+            path: cookie.path,
+            expires: cookie.expiration_date.map(|time| time as i64),
+            secure: cookie.secure,
+            http_only: cookie.http_only,
+            imported: true,
+        })
+        .collect())
+}
+
+// 每行为：域名 \t 包含子域名 \t 路径 \t 仅HTTPS \t 过期时间 \t 名称 \t 值
+fn parse_netscape_cookies(content: &str) -> Vec<StoredCookie> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(rest) => (rest, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            let expires = fields[4].trim().parse::<i64>().ok().filter(|&t| t > 0);
+            Some(StoredCookie {
+                name: fields[5].to_string(),
+                value: fields[6].trim_end_matches(['\r', '\n']).to_string(),
+                domain: fields[0].trim_start_matches('.').to_ascii_lowercase(),
+                host_only: !fields[1].eq_ignore_ascii_case("TRUE"),
+                path: fields[2].to_string(),
+                expires,
+                secure: fields[3].eq_ignore_ascii_case("TRUE"),
+                http_only,
+                imported: true,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domain_attribute_is_checked() {
+        let cookie =
+            parse_set_cookie("a=1; Domain=.music.163.com", "interface.music.163.com", "/").unwrap();
+        assert_eq!(cookie.domain, "music.163.com");
+        assert!(!cookie.host_only);
+        assert!(parse_set_cookie("a=1; Domain=qq.com", "music.163.com", "/").is_none());
+    }
+
+    #[test]
+    fn public_suffix_domain_is_rejected() {
+        assert!(parse_set_cookie("a=1; Domain=com", "music.163.com", "/").is_none());
+        assert!(parse_set_cookie("a=1; Domain=.com.cn", "www.kugou.com.cn", "/").is_none());
+        assert!(parse_set_cookie("a=1; Domain=cn", "www.kugou.com.cn", "/").is_none());
+        // 与请求主机相同时只发送给该主机
+        let cookie = parse_set_cookie("a=1; Domain=localhost", "localhost", "/").unwrap();
+        assert!(cookie.host_only);
+        assert!(parse_set_cookie("a=1; Domain=kugou.com.cn", "www.kugou.com.cn", "/").is_some());
+    }
+
+    #[test]
+    fn imported_session_cookies_are_persisted() {
+        let dir = std::env::temp_dir().join("http_cookies_imported");
+        let _ = fs::remove_dir_all(&dir);
+        let jar = CookieJar::load(&dir);
+        let imported =
+            parse_cookie_file(".music.163.com\tTRUE\t/\tFALSE\t0\tMUSIC_U\tabc").unwrap();
+        jar.insert("netease", imported).unwrap();
+        let session = parse_set_cookie("NMTID=1", "music.163.com", "/").unwrap();
+        jar.insert("netease", vec![session]).unwrap();
+        assert_eq!(jar.list("netease").len(), 2);
+
+        let names: Vec<String> = CookieJar::load(&dir)
+            .list("netease")
+            .into_iter()
+            .map(|cookie| cookie.name)
+            .collect();
+        assert_eq!(names, vec!["MUSIC_U".to_string()]);
+    }
+}
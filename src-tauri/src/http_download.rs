@@ -0,0 +1,457 @@
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::result::Result;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+use crate::http_client::HttpClientState;
+use crate::http_policy::FORBIDDEN_ERROR;
+
+// 未完成的下载保存为 目标文件名.part，完成后重命名
+const PART_EXTENSION: &str = "part";
+// 未完成下载的校验信息保存为 目标文件名.part.json
+const RESUME_EXTENSION: &str = "json";
+// 进度事件的最短间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// 下载进度事件
+#[derive(Clone, Serialize)]
+struct DownloadProgress {
+    request_id: Option<String>,
+    url: String,
+    dest: String,
+    downloaded: u64,
+    // 服务端没有返回文件大小时为空
+    total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadResult {
+    pub dest: String,
+    pub size: u64,
+    // 是否从上次中断的位置继续下载
+    pub resumed: bool,
+}
+
+// 续传前用来确认服务端的文件没有变化
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ResumeInfo {
+    total: Option<u64>,
+    // ETag 或 Last-Modified，续传时作为 If-Range 发送
+    validator: Option<String>,
+}
+
+fn with_extension_suffix(path: &Path, extension: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}", extension));
+    path.with_file_name(file_name)
+}
+
+pub(crate) fn part_path(dest: &Path) -> PathBuf {
+    with_extension_suffix(dest, PART_EXTENSION)
+}
+
+fn resume_path(part: &Path) -> PathBuf {
+    with_extension_suffix(part, RESUME_EXTENSION)
+}
+
+async fn read_resume_info(part: &Path) -> Option<ResumeInfo> {
+    let content = fs::read_to_string(resume_path(part)).await.ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+async fn write_resume_info(part: &Path, info: &ResumeInfo) -> Result<(), String> {
+    let content = serde_json::to_string(info).map_err(|e| format!("序列化下载信息失败: {}", e))?;
+    fs::write(resume_path(part), content)
+        .await
+        .map_err(|e| format!("保存下载信息失败: {}", e))
+}
+
+// 删除未完成的下载文件和校验信息
+pub(crate) fn remove_partial(dest: &Path) {
+    let part = part_path(dest);
+    let _ = std::fs::remove_file(resume_path(&part));
+    let _ = std::fs::remove_file(&part);
+}
+
+// 解析 Content-Range: bytes start-end/total，返回起始位置和文件总大小
+pub(crate) fn content_range(headers: &HeaderMap) -> Option<(Option<u64>, Option<u64>)> {
+    let value = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (range, total) = value.trim().strip_prefix("bytes")?.trim().split_once('/')?;
+    let start = range
+        .split_once('-')
+        .and_then(|(start, _)| start.trim().parse().ok());
+    Some((start, total.trim().parse().ok()))
+}
+
+// If-Range 可用的校验值：优先使用强 ETag，其次为 Last-Modified
+pub(crate) fn response_validator(headers: &HeaderMap) -> Option<String> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+}
+
+// 续传的响应是否与已下载的部分属于同一个文件：起始位置、文件大小和校验值都要一致。
+// total 和 validator 为已下载部分记录的文件大小和校验值
+pub(crate) fn resumes_part(
+    headers: &HeaderMap,
+    offset: u64,
+    total: Option<u64>,
+    validator: Option<&str>,
+) -> bool {
+    let (start, response_total) = content_range(headers).unwrap_or_default();
+    if start != Some(offset) || response_total.is_none() || response_total != total {
+        return false;
+    }
+    match (validator, response_validator(headers)) {
+        (Some(saved), Some(validator)) => saved == validator,
+        _ => true,
+    }
+}
+
+// 从 offset 开始请求文件，offset 为 0 时请求完整文件；服务端的文件与 validator 不一致时返回完整文件
+async fn send_range(
+    http: &HttpClientState,
+    url: &str,
+    headers: &HashMap<String, String>,
+    offset: u64,
+    validator: Option<&str>,
+) -> Result<Response, String> {
+    let mut builder = http.download_request(url, headers.clone()).await?;
+    if offset > 0 {
+        builder = builder.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = validator {
+            builder = builder.header(IF_RANGE, validator);
+        }
+    }
+    builder
+        .send()
+        .await
+        .map_err(|e| format!("发送请求失败: {}", e))
+}
+
+// 下载文件到 dest，已有未完成的下载时通过 Range 请求继续
+pub(crate) async fn download_to(
+    http: &HttpClientState,
+    url: &str,
+    dest: &Path,
+    headers: &HashMap<String, String>,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<DownloadResult, String> {
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("创建下载目录失败: {}", e))?;
+    }
+    let part = part_path(dest);
+    let info = read_resume_info(&part).await;
+    // 没有校验信息时无法确认已下载的部分是否属于同一个文件，从头下载
+    let mut offset = match info {
+        Some(_) => fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+    let validator = info.as_ref().and_then(|info| info.validator.as_deref());
+    let mut response = send_range(http, url, headers, offset, validator).await?;
+
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        let total = content_range(response.headers()).and_then(|(_, total)| total);
+        if total == Some(offset) && info.as_ref().is_some_and(|info| info.total == total) {
+            // 上次已经下载完整，只是没有完成重命名
+            return finish_download(&part, dest, offset, false).await;
+        }
+    }
+    // 已下载的部分与服务端的文件不一致（文件已更换，或地址换成了其他音质），重新下载
+    let mismatched = offset > 0
+        && match response.status() {
+            StatusCode::PARTIAL_CONTENT => !info.as_ref().is_some_and(|info| {
+                resumes_part(
+                    response.headers(),
+                    offset,
+                    info.total,
+                    info.validator.as_deref(),
+                )
+            }),
+            StatusCode::RANGE_NOT_SATISFIABLE => true,
+            _ => false,
+        };
+    if mismatched {
+        drop(response);
+        remove_partial(dest);
+        offset = 0;
+        response = send_range(http, url, headers, 0, None).await?;
+    }
+    if !response.status().is_success() {
+        return Err(format!("下载失败，状态码: {}", response.status().as_u16()));
+    }
+
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let total = if resumed {
+        content_range(response.headers()).and_then(|(_, total)| total)
+    } else {
+        // 服务端不支持 Range 或文件已变化时从头下载
+        offset = 0;
+        let total = response.content_length();
+        let info = ResumeInfo {
+            total,
+            validator: response_validator(response.headers()),
+        };
+        write_resume_info(&part, &info).await?;
+        total
+    };
+
+    let mut file = if resumed {
+        OpenOptions::new().append(true).open(&part).await
+    } else {
+        fs::File::create(&part).await
+    }
+    .map_err(|e| format!("打开下载文件失败: {}", e))?;
+
+    let read_timeout = http.settings().timeouts.read();
+    let mut downloaded = offset;
+    let mut last_progress = Instant::now();
+    on_progress(downloaded, total);
+    loop {
+        let chunk = match read_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+                .await
+                .map_err(|_| "读取响应超时".to_string())?,
+            None => response.chunk().await,
+        };
+        let chunk = match chunk.map_err(|e| format!("下载失败: {}", e))? {
+            Some(chunk) => chunk,
+            None => break,
+        };
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("写入下载文件失败: {}", e))?;
+        downloaded += chunk.len() as u64;
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            on_progress(downloaded, total);
+        }
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("写入下载文件失败: {}", e))?;
+    drop(file);
+    on_progress(downloaded, total);
+
+    // 大小不符时保留未完成的文件，下次继续下载
+    if let Some(total) = total {
+        if downloaded != total {
+            return Err(format!(
+                "文件大小不符，应为 {} 字节，实际为 {} 字节",
+                total, downloaded
+            ));
+        }
+    }
+    finish_download(&part, dest, downloaded, resumed).await
+}
+
+async fn finish_download(
+    part: &Path,
+    dest: &Path,
+    size: u64,
+    resumed: bool,
+) -> Result<DownloadResult, String> {
+    fs::rename(part, dest)
+        .await
+        .map_err(|e| format!("保存下载文件失败: {}", e))?;
+    let _ = fs::remove_file(resume_path(part)).await;
+    Ok(DownloadResult {
+        dest: dest.to_string_lossy().into_owned(),
+        size,
+        resumed,
+    })
+}
+
+// 下载保存目录：网络设置中的 download_dir，未设置时为系统的下载目录
+pub(crate) fn download_root(
+    app_handle: &AppHandle,
+    http: &HttpClientState,
+) -> Result<PathBuf, String> {
+    match http.settings().download_dir {
+        Some(dir) if !dir.trim().is_empty() => Ok(PathBuf::from(dir.trim())),
+        _ => app_handle
+            .path()
+            .download_dir()
+            .map_err(|e| format!("获取下载目录失败: {}", e)),
+    }
+}
+
+// 检查保存路径，只允许保存到下载目录内，避免前端覆盖任意文件。dest 为绝对路径或相对于下载目录的路径，
+// 返回规范化后的路径；经过符号链接指向下载目录以外的路径同样拒绝
+pub(crate) fn resolve_dest(root: &Path, dest: &str) -> Result<PathBuf, String> {
+    let invalid = || format!("无效的保存路径: {}", dest);
+    let path = Path::new(dest.trim());
+    if path.file_name().is_none() || path.components().any(|c| c == Component::ParentDir) {
+        return Err(invalid());
+    }
+    std::fs::create_dir_all(root).map_err(|e| format!("创建下载目录失败: {}", e))?;
+    let root = std::fs::canonicalize(root).map_err(|e| format!("无效的下载目录: {}", e))?;
+    let path = root.join(path);
+
+    // 从已存在的部分开始规范化，再拼接尚未创建的目录和文件名
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    while existing.symlink_metadata().is_err() {
+        missing.push(existing.file_name().ok_or_else(invalid)?);
+        existing = existing.parent().ok_or_else(invalid)?;
+    }
+    let mut resolved = std::fs::canonicalize(existing).map_err(|_| invalid())?;
+    resolved.extend(missing.iter().rev());
+    if resolved == root || !resolved.starts_with(&root) {
+        return Err(format!(
+            "{}: 保存路径 {} 不在下载目录 {} 中",
+            FORBIDDEN_ERROR,
+            dest,
+            root.display()
+        ));
+    }
+    Ok(resolved)
+}
+
+// 下载文件，进度通过 download-progress 事件发送；带 request_id 时可通过 cancel_request 取消，
+// 取消或失败后再次下载同一文件会从中断的位置继续；dest 必须在下载目录内
+#[tauri::command]
+pub async fn download_file(
+    app_handle: AppHandle,
+    http: State<'_, HttpClientState>,
+    url: String,
+    dest: String,
+    headers: Option<HashMap<String, String>>,
+    request_id: Option<String>,
+) -> Result<DownloadResult, String> {
+    let dest_path = resolve_dest(&download_root(&app_handle, &http)?, &dest)?;
+    let dest = dest_path.to_string_lossy().into_owned();
+    let headers = headers.unwrap_or_default();
+
+    let progress_id = request_id.clone();
+    let on_progress = |downloaded, total| {
+        let _ = app_handle.emit(
+            "download-progress",
+            DownloadProgress {
+                request_id: progress_id.clone(),
+                url: url.clone(),
+                dest: dest.clone(),
+                downloaded,
+                total,
+            },
+        );
+    };
+    let task = download_to(&http, &url, &dest_path, &headers, on_progress);
+    match request_id {
+        Some(id) => {
+            let guard = http.cancels().register(id);
+            guard.run(task).await
+        }
+        None => task.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("http_download_{}", name));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::canonicalize(root).unwrap()
+    }
+
+    #[test]
+    fn validator_prefers_strong_etag() {
+        let modified = "Mon, 19 Oct 2026 07:00:00 GMT";
+        assert_eq!(
+            response_validator(&headers(&[("etag", "\"v1\""), ("last-modified", modified)])),
+            Some("\"v1\"".to_string())
+        );
+        assert_eq!(
+            response_validator(&headers(&[
+                ("etag", "W/\"v1\""),
+                ("last-modified", modified)
+            ])),
+            Some(modified.to_string())
+        );
+        assert_eq!(response_validator(&headers(&[("etag", "W/\"v1\"")])), None);
+    }
+
+    #[test]
+    fn resume_requires_same_file() {
+        let (total, validator) = (Some(1000), Some("\"v1\""));
+        let resumed = headers(&[("content-range", "bytes 100-999/1000"), ("etag", "\"v1\"")]);
+        assert!(resumes_part(&resumed, 100, total, validator));
+        assert!(!resumes_part(&resumed, 200, total, validator));
+        let resized = headers(&[("content-range", "bytes 100-1199/1200"), ("etag", "\"v1\"")]);
+        assert!(!resumes_part(&resized, 100, total, validator));
+        let changed = headers(&[("content-range", "bytes 100-999/1000"), ("etag", "\"v2\"")]);
+        assert!(!resumes_part(&changed, 100, total, validator));
+        // 没有记录校验值时只比较位置和大小
+        assert!(resumes_part(&changed, 100, total, None));
+        let unknown_total = headers(&[("content-range", "bytes 100-999/*")]);
+        assert!(!resumes_part(&unknown_total, 100, total, None));
+    }
+
+    #[test]
+    fn dest_inside_download_dir() {
+        let root = temp_root("inside");
+        assert_eq!(resolve_dest(&root, "a.mp3").unwrap(), root.join("a.mp3"));
+        assert_eq!(
+            resolve_dest(&root, "歌手/专辑/a.mp3").unwrap(),
+            root.join("歌手/专辑/a.mp3")
+        );
+        let absolute = root.join("b.mp3");
+        assert_eq!(
+            resolve_dest(&root, &absolute.to_string_lossy()).unwrap(),
+            absolute
+        );
+    }
+
+    #[test]
+    fn dest_outside_download_dir() {
+        let root = temp_root("outside");
+        let outside = std::env::temp_dir().join("http_download_other.mp3");
+        for dest in [
+            outside.to_string_lossy().into_owned(),
+            "../a.mp3".to_string(),
+            "sub/../../a.mp3".to_string(),
+            root.to_string_lossy().into_owned(),
+            String::new(),
+        ] {
+            assert!(resolve_dest(&root, &dest).is_err(), "{}", dest);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dest_through_symlink() {
+        let root = temp_root("symlink");
+        let target = temp_root("symlink_target");
+        std::os::unix::fs::symlink(&target, root.join("link")).unwrap();
+        std::os::unix::fs::symlink(target.join("x.mp3"), root.join("dangling.mp3")).unwrap();
+        assert!(resolve_dest(&root, "link/a.mp3").is_err());
+        assert!(resolve_dest(&root, "dangling.mp3").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::http_cache::RawResponse;
+
+// 环境变量优先于设置，便于在 CI 中切换：off、record、replay
+pub const FIXTURE_MODE_ENV: &str = "MUBOX_HTTP_FIXTURES";
+// 录制文件目录
+pub const FIXTURE_DIR_ENV: &str = "MUBOX_HTTP_FIXTURES_DIR";
+const FIXTURES_DIR_NAME: &str = "http_fixtures";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixtureMode {
+    // 正常访问网络
+    #[default]
+    Off,
+    // 访问网络并保存请求和响应
+    Record,
+    // 只使用录制的响应，不访问网络
+    Replay,
+}
+
+impl FixtureMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "" => Some(FixtureMode::Off),
+            "record" => Some(FixtureMode::Record),
+            "replay" => Some(FixtureMode::Replay),
+            _ => None,
+        }
+    }
+}
+
+// 录制/回放设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FixtureSettings {
+    pub mode: FixtureMode,
+    // 录制文件目录，为空时使用应用数据目录下的 http_fixtures
+    pub dir: Option<String>,
+}
+
+// 录制文件内容，文本响应直接保存便于查看和手工修改
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    #[serde(default)]
+    request_body: Option<String>,
+    status: u16,
+    headers: HashMap<String, Vec<String>>,
+    // utf8 或 base64
+    body_encoding: String,
+    body: String,
+}
+
+// 请求录制和回放
+pub struct FixtureStore {
+    mode: FixtureMode,
+    dir: PathBuf,
+}
+
+impl FixtureStore {
+    // 按环境变量和设置确定模式与目录
+    pub fn new(settings: &FixtureSettings, data_dir: &Path) -> Self {
+        let mode = match std::env::var(FIXTURE_MODE_ENV) {
+            Ok(value) => FixtureMode::parse(&value).unwrap_or_else(|| {
+                eprintln!("无效的 {}: {}", FIXTURE_MODE_ENV, value);
+                settings.mode
+            }),
+            Err(_) => settings.mode,
+        };
+        let dir = std::env::var(FIXTURE_DIR_ENV)
+            .ok()
+            .or_else(|| settings.dir.clone())
+            .filter(|dir| !dir.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| data_dir.join(FIXTURES_DIR_NAME));
+        FixtureStore::with_mode(mode, dir)
+    }
+
+    // 指定模式与目录，不读取环境变量
+    pub fn with_mode(mode: FixtureMode, dir: PathBuf) -> Self {
+        FixtureStore { mode, dir }
+    }
+
+    pub fn mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    // 按主机分目录保存，文件名为请求的哈希
+    fn fixture_path(&self, url: &str, key: &str) -> PathBuf {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string());
+        self.dir.join(host).join(format!("{}.json", key))
+    }
+
+    // 读取录制的响应
+    pub fn replay(&self, method: &str, url: &str, key: &str) -> Result<RawResponse, String> {
+        let path = self.fixture_path(url, key);
+        let content = fs::read_to_string(&path)
+            .map_err(|_| format!("没有找到录制的响应: {} {} ({:?})", method, url, path))?;
+        let fixture: Fixture = serde_json::from_str(&content)
+            .map_err(|e| format!("录制文件格式错误 {:?}: {}", path, e))?;
+        let bytes = match fixture.body_encoding.as_str() {
+            "base64" => STANDARD
+                .decode(fixture.body.trim())
+                .map_err(|e| format!("录制文件格式错误 {:?}: {}", path, e))?,
+            _ => fixture.body.into_bytes(),
+        };
+        Ok(RawResponse {
+            status: fixture.status,
+            url: fixture.url,
+            headers: fixture.headers,
+            bytes,
+        })
+    }
+
+    // 保存请求和响应，失败时只记录日志
+    pub fn record(
+        &self,
+        method: &str,
+        url: &str,
+        request_body: Option<&[u8]>,
+        key: &str,
+        response: &RawResponse,
+    ) {
+        let (body_encoding, body) = match std::str::from_utf8(&response.bytes) {
+            Ok(text) => ("utf8", text.to_string()),
+            Err(_) => ("base64", STANDARD.encode(&response.bytes)),
+        };
+        let fixture = Fixture {
+            method: method.to_string(),
+            url: url.to_string(),
+            request_body: request_body.map(|body| String::from_utf8_lossy(body).into_owned()),
+            status: response.status,
+            headers: response
+                .headers
+                .iter()
+                .filter(|(name, _)| name.as_str() != "set-cookie")
+                .map(|(name, values)| (name.clone(), values.clone()))
+                .collect(),
+            body_encoding: body_encoding.to_string(),
+            body,
+        };
+
+        let path = self.fixture_path(url, key);
+        let saved = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| format!("创建录制目录失败: {}", e))
+            .and_then(|_| {
+                serde_json::to_string_pretty(&fixture)
+                    .map_err(|e| format!("序列化录制文件失败: {}", e))
+            })
+            .and_then(|content| {
+                fs::write(&path, content).map_err(|e| format!("保存录制文件失败: {}", e))
+            });
+        if let Err(e) = saved {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+// 回放 src-tauri/fixtures/http 中的响应，供各平台的解析测试使用；这些响应按接口格式手工编写，并非真实录制
+#[cfg(test)]
+pub(crate) fn replay_client(name: &str) -> crate::http_client::HttpClientState {
+    let dir = std::env::temp_dir().join(format!("http_replay_{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    let http = crate::http_client::HttpClientState::new(&dir, &dir, &dir);
+    // 不受 MUBOX_HTTP_FIXTURES 等环境变量影响，测试始终回放
+    http.set_fixtures(FixtureStore::with_mode(
+        FixtureMode::Replay,
+        PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/http")),
+    ));
+    http
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{Mutex as AsyncMutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+// 按主机限制并发数和请求间隔，避免短时间内大量请求被平台封禁
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    // 同一主机同时进行的请求数，0 表示不限制
+    pub max_concurrent_per_host: usize,
+    // 同一主机两次请求开始的最小间隔（毫秒）
+    pub min_interval_ms: u64,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        RateLimitSettings {
+            max_concurrent_per_host: 4,
+            min_interval_ms: 100,
+        }
+    }
+}
+
+struct HostSlot {
+    semaphore: Arc<Semaphore>,
+    next_start: AsyncMutex<Instant>,
+}
+
+pub struct HostLimiter {
+    settings: RateLimitSettings,
+    hosts: Mutex<HashMap<String, Arc<HostSlot>>>,
+}
+
+// 持有期间占用主机的一个并发名额
+pub struct HostPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl HostLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        HostLimiter {
+            settings,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn slot(&self, host: &str) -> Arc<HostSlot> {
+        let mut hosts = match self.hosts.lock() {
+            Ok(hosts) => hosts,
+            Err(e) => e.into_inner(),
+        };
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                Arc::new(HostSlot {
+                    semaphore: Arc::new(Semaphore::new(
+                        self.settings.max_concurrent_per_host.max(1),
+                    )),
+                    next_start: AsyncMutex::new(Instant::now()),
+                })
+            })
+            .clone()
+    }
+
+    // 等待直到可以向该主机发起请求
+    pub async fn acquire(&self, host: &str) -> HostPermit {
+        let slot = self.slot(host);
+
+        let permit = if self.settings.max_concurrent_per_host > 0 {
+            slot.semaphore.clone().acquire_owned().await.ok()
+        } else {
+            None
+        };
+
+        if self.settings.min_interval_ms > 0 {
+            let interval = Duration::from_millis(self.settings.min_interval_ms);
+            let mut next_start = slot.next_start.lock().await;
+            let now = Instant::now();
+            if *next_start > now {
+                tokio::time::sleep_until(*next_start).await;
+            }
+            *next_start = Instant::now().max(*next_start) + interval;
+        }
+
+        HostPermit { _permit: permit }
+    }
+}
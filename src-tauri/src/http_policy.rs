@@ -0,0 +1,411 @@
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::platform::host_rule_matches;
+
+// 请求被安全策略拒绝时返回的错误前缀
+pub const FORBIDDEN_ERROR: &str = "禁止访问";
+
+// 由客户端管理、不允许前端设置的请求头
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "te",
+    "trailer",
+    "upgrade",
+    "keep-alive",
+    "expect",
+    "proxy-authorization",
+    "proxy-connection",
+];
+
+fn forbidden(reason: String) -> String {
+    format!("{}: {}", FORBIDDEN_ERROR, reason)
+}
+
+// 前端发起请求的安全策略，避免后端被当作任意地址的代理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestPolicy {
+    // 允许访问的主机，规则格式见 host_rule_matches；为空时允许所有公网主机
+    pub allowed_hosts: Vec<String>,
+    // 允许访问回环、局域网等内网地址
+    pub allow_private_network: bool,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        RequestPolicy {
+            allowed_hosts: vec!["netease".into(), "qq".into(), "kugou".into()],
+            allow_private_network: false,
+        }
+    }
+}
+
+impl RequestPolicy {
+    // 检查请求地址的协议、主机是否允许访问，不做域名解析
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(forbidden(format!("不支持的协议 {}", url.scheme())));
+        }
+        let host = match url.host_str() {
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+            None => return Err(forbidden("请求地址缺少主机名".to_string())),
+        };
+
+        // Url 已将各种写法的 IP 地址规范化
+        let host_name = match host.parse::<IpAddr>() {
+            Ok(ip) => self.check_ip(ip)?,
+            Err(_) => {
+                let domain = host.trim_end_matches('.').to_ascii_lowercase();
+                if !self.allow_private_network
+                    && (domain == "localhost" || domain.ends_with(".localhost"))
+                {
+                    return Err(forbidden(format!("不允许访问本机地址 {}", domain)));
+                }
+                domain
+            }
+        };
+
+        if !self.allowed_hosts.is_empty()
+            && !self
+                .allowed_hosts
+                .iter()
+                .any(|rule| host_rule_matches(rule, &host_name))
+        {
+            return Err(forbidden(format!("{} 不在允许访问的主机列表中", host_name)));
+        }
+        Ok(())
+    }
+
+    fn check_ip(&self, ip: IpAddr) -> Result<String, String> {
+        if !self.allow_private_network && is_private_ip(ip) {
+            return Err(forbidden(format!("不允许访问内网地址 {}", ip)));
+        }
+        Ok(ip.to_string())
+    }
+
+    // 解析域名，拒绝解析到内网地址的域名；解析失败时交给请求本身报错
+    pub async fn check_resolved(&self, url: &Url) -> Result<(), String> {
+        if self.allow_private_network {
+            return Ok(());
+        }
+        let domain = match url.host_str() {
+            Some(host) if !host.starts_with('[') && host.parse::<IpAddr>().is_err() => host,
+            _ => return Ok(()),
+        };
+        let port = url.port_or_known_default().unwrap_or(80);
+        if let Ok(addrs) = tokio::net::lookup_host((domain, port)).await {
+            for addr in addrs {
+                if is_private_ip(addr.ip()) {
+                    return Err(forbidden(format!(
+                        "{} 解析到内网地址 {}",
+                        domain,
+                        addr.ip()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 客户端连接时使用的域名解析器，拒绝解析到内网地址的域名
+// 重定向的目标也经过这里，且检查的就是实际连接的地址，不会被检查之后的再次解析绕过
+pub struct PolicyResolver {
+    allow_private_network: bool,
+    // 不做检查的主机，如设置的代理服务器，通常在本机或局域网
+    trusted_hosts: Vec<String>,
+}
+
+impl PolicyResolver {
+    pub fn new(policy: &RequestPolicy, trusted_hosts: Vec<String>) -> Self {
+        PolicyResolver {
+            allow_private_network: policy.allow_private_network,
+            trusted_hosts: trusted_hosts
+                .iter()
+                .map(|host| host.trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+        }
+    }
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().trim_end_matches('.').to_ascii_lowercase();
+        let check = !self.allow_private_network && !self.trusted_hosts.contains(&host);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if check {
+                if let Some(addr) = addrs.iter().find(|addr| is_private_ip(addr.ip())) {
+                    let reason = format!("{} 解析到内网地址 {}", host, addr.ip());
+                    return Err(forbidden(reason).into());
+                }
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// 回环、私有网络、链路本地、未指定和组播等不应从前端访问的地址
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_ipv4(ip),
+            None => is_private_ipv6(ip),
+        },
+    }
+}
+
+fn is_private_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        // 0.0.0.0/8
+        || a == 0
+        // 运营商级 NAT 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+}
+
+fn is_private_ipv6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // 唯一本地地址 fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // 链路本地地址 fe80::/10
+        || (first & 0xffc0) == 0xfe80
+}
+
+// 移除由客户端管理的请求头，名称或值不合法时拒绝请求
+pub fn sanitize_headers(headers: &mut HashMap<String, String>) -> Result<(), String> {
+    for (name, value) in headers.iter() {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err()
+        {
+            return Err(forbidden(format!("无效的请求头 {}", name)));
+        }
+    }
+    headers.retain(|name, _| {
+        let name = name.to_ascii_lowercase();
+        let allowed = !FORBIDDEN_HEADERS.contains(&name.as_str());
+        if !allowed {
+            eprintln!("忽略请求头 {}", name);
+        }
+        allowed
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::domain_match;
+
+    fn url(value: &str) -> Url {
+        Url::parse(value).unwrap()
+    }
+
+    fn assert_forbidden(policy: &RequestPolicy, value: &str) {
+        let error = policy.check_url(&url(value)).unwrap_err();
+        assert!(error.starts_with(FORBIDDEN_ERROR), "{}: {}", value, error);
+    }
+
+    fn open_policy() -> RequestPolicy {
+        RequestPolicy {
+            allowed_hosts: vec![],
+            allow_private_network: false,
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_schemes() {
+        let policy = open_policy();
+        for value in [
+            "file:///etc/passwd",
+            "ftp://music.163.com/a",
+            "data:text/plain,abc",
+            "ws://music.163.com/",
+        ] {
+            assert_forbidden(&policy, value);
+        }
+    }
+
+    #[test]
+    fn rejects_localhost_names() {
+        let policy = open_policy();
+        for value in [
+            "http://localhost/",
+            "http://LOCALHOST:8080/",
+            "http://localhost./",
+            "http://api.localhost/",
+        ] {
+            assert_forbidden(&policy, value);
+        }
+        let private = RequestPolicy {
+            allow_private_network: true,
+            ..open_policy()
+        };
+        assert!(private.check_url(&url("http://localhost:8080/")).is_ok());
+    }
+
+    #[test]
+    fn rejects_private_ipv4() {
+        let policy = open_policy();
+        for value in [
+            "http://127.0.0.1/",
+            "http://2130706433/",
+            "http://10.1.2.3/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest",
+            "http://100.64.1.1/",
+            "http://0.0.0.0/",
+            "http://255.255.255.255/",
+            "http://224.0.0.1/",
+        ] {
+            assert_forbidden(&policy, value);
+        }
+        assert!(policy.check_url(&url("http://8.8.8.8/")).is_ok());
+        assert!(policy.check_url(&url("http://100.128.0.1/")).is_ok());
+    }
+
+    #[test]
+    fn rejects_private_ipv6() {
+        let policy = open_policy();
+        for value in [
+            "http://[::1]/",
+            "http://[::]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[ff02::1]/",
+        ] {
+            assert_forbidden(&policy, value);
+        }
+        assert!(policy
+            .check_url(&url("http://[2001:4860:4860::8888]/"))
+            .is_ok());
+    }
+
+    #[test]
+    fn private_ip_ranges() {
+        assert!(is_private_ip("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(is_private_ip("::1".parse().unwrap()));
+        assert!(is_private_ip("100.127.255.255".parse().unwrap()));
+        assert!(!is_private_ip("::ffff:8.8.8.8".parse().unwrap()));
+        assert!(!is_private_ip("100.63.255.255".parse().unwrap()));
+    }
+
+    #[test]
+    fn allowlist_matches_platform_hosts() {
+        let policy = RequestPolicy::default();
+        for value in [
+            "https://music.163.com/weapi/x",
+            "http://m701.music.126.net/a.mp3",
+            "https://u.y.qq.com/cgi-bin/musicu.fcg",
+            "https://y.gtimg.cn/b.jpg",
+            "http://c1.kgimg.com/a",
+            "https://www.kugou.com/",
+        ] {
+            assert!(policy.check_url(&url(value)).is_ok(), "{}", value);
+        }
+        for value in [
+            "https://example.com/",
+            "https://evil163.com/",
+            "https://music.163.com.evil.com/",
+            "https://qq.com.cn/",
+        ] {
+            assert_forbidden(&policy, value);
+        }
+    }
+
+    #[test]
+    fn host_rules() {
+        assert!(host_rule_matches("netease", "music.163.com"));
+        assert!(!host_rule_matches("netease", "y.qq.com"));
+        assert!(host_rule_matches("example.com", "example.com"));
+        assert!(host_rule_matches("example.com", "api.example.com"));
+        assert!(host_rule_matches(".example.com", "api.example.com"));
+        assert!(host_rule_matches("*.example.com", "api.example.com"));
+        assert!(!host_rule_matches("*.example.com", "example.com"));
+        assert!(!host_rule_matches("example.com", "badexample.com"));
+        assert!(!host_rule_matches("", "example.com"));
+        assert!(domain_match("163.com", "163.com"));
+        assert!(domain_match("music.163.com", "163.com"));
+        assert!(!domain_match("music163.com", "163.com"));
+        assert!(!domain_match("163.com", "music.163.com"));
+    }
+
+    fn resolve(resolver: &PolicyResolver, host: &str) -> Result<Vec<SocketAddr>, String> {
+        let name: Name = host.parse().unwrap();
+        tauri::async_runtime::block_on(resolver.resolve(name))
+            .map(|addrs| addrs.collect())
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn resolver_rejects_private_addresses() {
+        let resolver = PolicyResolver::new(&RequestPolicy::default(), vec![]);
+        let error = resolve(&resolver, "localhost").unwrap_err();
+        assert!(error.starts_with(FORBIDDEN_ERROR), "{}", error);
+
+        // 允许内网地址，或者是信任的主机（代理服务器）时不做检查
+        let policy = RequestPolicy {
+            allow_private_network: true,
+            ..Default::default()
+        };
+        let resolver = PolicyResolver::new(&policy, vec![]);
+        assert!(!resolve(&resolver, "localhost").unwrap().is_empty());
+        let resolver = PolicyResolver::new(&RequestPolicy::default(), vec!["LOCALHOST".into()]);
+        assert!(!resolve(&resolver, "localhost.").unwrap().is_empty());
+    }
+
+    #[test]
+    fn sanitize_removes_managed_headers() {
+        let mut headers: HashMap<String, String> = [
+            ("Host", "evil.com"),
+            ("Connection", "keep-alive"),
+            ("Transfer-Encoding", "chunked"),
+            ("TE", "trailers"),
+            ("Upgrade", "websocket"),
+            ("Proxy-Authorization", "Basic abc"),
+            ("Referer", "https://music.163.com/"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        sanitize_headers(&mut headers).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key("Referer"));
+    }
+
+    #[test]
+    fn sanitize_rejects_invalid_headers() {
+        for (name, value) in [
+            ("X-Test", "a\r\nHost: evil.com"),
+            ("X-Test", "a\nb"),
+            ("X-Test\r\nHost", "a"),
+            ("X Test", "a"),
+            ("", "a"),
+        ] {
+            let mut headers = HashMap::from([(name.to_string(), value.to_string())]);
+            let error = sanitize_headers(&mut headers).unwrap_err();
+            assert!(error.starts_with(FORBIDDEN_ERROR), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use reqwest::{Proxy, Url};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::platform::host_rule_matches;
+
+// 代理设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub enabled: bool,
+    // 代理地址，如 http://127.0.0.1:7890、https://proxy.example.com、socks5://127.0.0.1:1080
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // 只有匹配的请求走代理，为空时所有请求都走代理
+    pub include: Vec<String>,
+    // 匹配的请求不走代理，优先于 include
+    pub bypass: Vec<String>,
+}
+
+// 代理规则，每一项的格式见 host_rule_matches
+#[derive(Debug, Clone)]
+struct ProxyRules {
+    include: Vec<String>,
+    bypass: Vec<String>,
+}
+
+impl ProxyRules {
+    fn should_proxy(&self, url: &Url) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_ascii_lowercase(),
+            None => return false,
+        };
+        if self
+            .bypass
+            .iter()
+            .any(|rule| host_rule_matches(rule, &host))
+        {
+            return false;
+        }
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|rule| host_rule_matches(rule, &host))
+    }
+}
+
+impl ProxySettings {
+    // 带认证信息的代理地址
+    fn proxy_url(&self) -> Result<Url, String> {
+        let mut url = Url::parse(self.url.trim()).map_err(|e| format!("无效的代理地址: {}", e))?;
+        match url.scheme() {
+            "http" | "https" | "socks5" | "socks5h" => {}
+            scheme => return Err(format!("不支持的代理类型: {}", scheme)),
+        }
+        if let Some(username) = self.username.as_deref().filter(|u| !u.is_empty()) {
+            url.set_username(username)
+                .map_err(|_| "无法设置代理用户名".to_string())?;
+            url.set_password(self.password.as_deref())
+                .map_err(|_| "无法设置代理密码".to_string())?;
+        }
+        Ok(url)
+    }
+
+    // 按设置创建代理，未启用时返回空
+    pub fn build(&self) -> Result<Option<Proxy>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let proxy_url = self.proxy_url()?;
+        let rules = Arc::new(ProxyRules {
+            include: self.include.clone(),
+            bypass: self.bypass.clone(),
+        });
+        let proxy = Proxy::custom(move |url| {
+            if rules.should_proxy(url) {
+                Some(proxy_url.clone())
+            } else {
+                None
+            }
+        });
+        Ok(Some(proxy))
+    }
+
+    // 代理服务器的主机名，连接代理时不受内网地址限制
+    pub fn host(&self) -> Option<String> {
+        let url = Url::parse(self.url.trim()).ok()?;
+        url.host_str().map(str::to_string)
+    }
+
+    // 忽略规则、所有请求都走代理，用于测试代理是否可用
+    pub fn build_unconditional(&self) -> Result<Proxy, String> {
+        Proxy::all(self.proxy_url()?).map_err(|e| format!("创建代理失败: {}", e))
+    }
+}
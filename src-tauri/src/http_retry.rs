@@ -0,0 +1,71 @@
+use rand::Rng;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+// 会触发重试的状态码：限流和服务端临时错误
+const RETRYABLE_STATUS: &[u16] = &[408, 429, 500, 502, 503, 504];
+
+// 重试策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrySettings {
+    // 最多重试次数，0 表示不重试
+    pub max_retries: u32,
+    // 第一次重试前的等待时间，之后按指数增长
+    pub base_delay_ms: u64,
+    // 单次等待的上限，Retry-After 超过该值时不再重试
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            max_retries: 2,
+            base_delay_ms: 500,
+            max_delay_ms: 10_000,
+        }
+    }
+}
+
+impl RetrySettings {
+    // 第 attempt 次重试（从 0 开始）前的等待时间：指数退避，在 [delay/2, delay] 内随机抖动
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.min(16))
+            .min(self.max_delay_ms);
+        let jittered = rand::thread_rng().gen_range(delay / 2..=delay);
+        Duration::from_millis(jittered)
+    }
+
+    pub fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_ms)
+    }
+}
+
+// 幂等的请求方法重复发送不会产生副作用，默认只重试这些请求
+pub fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+pub fn is_retryable_status(status: u16) -> bool {
+    RETRYABLE_STATUS.contains(&status)
+}
+
+// 解析 Retry-After 响应头，支持秒数和 HTTP 日期两种格式
+pub fn retry_after(headers: &HashMap<String, Vec<String>>) -> Option<Duration> {
+    let value = headers.get("retry-after")?.first()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let time = httpdate::parse_http_date(value).ok()?;
+    Some(
+        time.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::http_fixtures::FixtureSettings;
+use crate::http_limiter::RateLimitSettings;
+use crate::http_policy::RequestPolicy;
+use crate::http_proxy::ProxySettings;
+use crate::http_retry::RetrySettings;
+
+// 超时设置（毫秒），0 表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpTimeouts {
+    // 建立连接
+    pub connect_ms: u64,
+    // 两次收到数据之间的最长间隔
+    pub read_ms: u64,
+    // 整个请求（含读取响应）
+    pub total_ms: u64,
+}
+
+impl Default for HttpTimeouts {
+    fn default() -> Self {
+        HttpTimeouts {
+            connect_ms: 10_000,
+            read_ms: 15_000,
+            total_ms: 30_000,
+        }
+    }
+}
+
+impl HttpTimeouts {
+    pub fn connect(&self) -> Option<Duration> {
+        to_duration(self.connect_ms)
+    }
+
+    pub fn read(&self) -> Option<Duration> {
+        to_duration(self.read_ms)
+    }
+
+    pub fn total(&self) -> Option<Duration> {
+        to_duration(self.total_ms)
+    }
+}
+
+fn to_duration(ms: u64) -> Option<Duration> {
+    if ms == 0 {
+        None
+    } else {
+        Some(Duration::from_millis(ms))
+    }
+}
+
+// 网络请求设置，保存在应用配置目录
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub timeouts: HttpTimeouts,
+    pub proxy: ProxySettings,
+    pub retry: RetrySettings,
+    pub rate_limit: RateLimitSettings,
+    pub policy: RequestPolicy,
+    pub fixtures: FixtureSettings,
+    // 离线模式：只使用缓存，不发送网络请求
+    pub offline: bool,
+    // 下载文件的保存目录，为空时使用系统的下载目录；下载只能保存到该目录内
+    pub download_dir: Option<String>,
+}
+
+impl HttpSettings {
+    // 读取设置，文件不存在或损坏时使用默认值
+    pub fn load(file: &Path) -> Self {
+        match fs::read_to_string(file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("解析网络设置失败 {:?}: {}", file, e);
+                HttpSettings::default()
+            }),
+            Err(_) => HttpSettings::default(),
+        }
+    }
+
+    pub fn save(&self, file: &Path) -> Result<(), String> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        }
+        let content =
+            serde_json::to_string_pretty(self).map_err(|e| format!("序列化网络设置失败: {}", e))?;
+        fs::write(file, content).map_err(|e| format!("保存网络设置失败: {}", e))
+    }
+}
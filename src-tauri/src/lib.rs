@@ -1,6 +1,38 @@
+mod audio_cache;
+mod audio_decoder;
 mod audio_metadata;
+mod charset;
+mod download_queue;
+mod download_tagger;
+mod ebur128;
+mod fallback_resolver;
+mod file_hash;
+mod fingerprint;
+mod http_cache;
+mod http_cancel;
 mod http_client;
+mod http_cookies;
+mod http_download;
+mod http_fixtures;
+mod http_limiter;
+mod http_policy;
+mod http_proxy;
+mod http_retry;
+mod http_settings;
+mod library;
+mod loudness;
+mod music_provider;
+mod netease_api;
+mod offline;
+mod platform;
+mod provider_kugou;
+mod provider_netease;
+mod provider_qq;
+mod replay_gain;
+mod search_aggregator;
 mod setup;
+mod stream_proxy;
+mod waveform;
 // 仅在桌面环境下导入的模块和类型
 #[cfg(desktop)]
 use std::path::PathBuf; // 用于处理文件路径的标准库类型
@@ -165,7 +197,55 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             http_client::http_get_text,
             http_client::http_post_text,
+            http_client::http_request,
+            http_client::cancel_request,
+            netease_api::netease_request,
+            music_provider::list_providers,
+            music_provider::provider_categories,
+            music_provider::provider_square,
+            music_provider::provider_toplist,
+            music_provider::provider_search,
+            music_provider::provider_playlist_detail,
+            music_provider::provider_play_url,
+            search_aggregator::search_all,
+            fallback_resolver::resolve_playable_source,
+            stream_proxy::proxy_stream_url,
+            audio_cache::get_audio_cache_info,
+            audio_cache::set_audio_cache_limit,
+            audio_cache::clear_audio_cache,
+            offline::get_offline_mode,
+            offline::set_offline_mode,
+            offline::list_offline_playlists,
+            offline::add_offline_playlist,
+            offline::sync_offline_playlist,
+            offline::remove_offline_playlist,
+            offline::offline_track_status,
+            http_download::download_file,
+            download_queue::enqueue_download,
+            download_queue::list_downloads,
+            download_queue::pause_download,
+            download_queue::resume_download,
+            download_queue::retry_download,
+            download_queue::remove_download,
+            download_queue::clear_download_history,
+            download_queue::set_max_concurrent_downloads,
+            download_queue::set_download_tagging,
+            http_client::get_http_settings,
+            http_client::set_http_settings,
+            http_client::list_cookies,
+            http_client::import_cookies,
+            http_client::clear_cookies,
+            http_client::test_proxy,
+            http_client::get_http_cache_info,
+            http_client::clear_http_cache,
             audio_metadata::get_audio_metadata,
+            library::get_library_entries,
+            library::save_library,
+            fingerprint::compute_fingerprints,
+            fingerprint::find_duplicates,
+            loudness::analyze_loudness,
+            loudness::cancel_loudness_analysis,
+            waveform::get_waveform_peaks,
             check_for_updates,
             get_app_info
         ]);
@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+use crate::fingerprint::AcousticFingerprint;
+use crate::loudness::TrackLoudness;
+
+const LIBRARY_FILE_NAME: &str = "library.json";
+
+// 曲库中单个本地文件的分析数据
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub full_path: String,
+    #[serde(default)]
+    pub loudness: Option<TrackLoudness>,
+    #[serde(default)]
+    pub fingerprint: Option<AcousticFingerprint>,
+    #[serde(default)]
+    pub tags: Option<LibraryTags>,
+    #[serde(default)]
+    pub track_id: Option<LibraryTrackId>,
+}
+
+// 本地文件的稳定标识，计算需要解码整个文件，按修改时间缓存
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LibraryTrackId {
+    pub id: String,
+    // 计算标识时文件的修改时间（Unix 秒）
+    pub modified: u64,
+}
+
+// 本地文件的基本标签，用于与在线歌曲匹配；文件修改时间变化后重新读取
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LibraryTags {
+    pub title: String,
+    pub artist: String,
+    // 毫秒
+    pub duration: u64,
+    // 读取标签时文件的修改时间（Unix 秒）
+    pub modified: u64,
+}
+
+// 文件的修改时间（Unix 秒），文件不存在时返回 None
+pub fn modified_secs(path: &Path) -> Option<u64> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+// 本地曲库：保存后端对本地文件的分析结果，以文件路径为键，持久化到应用数据目录
+#[derive(Debug, Default)]
+pub struct Library {
+    file: PathBuf,
+    entries: HashMap<String, LibraryEntry>,
+    // 有未保存的修改
+    dirty: bool,
+}
+
+pub type LibraryState = Mutex<Library>;
+
+impl Library {
+    // 从应用数据目录加载曲库，文件不存在或损坏时返回空曲库
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(LIBRARY_FILE_NAME);
+        let entries = match fs::read_to_string(&file) {
+            Ok(content) => match serde_json::from_str::<Vec<LibraryEntry>>(&content) {
+                Ok(list) => list
+                    .into_iter()
+                    .map(|entry| (entry.full_path.clone(), entry))
+                    .collect(),
+                Err(e) => {
+                    eprintln!("解析曲库文件失败 {:?}: {}", file, e);
+                    HashMap::new()
+                }
+            },
+            Err(_) => HashMap::new(),
+        };
+        Library {
+            file,
+            entries,
+            dirty: false,
+        }
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        }
+        let list: Vec<&LibraryEntry> = self.entries.values().collect();
+        let content = serde_json::to_string(&list).map_err(|e| format!("序列化曲库失败: {}", e))?;
+        // 先写临时文件再替换，避免写入中断导致曲库损坏
+        let tmp_file = self.file.with_extension("json.tmp");
+        fs::write(&tmp_file, content).map_err(|e| format!("保存曲库失败: {}", e))?;
+        fs::rename(&tmp_file, &self.file).map_err(|e| format!("保存曲库失败: {}", e))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // 标记有未保存的修改，逐个文件更新时由调用方在整批处理完成后统一保存
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn save_if_dirty(&mut self) -> Result<(), String> {
+        if self.dirty {
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn get(&self, full_path: &str) -> Option<&LibraryEntry> {
+        self.entries.get(full_path)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LibraryEntry> {
+        self.entries.values()
+    }
+
+    // 获取条目，不存在时创建
+    pub fn entry_mut(&mut self, full_path: &str) -> &mut LibraryEntry {
+        self.entries
+            .entry(full_path.to_string())
+            .or_insert_with(|| LibraryEntry {
+                full_path: full_path.to_string(),
+                ..Default::default()
+            })
+    }
+}
+
+#[tauri::command]
+pub fn get_library_entries(
+    library: State<'_, LibraryState>,
+    full_paths: Vec<String>,
+) -> Result<Vec<LibraryEntry>, String> {
+    let library = library.lock().map_err(|e| format!("读取曲库失败: {}", e))?;
+    Ok(full_paths
+        .iter()
+        .filter_map(|full_path| library.get(full_path).cloned())
+        .collect())
+}
+
+// 保存曲库中未保存的修改，前端在扫描或迁移一批文件后调用
+#[tauri::command]
+pub fn save_library(library: State<'_, LibraryState>) -> Result<(), String> {
+    let mut library = library.lock().map_err(|e| format!("保存曲库失败: {}", e))?;
+    library.save_if_dirty()
+}
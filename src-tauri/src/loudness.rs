@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio_decoder::decode_file;
+use crate::ebur128::{LoudnessBlocks, LoudnessMeter, LoudnessResult};
+use crate::library::LibraryState;
+use crate::replay_gain::{write_replay_gain, ReplayGain};
+
+// 保存在曲库中的响度分析结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackLoudness {
+    pub track: LoudnessResult,
+    // 同一专辑的曲目一起分析时才有专辑结果
+    pub album: Option<LoudnessResult>,
+    // 分析时间（Unix 秒）
+    pub analyzed_at: u64,
+}
+
+// 响度分析任务状态，同一时间只允许一个任务运行
+#[derive(Default)]
+pub struct LoudnessJob {
+    running: Arc<AtomicBool>,
+    cancel: Arc<AtomicBool>,
+}
+
+// 每分析完一首曲目发送的进度事件
+#[derive(Clone, Serialize)]
+struct LoudnessProgress {
+    current: usize,
+    total: usize,
+    full_path: String,
+    result: Option<LoudnessResult>,
+    error: Option<String>,
+}
+
+// 任务结束事件
+#[derive(Clone, Serialize)]
+struct LoudnessFinished {
+    cancelled: bool,
+    analyzed: usize,
+    failed: usize,
+}
+
+#[tauri::command]
+pub fn analyze_loudness(
+    app_handle: AppHandle,
+    job: State<'_, LoudnessJob>,
+    full_paths: Vec<String>,
+    write_tags: bool,
+) -> Result<(), String> {
+    if job.running.swap(true, Ordering::SeqCst) {
+        return Err("已有响度分析任务正在运行".to_string());
+    }
+    job.cancel.store(false, Ordering::SeqCst);
+
+    let running = job.running.clone();
+    let cancel = job.cancel.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let finished = run_analysis(&app_handle, &full_paths, write_tags, &cancel);
+        running.store(false, Ordering::SeqCst);
+        let _ = app_handle.emit("loudness-finished", finished);
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_loudness_analysis(job: State<'_, LoudnessJob>) {
+    job.cancel.store(true, Ordering::SeqCst);
+}
+
+// 解码并测量单个文件
+fn measure_file(path: &Path, cancel: &AtomicBool) -> Result<LoudnessBlocks, String> {
+    let mut meter: Option<LoudnessMeter> = None;
+    decode_file(path, Some(cancel), |info, samples| {
+        // 声道数变化时（极少见）重新建立响度计会丢失之前的数据，这里保持第一次的布局
+        let meter =
+            meter.get_or_insert_with(|| LoudnessMeter::new(info.sample_rate, info.channels));
+        if meter.channels() == info.channels {
+            meter.process(samples);
+        }
+        ControlFlow::Continue(())
+    })?;
+    meter
+        .map(LoudnessMeter::finish)
+        .ok_or_else(|| "没有解码到音频数据".to_string())
+}
+
+// 读取专辑名，用于把同一专辑的曲目归为一组
+fn read_album_key(path: &Path) -> Option<String> {
+    use lofty::prelude::*;
+
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+    let album = tag.album()?.trim().to_string();
+    if album.is_empty() {
+        return None;
+    }
+    let artist = tag
+        .get_string(&lofty::tag::ItemKey::AlbumArtist)
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    Some(format!("{}\u{0}{}", artist, album))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn run_analysis(
+    app_handle: &AppHandle,
+    full_paths: &[String],
+    write_tags: bool,
+    cancel: &AtomicBool,
+) -> LoudnessFinished {
+    let library = app_handle.state::<LibraryState>();
+    let total = full_paths.len();
+    let mut measured: Vec<(String, Option<String>, LoudnessBlocks)> = Vec::new();
+    let mut failed = 0;
+
+    for (index, full_path) in full_paths.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let path = Path::new(full_path);
+        let (result, error) = match measure_file(path, cancel) {
+            Ok(blocks) => {
+                let result = blocks.result();
+                measured.push((full_path.clone(), read_album_key(path), blocks));
+                (Some(result), None)
+            }
+            Err(e) => {
+                if !cancel.load(Ordering::SeqCst) {
+                    eprintln!("响度分析失败 {}: {}", full_path, e);
+                    failed += 1;
+                }
+                (None, Some(e))
+            }
+        };
+
+        let _ = app_handle.emit(
+            "loudness-progress",
+            LoudnessProgress {
+                current: index + 1,
+                total,
+                full_path: full_path.clone(),
+                result,
+                error,
+            },
+        );
+    }
+
+    let cancelled = cancel.load(Ordering::SeqCst);
+
+    // 同一专辑的曲目合并计算专辑响度；任务被取消时专辑可能不完整，不计算专辑结果
+    let mut albums: HashMap<&str, Vec<LoudnessBlocks>> = HashMap::new();
+    if !cancelled {
+        for (_, album_key, blocks) in &measured {
+            if let Some(album_key) = album_key {
+                albums
+                    .entry(album_key.as_str())
+                    .or_default()
+                    .push(blocks.clone());
+            }
+        }
+    }
+    let album_results: HashMap<&str, LoudnessResult> = albums
+        .iter()
+        .map(|(key, tracks)| (*key, LoudnessBlocks::combine(tracks)))
+        .collect();
+
+    let analyzed_at = now_secs();
+    let mut results = Vec::with_capacity(measured.len());
+    for (full_path, album_key, blocks) in &measured {
+        let album = album_key
+            .as_deref()
+            .and_then(|key| album_results.get(key))
+            .cloned();
+        results.push((
+            full_path.clone(),
+            TrackLoudness {
+                track: blocks.result(),
+                album,
+                analyzed_at,
+            },
+        ));
+    }
+
+    if write_tags {
+        for (full_path, loudness) in &results {
+            let replay_gain = ReplayGain {
+                track_gain: loudness.track.replay_gain,
+                track_peak: Some(loudness.track.true_peak),
+                album_gain: loudness.album.as_ref().and_then(|a| a.replay_gain),
+                album_peak: loudness.album.as_ref().map(|a| a.true_peak),
+            };
+            if let Err(e) = write_replay_gain(Path::new(full_path), &replay_gain) {
+                eprintln!("写入 ReplayGain 标签失败 {}: {}", full_path, e);
+            }
+        }
+    }
+
+    match library.lock() {
+        Ok(mut library) => {
+            for (full_path, loudness) in &results {
+                library.entry_mut(full_path).loudness = Some(loudness.clone());
+            }
+            if let Err(e) = library.save() {
+                eprintln!("{}", e);
+            }
+        }
+        Err(e) => eprintln!("更新曲库失败: {}", e),
+    }
+
+    LoudnessFinished {
+        cancelled,
+        analyzed: results.len(),
+        failed,
+    }
+}
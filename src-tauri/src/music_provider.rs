@@ -0,0 +1,434 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::State;
+
+use crate::audio_cache::AudioCache;
+use crate::http_client::{HttpClientState, HttpRequest};
+use crate::offline::OfflineManager;
+use crate::provider_kugou::KuGou;
+use crate::provider_netease::NetEase;
+use crate::provider_qq::QQ;
+use crate::stream_proxy::StreamProxy;
+
+// 歌单类型，与前端 Playlist 一致
+pub const NORMAL_PLAYLIST_TYPE: u8 = 0;
+pub const NORMAL_RADIO_PLAYLIST_TYPE: u8 = 1;
+pub const ANCHOR_RADIO_PLAYLIST_TYPE: u8 = 3;
+
+// 前端传入的字段类型不固定（如 id 可能是数字、歌词是对象、本地歌曲时长是小数），按 JSON 值宽松解析
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(value_string(&Value::deserialize(deserializer)?))
+}
+
+fn lenient_option_string<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(non_empty(value_string(&Value::deserialize(deserializer)?)))
+}
+
+fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(value_u64(&Value::deserialize(deserializer)?))
+}
+
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(value) => value,
+        Value::Number(number) => number.as_f64().is_some_and(|n| n != 0.0),
+        _ => false,
+    })
+}
+
+// 歌手、专辑等只有 id 和名称的信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamedRef {
+    #[serde(deserialize_with = "lenient_string")]
+    pub id: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub name: String,
+}
+
+// 歌曲，字段与前端 Track 一致
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Track {
+    #[serde(deserialize_with = "lenient_string")]
+    pub id: String,
+    pub platform: String,
+    #[serde(deserialize_with = "lenient_string")]
+    pub title: String,
+    pub artist: Vec<NamedRef>,
+    pub album: NamedRef,
+    // 毫秒
+    #[serde(deserialize_with = "lenient_u64")]
+    pub duration: u64,
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub cover: Option<String>,
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub url: Option<String>,
+    // 所属歌单 id
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub pid: Option<String>,
+    // MV id
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub mv: Option<String>,
+    // 歌词原文（LRC），前端解析后的歌词对象会被忽略
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub lyric: Option<String>,
+    // 酷狗等平台获取播放地址需要的文件哈希
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub hash: Option<String>,
+    // 网易云主播电台节目对应的歌曲 id，获取播放地址时使用
+    #[serde(deserialize_with = "lenient_option_string")]
+    pub songlist_id: Option<String>,
+    // VIP 付费信息
+    #[serde(deserialize_with = "lenient_bool")]
+    pub pay_play: bool,
+    #[serde(deserialize_with = "lenient_bool")]
+    pub pay_download: bool,
+}
+
+impl Track {
+    pub fn new(id: impl Into<String>, platform: &str) -> Self {
+        Track {
+            id: id.into(),
+            platform: platform.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+// 歌单，字段与前端 Playlist 一致；列表中的歌单不包含歌曲
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: String,
+    pub platform: String,
+    pub cover: Option<String>,
+    pub title: String,
+    pub url: Option<String>,
+    pub about: String,
+    pub data: Vec<Track>,
+    pub total: u64,
+    #[serde(rename = "type")]
+    pub playlist_type: u8,
+    // 播放量
+    pub listen_num: Option<u64>,
+}
+
+impl Playlist {
+    pub fn new(id: impl Into<String>, platform: &str, title: impl Into<String>) -> Self {
+        Playlist {
+            id: id.into(),
+            platform: platform.to_string(),
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+}
+
+// 分类项，key 为显示名称，value 为请求歌单广场时使用的值
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryItem {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Category {
+    pub name: String,
+    pub data: Vec<CategoryItem>,
+}
+
+impl Category {
+    pub fn new(name: impl Into<String>) -> Self {
+        Category {
+            name: name.into(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.data.push(CategoryItem {
+            key: key.into(),
+            value: value.into(),
+        });
+    }
+}
+
+// 歌单广场的一页
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistPage {
+    pub platform: String,
+    pub cate: String,
+    pub offset: u32,
+    pub limit: u32,
+    // 总页数
+    pub total: u64,
+    pub data: Vec<Playlist>,
+}
+
+// 搜索结果的一页
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub platform: String,
+    pub keyword: String,
+    pub offset: u32,
+    pub limit: u32,
+    // 结果总数
+    pub total: u64,
+    pub data: Vec<Track>,
+}
+
+fn unsupported(name: &str, operation: &str) -> String {
+    format!("{} 不支持{}", name, operation)
+}
+
+// 音乐平台，请求都通过共享的 HTTP 客户端发送
+#[async_trait]
+pub trait MusicProvider: Send + Sync {
+    // 平台编码，与前端 vendor 的 CODE 一致
+    fn code(&self) -> &'static str;
+
+    // 显示名称
+    fn name(&self) -> &'static str;
+
+    // 歌单分类
+    async fn categories(&self, _http: &HttpClientState) -> Result<Vec<Category>, String> {
+        Err(unsupported(self.name(), "歌单分类"))
+    }
+
+    // 歌单广场，cate 为分类项的 value，为空时使用默认分类
+    async fn square(
+        &self,
+        _http: &HttpClientState,
+        _cate: &str,
+        _offset: u32,
+        _limit: u32,
+    ) -> Result<PlaylistPage, String> {
+        Err(unsupported(self.name(), "歌单广场"))
+    }
+
+    // 排行榜列表
+    async fn toplist(&self, _http: &HttpClientState) -> Result<Vec<Playlist>, String> {
+        Err(unsupported(self.name(), "排行榜"))
+    }
+
+    // 搜索歌曲
+    async fn search(
+        &self,
+        http: &HttpClientState,
+        keyword: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<SearchResult, String>;
+
+    // 歌单详情（包括排行榜），歌曲从 offset 开始最多 limit 首
+    async fn playlist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String>;
+
+    // 歌曲播放详情：播放地址，以及平台能提供的封面、歌词等
+    async fn play_detail(&self, http: &HttpClientState, track: &Track) -> Result<Track, String>;
+}
+
+// 已注册的音乐平台
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn MusicProvider>>,
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        ProviderRegistry {
+            providers: vec![Arc::new(NetEase), Arc::new(QQ), Arc::new(KuGou)],
+        }
+    }
+}
+
+impl ProviderRegistry {
+    pub fn get(&self, code: &str) -> Result<Arc<dyn MusicProvider>, String> {
+        self.providers
+            .iter()
+            .find(|provider| provider.code() == code)
+            .cloned()
+            .ok_or_else(|| format!("未知的音乐平台: {}", code))
+    }
+
+    pub fn all(&self) -> &[Arc<dyn MusicProvider>] {
+        &self.providers
+    }
+}
+
+// 发送请求并解析 JSON 响应
+pub(crate) async fn fetch_json(
+    http: &HttpClientState,
+    request: HttpRequest,
+) -> Result<Value, String> {
+    let response = http.execute(request).await?;
+    serde_json::from_str(response.body.trim()).map_err(|e| format!("解析响应失败: {}", e))
+}
+
+// 平台返回的 id 有时是数字有时是字符串，统一转为字符串
+pub(crate) fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Number(number) => number.to_string(),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn value_u64(value: &Value) -> u64 {
+    match value {
+        Value::Number(number) => number
+            .as_u64()
+            .or_else(|| number.as_f64().map(|n| n.max(0.0) as u64))
+            .unwrap_or(0),
+        Value::String(text) => text.trim().parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// 取数组字段，不存在时说明响应格式与预期不符
+pub(crate) fn value_array<'a>(value: &'a Value, what: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .as_array()
+        .ok_or_else(|| format!("响应格式错误: 缺少{}", what))
+}
+
+pub(crate) fn non_empty(text: String) -> Option<String> {
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProviderInfo {
+    code: &'static str,
+    name: &'static str,
+}
+
+// 已注册的音乐平台
+#[tauri::command]
+pub fn list_providers(registry: State<'_, ProviderRegistry>) -> Vec<ProviderInfo> {
+    registry
+        .all()
+        .iter()
+        .map(|provider| ProviderInfo {
+            code: provider.code(),
+            name: provider.name(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn provider_categories(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    platform: String,
+) -> Result<Vec<Category>, String> {
+    registry.get(&platform)?.categories(&http).await
+}
+
+#[tauri::command]
+pub async fn provider_square(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    platform: String,
+    cate: Option<String>,
+    offset: u32,
+    limit: u32,
+) -> Result<PlaylistPage, String> {
+    let cate = cate.unwrap_or_default();
+    registry
+        .get(&platform)?
+        .square(&http, &cate, offset, limit)
+        .await
+}
+
+#[tauri::command]
+pub async fn provider_toplist(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    platform: String,
+) -> Result<Vec<Playlist>, String> {
+    registry.get(&platform)?.toplist(&http).await
+}
+
+#[tauri::command]
+pub async fn provider_search(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    platform: String,
+    keyword: String,
+    offset: u32,
+    limit: u32,
+) -> Result<SearchResult, String> {
+    let keyword = keyword.trim();
+    if keyword.is_empty() {
+        return Err("搜索关键字不能为空".to_string());
+    }
+    registry
+        .get(&platform)?
+        .search(&http, keyword, offset, limit)
+        .await
+}
+
+#[tauri::command]
+pub async fn provider_playlist_detail(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    offline: State<'_, OfflineManager>,
+    platform: String,
+    id: String,
+    offset: u32,
+    limit: u32,
+) -> Result<Playlist, String> {
+    // 离线模式下优先使用离线歌单保存的完整详情
+    if http.settings().offline {
+        if let Some(mut playlist) = offline.playlist(&platform, id.trim()) {
+            playlist.data = playlist
+                .data
+                .into_iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .collect();
+            return Ok(playlist);
+        }
+    }
+    registry
+        .get(&platform)?
+        .playlist_detail(&http, id.trim(), offset, limit)
+        .await
+}
+
+// 获取歌曲的播放地址，返回的 Track 只包含平台提供的播放信息
+#[tauri::command]
+pub async fn provider_play_url(
+    registry: State<'_, ProviderRegistry>,
+    http: State<'_, HttpClientState>,
+    proxy: State<'_, StreamProxy>,
+    cache: State<'_, AudioCache>,
+    track: Track,
+) -> Result<Track, String> {
+    // 离线模式下只能播放已缓存的音频，返回本机代理的地址
+    if http.settings().offline {
+        let url = proxy
+            .cached_stream_url(&cache, &track.platform, &track.id)
+            .ok_or_else(|| format!("离线模式下歌曲未缓存: {}", track.title))?;
+        let mut result = Track::new(track.id.clone(), &track.platform);
+        result.url = Some(url);
+        return Ok(result);
+    }
+    registry
+        .get(&track.platform)?
+        .play_detail(&http, &track)
+        .await
+}
@@ -0,0 +1,283 @@
+use aes::cipher::{block_padding::Pkcs7, BlockEncryptMut, KeyInit, KeyIvInit};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use md5::{Digest, Md5};
+use num_bigint::BigUint;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::http_client::{HttpBody, HttpClientState, HttpRequest};
+
+type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+type Aes128EcbEnc = ecb::Encryptor<aes::Aes128>;
+
+const WEAPI_BASE_URL: &str = "https://music.163.com/weapi";
+const EAPI_BASE_URL: &str = "https://interface.music.163.com/eapi";
+
+// weapi 常量，与网页版一致
+const WEAPI_NONCE: &[u8; 16] = b"0CoJUm6Qyw8W8jud";
+const WEAPI_IV: &[u8; 16] = b"0102030405060708";
+const WEAPI_PUBLIC_EXPONENT: &str = "010001";
+const WEAPI_MODULUS: &str = concat!(
+    "00e0b509f6259df8642dbc35662901477df22677ec152b5ff68ace615bb7b72",
+    "5152b3ab17a876aea8a5aa76d2e417629ec4ee341f56135fccf695280104e0312ecbd",
+    "a92557c93870114af6c9d05c4f7f0c3685b7a46bee255932575cce10b424d813cfe48",
+    "75d3e82047b97ddef52741d546b8e289dc6935b3ece0462db0a22b8e7"
+);
+// 随机密钥的字符集
+const SECRET_KEY_CHARS: &[u8] = b"0123456789abcdef";
+
+// eapi 常量，与客户端一致
+const EAPI_KEY: &[u8; 16] = b"e82ckenh8dichen8";
+const EAPI_SEPARATOR: &str = "-36cd479b6b5-";
+
+const HEADER_REFERER: &str = "https://music.163.com/";
+
+// 加密方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NeteaseCrypto {
+    // 网页版接口
+    #[default]
+    Weapi,
+    // 客户端接口
+    Eapi,
+}
+
+// weapi 加密结果，作为表单的 params 和 encSecKey 提交
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeapiForm {
+    pub params: String,
+    pub enc_sec_key: String,
+}
+
+fn aes_cbc_base64(data: &[u8], key: &[u8; 16], iv: &[u8; 16]) -> String {
+    let encrypted = Aes128CbcEnc::new(key.into(), iv.into()).encrypt_padded_vec_mut::<Pkcs7>(data);
+    STANDARD.encode(encrypted)
+}
+
+// 不填充的 RSA：密钥倒序后直接做模幂运算，结果为 256 位十六进制
+fn rsa_encrypt(data: &[u8]) -> String {
+    let reversed: Vec<u8> = data.iter().rev().copied().collect();
+    let exponent = BigUint::parse_bytes(WEAPI_PUBLIC_EXPONENT.as_bytes(), 16).unwrap_or_default();
+    let modulus = BigUint::parse_bytes(WEAPI_MODULUS.as_bytes(), 16).unwrap_or_default();
+    let encrypted = BigUint::from_bytes_be(&reversed).modpow(&exponent, &modulus);
+    format!("{:0>256}", encrypted.to_str_radix(16))
+}
+
+fn random_secret_key() -> [u8; 16] {
+    let mut rng = rand::thread_rng();
+    let mut key = [0u8; 16];
+    for byte in key.iter_mut() {
+        *byte = SECRET_KEY_CHARS[rng.gen_range(0..SECRET_KEY_CHARS.len())];
+    }
+    key
+}
+
+// 使用指定的随机密钥加密，便于对照已知结果
+pub fn weapi_with_key(text: &str, secret_key: &[u8; 16]) -> WeapiForm {
+    let first = aes_cbc_base64(text.as_bytes(), WEAPI_NONCE, WEAPI_IV);
+    WeapiForm {
+        params: aes_cbc_base64(first.as_bytes(), secret_key, WEAPI_IV),
+        enc_sec_key: rsa_encrypt(secret_key),
+    }
+}
+
+// weapi：两次 AES-CBC 加密请求内容，随机密钥通过 RSA 加密后一起提交
+pub fn weapi(text: &str) -> WeapiForm {
+    weapi_with_key(text, &random_secret_key())
+}
+
+// eapi：对 接口路径-内容-摘要 做 AES-ECB 加密，结果为大写十六进制；api_path 形如 /api/song/detail
+pub fn eapi(api_path: &str, text: &str) -> String {
+    let message = format!("nobody{}use{}md5forencrypt", api_path, text);
+    let digest = format!("{:x}", Md5::digest(message.as_bytes()));
+    let data = format!(
+        "{}{}{}{}{}",
+        api_path, EAPI_SEPARATOR, text, EAPI_SEPARATOR, digest
+    );
+    let encrypted =
+        Aes128EcbEnc::new(EAPI_KEY.into()).encrypt_padded_vec_mut::<Pkcs7>(data.as_bytes());
+    hex::encode_upper(encrypted)
+}
+
+// 去掉 /weapi、/eapi、/api 前缀，返回接口路径和查询字符串
+fn split_api_path(path: &str) -> (String, &str) {
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (path, ""),
+    };
+    let path = path.trim().trim_start_matches('/');
+    let path = ["weapi/", "eapi/", "api/"]
+        .iter()
+        .find_map(|prefix| path.strip_prefix(prefix))
+        .unwrap_or(path);
+    (format!("/{}", path), query)
+}
+
+// 按 crypto 指定的方式加密参数后请求网易云音乐接口，返回解析后的 JSON
+// offline_cache 为 true 时保存响应供离线模式使用，播放地址有时效，不应保存
+pub async fn request(
+    http: &HttpClientState,
+    path: &str,
+    payload: &Value,
+    crypto: NeteaseCrypto,
+    request_id: Option<String>,
+    offline_cache: bool,
+) -> Result<Value, String> {
+    let (api_path, query) = split_api_path(path);
+    // 已经序列化的参数直接使用
+    let text = match payload {
+        Value::String(text) => text.clone(),
+        Value::Null => "{}".to_string(),
+        payload => serde_json::to_string(payload).map_err(|e| format!("序列化参数失败: {}", e))?,
+    };
+    let (base_url, form) = match crypto {
+        NeteaseCrypto::Weapi => {
+            let form = weapi(&text);
+            (
+                WEAPI_BASE_URL,
+                HashMap::from([
+                    ("params".to_string(), Value::String(form.params)),
+                    ("encSecKey".to_string(), Value::String(form.enc_sec_key)),
+                ]),
+            )
+        }
+        NeteaseCrypto::Eapi => {
+            let params = eapi(&format!("/api{}", api_path), &text);
+            (
+                EAPI_BASE_URL,
+                HashMap::from([("params".to_string(), Value::String(params))]),
+            )
+        }
+    };
+    let mut url = format!("{}{}", base_url, api_path);
+    if !query.is_empty() {
+        url.push('?');
+        url.push_str(query);
+    }
+
+    let mut request = HttpRequest::new("POST", &url);
+    request.headers = HashMap::from([
+        ("Referer".to_string(), HEADER_REFERER.to_string()),
+        ("Origin".to_string(), HEADER_REFERER.to_string()),
+    ]);
+    request.body = Some(HttpBody::Form(form));
+    // 加密参数每次都不同，按明文参数生成缓存键，录制的响应也能回放
+    request.cache_key = Some(format!("netease {:?} {} {}", crypto, url, text));
+    request.request_id = request_id;
+    request.offline_cache = offline_cache;
+
+    let response = http.execute(request).await?;
+    serde_json::from_str(&response.body).map_err(|e| format!("解析网易云音乐响应失败: {}", e))
+}
+
+// 请求网易云音乐接口：payload 为请求参数，按 crypto 指定的方式（默认 weapi）加密后提交，返回解析后的 JSON
+#[tauri::command]
+pub async fn netease_request(
+    http: State<'_, HttpClientState>,
+    path: String,
+    payload: Value,
+    crypto: Option<NeteaseCrypto>,
+    request_id: Option<String>,
+    offline_cache: Option<bool>,
+) -> Result<Value, String> {
+    request(
+        &http,
+        &path,
+        &payload,
+        crypto.unwrap_or_default(),
+        request_id,
+        offline_cache.unwrap_or(false),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // weapi 的期望结果由已删除的 src/vendor/netease.js 中的 weapi 按相同的明文和密钥计算得到
+    #[test]
+    fn weapi_matches_js_output() {
+        let form = weapi_with_key(
+            r#"{"ids":[347230],"level":"standard","encodeType":"aac","csrf_token":""}"#,
+            b"0123456789abcdef",
+        );
+        assert_eq!(
+            form.params,
+            concat!(
+                "4Q+uP0y5upYh5M2vPLml0lB3wc//uqnNAkvWGTllDKEjosR4ppsGXPWRqpsInsvn2XwLh/tmsz0EjOFL",
+                "gHcSHWyELLSWmB/xE/wCO7lzx6efeWab7sraecbmBmjeR1RZJ3dnj2MqVSmK4QoSGk+HBQ=="
+            )
+        );
+        assert_eq!(
+            form.enc_sec_key,
+            concat!(
+                "35701388baf89fed412e11269b9c76625d095ecaf17f03fa018abe19ea2d38b949debf242ee39a71",
+                "ca1f6cda71b1b86a45aa909ee27f7e78e267d34e732f0de948206c3340a788d0003372183e2f753c",
+                "1f78b66ac23d134ac1fc9b993156520ea826b8aa89a962d4491b4b8d7e08738e1da9b07aa39bf4a7",
+                "ef0b1c210728cd52"
+            )
+        );
+    }
+
+    #[test]
+    fn weapi_matches_js_output_for_utf8_text() {
+        let form = weapi_with_key(r#"{"s":"晴天","type":1,"limit":30}"#, b"aaaabbbbccccdddd");
+        assert_eq!(
+            form.params,
+            concat!(
+                "DpNbZXts+vULHIhQUsc6k8wuerNusDDFnoi6v1SyHwcRFQFAOmUjt2pzeO9LTyvo/JZV/Pj0j2eRqe4P",
+                "MFEeXiiaE3fyAjmRLo8ZgSbka5g="
+            )
+        );
+        assert_eq!(
+            form.enc_sec_key,
+            concat!(
+                "814e4abf9c1c6a2af74a7ecca8843f3052626c5c054584352e3fd38a519bd659e687cf1c079e1aac",
+                "5dd9d491af6b8abf92109862ada93dc7b0ef94a8ee79d557ff2a20512b87ce507e357861366b8542",
+                "139c67896748852d4086104a8dfc99a2e2e0640b46a4357407b72407b2849b323425c6ed45a0222e",
+                "69d551a2e59e15b7"
+            )
+        );
+    }
+
+    #[test]
+    fn rsa_encrypt_pads_to_256_hex_digits() {
+        let encrypted = rsa_encrypt(b"0000000000000001");
+        assert_eq!(encrypted.len(), 256);
+        assert_eq!(rsa_encrypt(b"0123456789abcdef").len(), 256);
+    }
+
+    // netease.js 没有 eapi，期望结果按客户端的算法（AES-128-ECB、大写十六进制）用 Node 的 crypto 计算
+    #[test]
+    fn eapi_matches_reference_output() {
+        assert_eq!(
+            eapi(
+                "/api/song/enhance/player/url",
+                r#"{"ids":"[347230]","br":999000}"#
+            ),
+            concat!(
+                "FA90B329E9614F79E79598F37DC2EDB430F8378D2A2796338F0BFDEAEF824A22975CDA9D96D79E6D",
+                "C4A59218CDB8199FE42FBA647FBC921E93931904946F12F96AC448186463D7DEDFC2FE055C22224E",
+                "009BDE371170404A117AF58CC6493039870B9E718CC604C9C3190067887FC276672190AD54C967EF",
+                "C8886DA405DEFCBD"
+            )
+        );
+    }
+
+    #[test]
+    fn split_api_path_strips_prefix() {
+        assert_eq!(
+            split_api_path("/weapi/v3/playlist/detail?csrf_token="),
+            ("/v3/playlist/detail".to_string(), "csrf_token=")
+        );
+        assert_eq!(
+            split_api_path("api/song/detail"),
+            ("/song/detail".to_string(), "")
+        );
+    }
+}
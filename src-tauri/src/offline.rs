@@ -0,0 +1,605 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio_cache::AudioCache;
+use crate::http_client::HttpClientState;
+use crate::music_provider::{MusicProvider, Playlist, ProviderRegistry, Track};
+use crate::stream_proxy::prefetch_audio;
+
+const OFFLINE_FILE_NAME: &str = "offline.json";
+// 分页获取歌单详情时每页的歌曲数
+const PLAYLIST_PAGE_SIZE: u32 = 500;
+
+// 标记为离线可用的歌单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflinePlaylist {
+    pub platform: String,
+    pub id: String,
+    // 最近一次同步的歌单详情，包含全部歌曲
+    #[serde(default)]
+    pub playlist: Option<Playlist>,
+    pub added_at: i64,
+    #[serde(default)]
+    pub synced_at: Option<i64>,
+    // 最近一次同步的错误
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl OfflinePlaylist {
+    fn is(&self, platform: &str, id: &str) -> bool {
+        self.platform == platform && self.id == id
+    }
+
+    fn tracks(&self) -> &[Track] {
+        self.playlist
+            .as_ref()
+            .map(|playlist| playlist.data.as_slice())
+            .unwrap_or_default()
+    }
+
+    // 平台限制了可获取的歌曲数时，歌单中没有获取到的歌曲数
+    fn missing(&self) -> usize {
+        self.playlist.as_ref().map_or(0, |playlist| {
+            (playlist.total as usize).saturating_sub(playlist.data.len())
+        })
+    }
+}
+
+// 离线歌单的概况
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflinePlaylistInfo {
+    pub platform: String,
+    pub id: String,
+    pub title: String,
+    pub cover: Option<String>,
+    pub total: usize,
+    // 音频已完整缓存的歌曲数
+    pub ready: usize,
+    // 歌单中没有获取到、不能离线播放的歌曲数
+    pub missing: usize,
+    pub added_at: i64,
+    pub synced_at: Option<i64>,
+    pub error: Option<String>,
+    pub syncing: bool,
+}
+
+// 歌曲的离线状态
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineTrackStatus {
+    pub platform: String,
+    pub id: String,
+    // 音频已完整缓存，离线时可以播放
+    pub ready: bool,
+    pub cached_bytes: u64,
+    pub total_bytes: Option<u64>,
+    // 是否在离线歌单中
+    pub pinned: bool,
+}
+
+// 同步进度事件
+#[derive(Clone, Serialize)]
+struct OfflineSyncProgress<'a> {
+    platform: &'a str,
+    id: &'a str,
+    done: usize,
+    total: usize,
+    failed: usize,
+    finished: bool,
+    error: Option<&'a str>,
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn sync_key(platform: &str, id: &str) -> String {
+    format!("{}:{}", platform, id)
+}
+
+// 离线歌单，持久化到应用数据目录
+pub struct OfflineManager {
+    file: PathBuf,
+    playlists: Mutex<Vec<OfflinePlaylist>>,
+    // 正在同步的歌单
+    syncing: Mutex<HashSet<String>>,
+}
+
+impl OfflineManager {
+    pub fn load(data_dir: &Path) -> Self {
+        let file = data_dir.join(OFFLINE_FILE_NAME);
+        let playlists = match fs::read_to_string(&file) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                eprintln!("解析离线歌单失败 {:?}: {}", file, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        OfflineManager {
+            file,
+            playlists: Mutex::new(playlists),
+            syncing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn save(&self, playlists: &[OfflinePlaylist]) -> Result<(), String> {
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+        }
+        let content =
+            serde_json::to_string(playlists).map_err(|e| format!("序列化离线歌单失败: {}", e))?;
+        let tmp_file = self.file.with_extension("json.tmp");
+        fs::write(&tmp_file, content).map_err(|e| format!("保存离线歌单失败: {}", e))?;
+        fs::rename(&tmp_file, &self.file).map_err(|e| format!("保存离线歌单失败: {}", e))
+    }
+
+    // 修改离线歌单并保存
+    fn update<R>(
+        &self,
+        f: impl FnOnce(&mut Vec<OfflinePlaylist>) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut playlists = self.playlists.lock().map_err(|e| e.to_string())?;
+        let result = f(&mut playlists)?;
+        self.save(&playlists)?;
+        Ok(result)
+    }
+
+    fn snapshot(&self) -> Vec<OfflinePlaylist> {
+        match self.playlists.lock() {
+            Ok(playlists) => playlists.clone(),
+            Err(e) => e.into_inner().clone(),
+        }
+    }
+
+    fn contains(&self, platform: &str, id: &str) -> bool {
+        self.snapshot()
+            .iter()
+            .any(|playlist| playlist.is(platform, id))
+    }
+
+    // 已同步的歌单详情
+    pub fn playlist(&self, platform: &str, id: &str) -> Option<Playlist> {
+        self.snapshot()
+            .into_iter()
+            .find(|playlist| playlist.is(platform, id))
+            .and_then(|playlist| playlist.playlist)
+    }
+
+    // 离线歌单中全部歌曲的缓存键
+    fn pinned_keys(&self) -> HashSet<String> {
+        self.snapshot()
+            .iter()
+            .flat_map(|playlist| {
+                playlist
+                    .tracks()
+                    .iter()
+                    .map(|track| AudioCache::key_for(&track.platform, &track.id))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn is_syncing(&self, platform: &str, id: &str) -> bool {
+        self.syncing
+            .lock()
+            .is_ok_and(|syncing| syncing.contains(&sync_key(platform, id)))
+    }
+
+    // 已经在同步时返回 false
+    fn start_sync(&self, platform: &str, id: &str) -> bool {
+        self.syncing
+            .lock()
+            .is_ok_and(|mut syncing| syncing.insert(sync_key(platform, id)))
+    }
+
+    fn finish_sync(&self, platform: &str, id: &str) {
+        if let Ok(mut syncing) = self.syncing.lock() {
+            syncing.remove(&sync_key(platform, id));
+        }
+    }
+}
+
+fn emit_progress(app_handle: &AppHandle, progress: OfflineSyncProgress) {
+    let _ = app_handle.emit("offline-sync-progress", progress);
+}
+
+// 获取播放地址并下载完整音频
+async fn prefetch_track(
+    http: &HttpClientState,
+    registry: &ProviderRegistry,
+    cache: &AudioCache,
+    track: &Track,
+) -> Result<(), String> {
+    let detail = registry
+        .get(&track.platform)?
+        .play_detail(http, track)
+        .await?;
+    let url = detail
+        .url
+        .ok_or_else(|| format!("没有播放地址: {}", track.title))?;
+    prefetch_audio(http, cache, &track.platform, &track.id, &url, true).await
+}
+
+// 分页获取完整的歌单详情，平台不再返回新的歌曲时停止
+async fn fetch_playlist(
+    http: &HttpClientState,
+    provider: &dyn MusicProvider,
+    id: &str,
+) -> Result<Playlist, String> {
+    let mut playlist = provider
+        .playlist_detail(http, id, 0, PLAYLIST_PAGE_SIZE)
+        .await?;
+    let mut ids: HashSet<String> = playlist.data.iter().map(|track| track.id.clone()).collect();
+    while (playlist.data.len() as u64) < playlist.total {
+        let offset = playlist.data.len() as u32;
+        let page = provider
+            .playlist_detail(http, id, offset, PLAYLIST_PAGE_SIZE)
+            .await?;
+        let before = playlist.data.len();
+        for track in page.data {
+            if ids.insert(track.id.clone()) {
+                playlist.data.push(track);
+            }
+        }
+        if playlist.data.len() == before {
+            break;
+        }
+    }
+    if (playlist.data.len() as u64) < playlist.total {
+        eprintln!(
+            "歌单 {} {} 只获取到 {}/{} 首歌曲",
+            provider.code(),
+            id,
+            playlist.data.len(),
+            playlist.total
+        );
+    }
+    Ok(playlist)
+}
+
+// 同步歌单详情并缓存全部歌曲的音频，返回缓存失败的歌曲数
+async fn sync_tracks(app_handle: &AppHandle, platform: &str, id: &str) -> Result<usize, String> {
+    let http = app_handle.state::<HttpClientState>();
+    if http.settings().offline {
+        return Err("离线模式下无法同步歌单".to_string());
+    }
+    let registry = app_handle.state::<ProviderRegistry>();
+    let cache = app_handle.state::<AudioCache>();
+    let manager = app_handle.state::<OfflineManager>();
+
+    let playlist = fetch_playlist(&http, registry.get(platform)?.as_ref(), id).await?;
+    let tracks = playlist.data.clone();
+    manager.update(|playlists| {
+        let offline = playlists
+            .iter_mut()
+            .find(|playlist| playlist.is(platform, id))
+            .ok_or_else(|| format!("离线歌单不存在: {}", id))?;
+        offline.playlist = Some(playlist);
+        Ok(())
+    })?;
+    cache.set_pinned(&manager.pinned_keys());
+
+    let mut failed = 0;
+    for (index, track) in tracks.iter().enumerate() {
+        // 同步过程中歌单被移除时停止
+        if !manager.contains(platform, id) {
+            break;
+        }
+        if !cache.is_complete(&track.platform, &track.id) {
+            if let Err(e) = prefetch_track(&http, &registry, &cache, track).await {
+                eprintln!("缓存离线歌曲失败 {} {}: {}", track.platform, track.id, e);
+                failed += 1;
+            }
+        }
+        emit_progress(
+            app_handle,
+            OfflineSyncProgress {
+                platform,
+                id,
+                done: index + 1,
+                total: tracks.len(),
+                failed,
+                finished: false,
+                error: None,
+            },
+        );
+    }
+    Ok(failed)
+}
+
+// 在后台同步离线歌单，同一歌单同时只同步一次
+fn spawn_sync(app_handle: &AppHandle, platform: String, id: String) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let manager = app_handle.state::<OfflineManager>();
+        if !manager.start_sync(&platform, &id) {
+            return;
+        }
+        let result = sync_tracks(&app_handle, &platform, &id).await;
+        let error = match &result {
+            Ok(0) => None,
+            Ok(failed) => Some(format!("{} 首歌曲缓存失败", failed)),
+            Err(e) => Some(e.clone()),
+        };
+        let saved = manager.update(|playlists| {
+            if let Some(offline) = playlists
+                .iter_mut()
+                .find(|playlist| playlist.is(&platform, &id))
+            {
+                if result.is_ok() {
+                    offline.synced_at = Some(now_secs());
+                }
+                offline.error = error.clone();
+            }
+            Ok(())
+        });
+        if let Err(e) = saved {
+            eprintln!("{}", e);
+        }
+        manager.finish_sync(&platform, &id);
+
+        let total = manager
+            .playlist(&platform, &id)
+            .map_or(0, |playlist| playlist.data.len());
+        emit_progress(
+            &app_handle,
+            OfflineSyncProgress {
+                platform: &platform,
+                id: &id,
+                done: total,
+                total,
+                failed: *result.as_ref().unwrap_or(&0),
+                finished: true,
+                error: error.as_deref(),
+            },
+        );
+    });
+}
+
+#[tauri::command]
+pub fn get_offline_mode(http: State<'_, HttpClientState>) -> bool {
+    http.settings().offline
+}
+
+// 开启离线模式后平台接口只使用缓存，歌曲只能播放已缓存的音频
+#[tauri::command]
+pub fn set_offline_mode(http: State<'_, HttpClientState>, enabled: bool) -> Result<(), String> {
+    let mut settings = http.settings();
+    settings.offline = enabled;
+    http.update_settings(settings)
+}
+
+#[tauri::command]
+pub fn list_offline_playlists(
+    manager: State<'_, OfflineManager>,
+    cache: State<'_, AudioCache>,
+) -> Vec<OfflinePlaylistInfo> {
+    manager
+        .snapshot()
+        .into_iter()
+        .map(|offline| {
+            let tracks = offline.tracks();
+            OfflinePlaylistInfo {
+                title: offline
+                    .playlist
+                    .as_ref()
+                    .map(|playlist| playlist.title.clone())
+                    .unwrap_or_default(),
+                cover: offline
+                    .playlist
+                    .as_ref()
+                    .and_then(|playlist| playlist.cover.clone()),
+                total: tracks.len(),
+                ready: tracks
+                    .iter()
+                    .filter(|track| cache.is_complete(&track.platform, &track.id))
+                    .count(),
+                missing: offline.missing(),
+                syncing: manager.is_syncing(&offline.platform, &offline.id),
+                platform: offline.platform,
+                id: offline.id,
+                added_at: offline.added_at,
+                synced_at: offline.synced_at,
+                error: offline.error,
+            }
+        })
+        .collect()
+}
+
+// 标记歌单为离线可用，并在后台缓存歌单详情和全部歌曲的音频
+#[tauri::command]
+pub fn add_offline_playlist(
+    app_handle: AppHandle,
+    manager: State<'_, OfflineManager>,
+    registry: State<'_, ProviderRegistry>,
+    platform: String,
+    id: String,
+) -> Result<(), String> {
+    let id = id.trim().to_string();
+    registry.get(&platform)?;
+    manager.update(|playlists| {
+        if !playlists.iter().any(|playlist| playlist.is(&platform, &id)) {
+            playlists.push(OfflinePlaylist {
+                platform: platform.clone(),
+                id: id.clone(),
+                playlist: None,
+                added_at: now_secs(),
+                synced_at: None,
+                error: None,
+            });
+        }
+        Ok(())
+    })?;
+    spawn_sync(&app_handle, platform, id);
+    Ok(())
+}
+
+// 重新同步离线歌单，缓存新增的歌曲
+#[tauri::command]
+pub fn sync_offline_playlist(
+    app_handle: AppHandle,
+    manager: State<'_, OfflineManager>,
+    platform: String,
+    id: String,
+) -> Result<(), String> {
+    if !manager.contains(&platform, &id) {
+        return Err(format!("离线歌单不存在: {}", id));
+    }
+    spawn_sync(&app_handle, platform, id);
+    Ok(())
+}
+
+// 取消歌单的离线可用，已缓存的音频按普通缓存处理
+#[tauri::command]
+pub fn remove_offline_playlist(
+    manager: State<'_, OfflineManager>,
+    cache: State<'_, AudioCache>,
+    platform: String,
+    id: String,
+) -> Result<(), String> {
+    manager.update(|playlists| {
+        playlists.retain(|playlist| !playlist.is(&platform, &id));
+        Ok(())
+    })?;
+    cache.set_pinned(&manager.pinned_keys());
+    Ok(())
+}
+
+// 查询歌曲是否可以离线播放
+#[tauri::command]
+pub fn offline_track_status(
+    manager: State<'_, OfflineManager>,
+    cache: State<'_, AudioCache>,
+    tracks: Vec<Track>,
+) -> Vec<OfflineTrackStatus> {
+    let pinned = manager.pinned_keys();
+    tracks
+        .into_iter()
+        .map(|track| {
+            let key = AudioCache::key_for(&track.platform, &track.id);
+            let entry = cache.entry(&key);
+            OfflineTrackStatus {
+                ready: entry.as_ref().is_some_and(|entry| entry.complete),
+                cached_bytes: entry.as_ref().map_or(0, |entry| entry.size),
+                total_bytes: entry.as_ref().and_then(|entry| entry.total),
+                pinned: pinned.contains(&key),
+                platform: track.platform,
+                id: track.id,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music_provider::SearchResult;
+    use async_trait::async_trait;
+
+    // 歌单共有 total 首歌曲，平台最多只返回前 available 首
+    struct PagedProvider {
+        total: u64,
+        available: usize,
+        // 为 true 时忽略 offset，总是返回第一页
+        ignore_offset: bool,
+    }
+
+    #[async_trait]
+    impl MusicProvider for PagedProvider {
+        fn code(&self) -> &'static str {
+            "test"
+        }
+
+        fn name(&self) -> &'static str {
+            "测试"
+        }
+
+        async fn search(
+            &self,
+            _http: &HttpClientState,
+            _keyword: &str,
+            _offset: u32,
+            _limit: u32,
+        ) -> Result<SearchResult, String> {
+            Err("不支持搜索".to_string())
+        }
+
+        async fn playlist_detail(
+            &self,
+            _http: &HttpClientState,
+            id: &str,
+            offset: u32,
+            limit: u32,
+        ) -> Result<Playlist, String> {
+            let offset = if self.ignore_offset {
+                0
+            } else {
+                offset as usize
+            };
+            let mut playlist = Playlist::new(id, "test", "歌单");
+            playlist.total = self.total;
+            playlist.data = (offset..self.available.min(offset + limit as usize))
+                .map(|index| Track::new(index.to_string(), "test"))
+                .collect();
+            Ok(playlist)
+        }
+
+        async fn play_detail(
+            &self,
+            _http: &HttpClientState,
+            track: &Track,
+        ) -> Result<Track, String> {
+            Ok(track.clone())
+        }
+    }
+
+    fn http() -> HttpClientState {
+        let dir = std::env::temp_dir().join("offline_fetch_playlist");
+        HttpClientState::new(&dir, &dir, &dir)
+    }
+
+    #[test]
+    fn fetches_every_page() {
+        let provider = PagedProvider {
+            total: 1200,
+            available: 1200,
+            ignore_offset: false,
+        };
+        let playlist =
+            tauri::async_runtime::block_on(fetch_playlist(&http(), &provider, "1")).unwrap();
+        assert_eq!(playlist.data.len(), 1200);
+        assert_eq!(playlist.data[1199].id, "1199");
+    }
+
+    #[test]
+    fn reports_tracks_the_platform_withholds() {
+        for ignore_offset in [false, true] {
+            let provider = PagedProvider {
+                total: 1200,
+                available: 700,
+                ignore_offset,
+            };
+            let playlist =
+                tauri::async_runtime::block_on(fetch_playlist(&http(), &provider, "1")).unwrap();
+            let expected = if ignore_offset { 500 } else { 700 };
+            assert_eq!(playlist.data.len(), expected);
+            let offline = OfflinePlaylist {
+                platform: "test".to_string(),
+                id: "1".to_string(),
+                playlist: Some(playlist),
+                added_at: 0,
+                synced_at: None,
+                error: None,
+            };
+            assert_eq!(offline.missing(), 1200 - expected);
+        }
+    }
+}
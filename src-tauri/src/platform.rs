@@ -0,0 +1,45 @@
+// 音乐平台与其域名
+const PLATFORM_DOMAINS: &[(&str, &[&str])] = &[
+    ("netease", &["163.com", "126.net"]),
+    ("qq", &["qq.com", "gtimg.cn"]),
+    ("kugou", &["kugou.com", "kgimg.com"]),
+];
+// 不属于任何平台的域名
+pub const OTHER_PLATFORM: &str = "other";
+
+// 主机名是否属于该域名（相同或为其子域名）
+pub fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+// 根据主机名判断所属平台
+pub fn platform_for_host(host: &str) -> &'static str {
+    let host = host.to_ascii_lowercase();
+    PLATFORM_DOMAINS
+        .iter()
+        .find(|(_, domains)| domains.iter().any(|domain| domain_match(&host, domain)))
+        .map(|(platform, _)| *platform)
+        .unwrap_or(OTHER_PLATFORM)
+}
+
+// 是否为已知的平台名称
+pub fn is_known_platform(name: &str) -> bool {
+    PLATFORM_DOMAINS
+        .iter()
+        .any(|(platform, _)| *platform == name)
+}
+
+// 主机名是否匹配规则，规则可以是平台名称（netease、qq、kugou）、主机名或 *.example.com 形式的通配
+pub fn host_rule_matches(rule: &str, host: &str) -> bool {
+    let rule = rule.trim().to_ascii_lowercase();
+    if rule.is_empty() {
+        return false;
+    }
+    if is_known_platform(&rule) {
+        return platform_for_host(host) == rule;
+    }
+    match rule.strip_prefix("*.") {
+        Some(domain) => host.ends_with(&format!(".{}", domain)),
+        None => domain_match(host, rule.trim_start_matches('.')),
+    }
+}
@@ -0,0 +1,651 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::http_client::{HttpClientState, HttpRequest};
+use crate::music_provider::{
+    fetch_json, non_empty, value_array, value_string, value_u64, Category, MusicProvider, NamedRef,
+    Playlist, PlaylistPage, SearchResult, Track,
+};
+
+const CODE: &str = "kugou";
+const REFERER: &str = "https://www.kugou.com/";
+// 与前端 KuGou vendor 使用的设备信息一致
+const KG_MID: &str = "b1ce9c8ff7a5081551d9fe09a396d9c1";
+const KG_DFID: &str = "11TXg30ah9CE2JoRol2OeAmD";
+// 封面尺寸
+const COVER_SIZE: &str = "480";
+// 以下分类 id 与前端 KuGou vendor 一致
+const TOPLIST_CODE: &str = "0-0-0";
+const TOPLIST_PREFIX: &str = "TOP_";
+// 歌单分类和歌单广场的页面，参数直接拼接在路径之后
+const SQUARE_URL: &str =
+    "http://mac.kugou.com/v2/musicol/yueku/v1/special/index/getData/getData.html";
+// 歌单广场默认按推荐排序
+const SORT_RECOMMEND: u32 = 5;
+const RANK_API_URL: &str = "http://mobilecdnbj.kugou.com/api/v3/rank";
+// 排行榜接口每页最多返回的歌曲数
+const TOPLIST_PAGE_SIZE: u32 = 100;
+
+pub struct KuGou;
+
+// offline_cache 为 true 时保存响应供离线模式使用；播放地址会过期，不保存
+fn request(url: &str, query: Value, offline_cache: bool) -> HttpRequest {
+    let mut request = HttpRequest::new("GET", url);
+    request.headers = HashMap::from([
+        ("Referer".to_string(), REFERER.to_string()),
+        ("Origin".to_string(), REFERER.to_string()),
+    ]);
+    request.query = match query {
+        Value::Object(map) => Some(map.into_iter().collect()),
+        _ => None,
+    };
+    request.offline_cache = offline_cache;
+    request
+}
+
+// 换成较大尺寸的封面，与前端 getCustomCover 一致
+fn custom_cover(origin: &str) -> Option<String> {
+    let origin = origin.trim();
+    if origin.is_empty() {
+        return None;
+    }
+    let cover = if let Some((_, rest)) = origin
+        .split_once("/custom/150/")
+        .or_else(|| origin.split_once("/temppic/"))
+    {
+        format!("https://imgessl.kugou.com/custom/{}/{}", COVER_SIZE, rest)
+    } else if origin.contains("/{size}") {
+        origin.replace("/{size}", &format!("/{}", COVER_SIZE))
+    } else if let Some((_, rest)) = origin.split_once("/stdmusic/") {
+        format!("https://imge.kugou.com/stdmusic/{}/{}", COVER_SIZE, rest)
+    } else {
+        origin.to_string()
+    };
+    Some(cover)
+}
+
+fn authors(list: &Value) -> Vec<NamedRef> {
+    list.as_array()
+        .map(|list| {
+            list.iter()
+                .map(|author| NamedRef {
+                    id: value_string(&author["author_id"]),
+                    name: value_string(&author["author_name"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 页面脚本中 marker 之后的 JSON 值，如 var data=[...]
+fn json_after(text: &str, marker: &str) -> Option<Value> {
+    let start = text.find(marker)? + marker.len();
+    serde_json::Deserializer::from_str(text[start..].trim_start())
+        .into_iter::<Value>()
+        .next()?
+        .ok()
+}
+
+// 开始标签中的属性值
+fn attr_value(tag: &str, attr: &str) -> Option<String> {
+    let marker = format!(" {}=\"", attr);
+    let value = &tag[tag.find(&marker)? + marker.len()..];
+    Some(unescape_html(&value[..value.find('"')?]))
+}
+
+// 页面脚本中 marker 之后的整数，如 global.total = 100; 可能带引号
+fn number_after(text: &str, marker: &str) -> u64 {
+    let Some(start) = text.find(marker) else {
+        return 0;
+    };
+    let rest = text[start + marker.len()..]
+        .trim_start()
+        .trim_start_matches(['"', '\'']);
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    rest[..end].parse().unwrap_or(0)
+}
+
+// anchor 之后第一个 img 标签的属性值
+fn img_attr(html: &str, anchor: &str, attr: &str) -> Option<String> {
+    let rest = &html[html.find(anchor)?..];
+    let tag = &rest[rest.find("<img")?..];
+    attr_value(&tag[..tag.find('>')?], attr)
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+// 歌单分类页面：每组分类的名称在 h3 中，分类链接的 href 中 &c= 之后为分类值
+fn parse_categories(html: &str) -> Vec<Category> {
+    let start = html
+        .find("pc_specail_menu")
+        .or_else(|| html.find("pc_special_menu"))
+        .unwrap_or(html.len());
+    let mut result = Vec::new();
+    for group in html[start..].split("<h3").skip(1) {
+        let name = match group.split_once('>') {
+            Some((_, rest)) => rest[..rest.find("</h3>").unwrap_or(rest.len())].trim(),
+            None => continue,
+        };
+        let mut category = Category::new(unescape_html(name));
+        for link in group.split("<a ").skip(1) {
+            let Some(end) = link.find('>') else { continue };
+            let value = attr_value(&format!(" {}", &link[..end]), "href")
+                .and_then(|href| Some(href.split_once("&c=")?.1.split('\'').next()?.to_string()));
+            let text = &link[end + 1..];
+            let text = text[..text.find("</a>").unwrap_or(text.len())].trim();
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                category.add(unescape_html(text), value);
+            }
+        }
+        if !category.data.is_empty() {
+            result.push(category);
+        }
+    }
+    result
+}
+
+// 排行榜歌曲的 filename 为“歌手 - 歌名”，多个歌手以、分隔
+fn split_file_name(file_name: &str) -> (Vec<NamedRef>, String) {
+    match file_name.split_once(" - ") {
+        Some((singers, title)) => (
+            singers
+                .split('、')
+                .map(|name| NamedRef {
+                    id: String::new(),
+                    name: name.trim().to_string(),
+                })
+                .collect(),
+            title.trim().to_string(),
+        ),
+        None => (Vec::new(), file_name.trim().to_string()),
+    }
+}
+
+// anchor 所在元素的文本
+fn element_text(html: &str, anchor: &str) -> Option<String> {
+    let rest = &html[html.find(anchor)?..];
+    let text = &rest[rest.find('>')? + 1..];
+    Some(
+        text[..text.find('<').unwrap_or(text.len())]
+            .trim()
+            .to_string(),
+    )
+}
+
+impl KuGou {
+    // 排行榜作为歌单广场的一页返回
+    async fn toplist_page(
+        &self,
+        http: &HttpClientState,
+        cate: &str,
+    ) -> Result<PlaylistPage, String> {
+        let data = self.toplist(http).await?;
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset: 0,
+            limit: data.len() as u32,
+            total: 1,
+            data,
+        })
+    }
+
+    async fn toplist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String> {
+        let rank_id = id.trim_start_matches(TOPLIST_PREFIX);
+        let page_size = limit.clamp(1, TOPLIST_PAGE_SIZE);
+        let query = json!({
+            "version": 9108,
+            "ranktype": 1,
+            "plat": 0,
+            "area_code": 1,
+            "with_res_tag": 0,
+            "rankid": rank_id,
+            "page": offset / page_size + 1,
+            "pagesize": page_size,
+        });
+        let json = fetch_json(
+            http,
+            request(&format!("{}/song", RANK_API_URL), query, true),
+        )
+        .await?;
+        // 歌曲接口不返回排行榜信息，从排行榜列表中查找
+        let mut result = self
+            .toplist(http)
+            .await
+            .ok()
+            .and_then(|list| list.into_iter().find(|playlist| playlist.id == id))
+            .unwrap_or_else(|| Playlist::new(id, CODE, ""));
+        result.total = value_u64(&json["data"]["total"]);
+        for item in value_array(&json["data"]["info"], "歌曲列表")? {
+            let (artist, title) = split_file_name(&value_string(&item["filename"]));
+            let hash = value_string(&item["hash"]);
+            let track_id = non_empty(value_string(&item["audio_id"]))
+                .filter(|id| id != "0")
+                .unwrap_or_else(|| hash.clone());
+            let mut track = Track::new(track_id, CODE);
+            track.title = title;
+            track.artist = artist;
+            track.album = NamedRef {
+                id: value_string(&item["album_id"]),
+                name: value_string(&item["remark"]),
+            };
+            track.duration = value_u64(&item["duration"]) * 1000;
+            track.cover = custom_cover(&value_string(&item["album_sizable_cover"]));
+            track.hash = non_empty(hash);
+            track.pid = Some(id.to_string());
+            result.data.push(track);
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl MusicProvider for KuGou {
+    fn code(&self) -> &'static str {
+        CODE
+    }
+
+    fn name(&self) -> &'static str {
+        "酷狗音乐"
+    }
+
+    async fn categories(&self, http: &HttpClientState) -> Result<Vec<Category>, String> {
+        let url = format!("{}&cdn=cdn&t={}&c=", SQUARE_URL, SORT_RECOMMEND);
+        let html = http.execute(request(&url, Value::Null, true)).await?.body;
+        let mut result = parse_categories(&html);
+        if result.is_empty() {
+            return Err("响应格式错误: 缺少分类列表".to_string());
+        }
+        // 在第一个分类中加入排行榜；前端的电台分类没有实现，不加入
+        result[0].add("榜单", TOPLIST_CODE);
+        Ok(result)
+    }
+
+    async fn square(
+        &self,
+        http: &HttpClientState,
+        cate: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<PlaylistPage, String> {
+        if cate.trim() == TOPLIST_CODE {
+            return self.toplist_page(http, cate).await;
+        }
+        let limit = limit.max(1);
+        let url = format!(
+            "{}&cdn=cdn&p={}&pagesize={}&t={}&c={}",
+            SQUARE_URL,
+            offset / limit + 1,
+            limit,
+            SORT_RECOMMEND,
+            cate.trim()
+        );
+        let html = http.execute(request(&url, Value::Null, true)).await?.body;
+
+        // 歌单列表在页面脚本的 global.special = 中
+        let list = json_after(&html, "global.special =").unwrap_or(Value::Null);
+        let mut data = Vec::new();
+        for item in value_array(&list, "歌单列表")? {
+            let mut playlist = Playlist::new(
+                value_string(&item["specialid"]),
+                CODE,
+                value_string(&item["specialname"]),
+            );
+            playlist.cover = custom_cover(&value_string(&item["img"]));
+            playlist.about = value_string(&item["intro"]);
+            data.push(playlist);
+        }
+        let total = number_after(&html, "global.total =");
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset,
+            limit,
+            total: total.div_ceil(u64::from(limit)),
+            data,
+        })
+    }
+
+    async fn toplist(&self, http: &HttpClientState) -> Result<Vec<Playlist>, String> {
+        let query = json!({
+            "version": 9108,
+            "plat": 0,
+            "showtype": 2,
+            "parentid": 0,
+            "apiver": 6,
+            "area_code": 1,
+            "withsong": 0,
+        });
+        let json = fetch_json(
+            http,
+            request(&format!("{}/list", RANK_API_URL), query, true),
+        )
+        .await?;
+        let mut result = Vec::new();
+        for item in value_array(&json["data"]["info"], "排行榜列表")? {
+            let id = format!("{}{}", TOPLIST_PREFIX, value_string(&item["rankid"]));
+            let mut playlist = Playlist::new(id, CODE, value_string(&item["rankname"]));
+            playlist.cover = custom_cover(&value_string(&item["imgurl"]));
+            playlist.about = value_string(&item["intro"]);
+            result.push(playlist);
+        }
+        Ok(result)
+    }
+
+    async fn search(
+        &self,
+        http: &HttpClientState,
+        keyword: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<SearchResult, String> {
+        let limit = limit.max(1);
+        let query = json!({
+            "keyword": keyword,
+            "page": offset / limit + 1,
+            "pagesize": limit,
+            "platform": "WebFilter",
+            "format": "json",
+        });
+        let json = fetch_json(
+            http,
+            request("https://songsearch.kugou.com/song_search_v2", query, true),
+        )
+        .await?;
+        let mut data = Vec::new();
+        for item in json["data"]["lists"].as_array().into_iter().flatten() {
+            let hash = value_string(&item["FileHash"]);
+            let id = non_empty(value_string(&item["Audioid"]))
+                .filter(|id| id != "0")
+                .unwrap_or_else(|| hash.clone());
+            let mut track = Track::new(id, CODE);
+            track.title = value_string(&item["SongName"]);
+            track.artist = match item["Singers"].as_array() {
+                Some(singers) => singers
+                    .iter()
+                    .map(|singer| NamedRef {
+                        id: value_string(&singer["id"]),
+                        name: value_string(&singer["name"]),
+                    })
+                    .collect(),
+                None => value_string(&item["SingerName"])
+                    .split('、')
+                    .map(|name| NamedRef {
+                        id: String::new(),
+                        name: name.trim().to_string(),
+                    })
+                    .collect(),
+            };
+            track.album = NamedRef {
+                id: value_string(&item["AlbumID"]),
+                name: value_string(&item["AlbumName"]),
+            };
+            track.duration = value_u64(&item["Duration"]) * 1000;
+            track.cover = custom_cover(&value_string(&item["Image"]));
+            track.hash = non_empty(hash);
+            track.pay_play = value_u64(&item["PayType"]) != 0;
+            data.push(track);
+        }
+        Ok(SearchResult {
+            platform: CODE.to_string(),
+            keyword: keyword.to_string(),
+            offset,
+            limit,
+            total: value_u64(&json["data"]["total"]),
+            data,
+        })
+    }
+
+    async fn playlist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String> {
+        if id.starts_with(TOPLIST_PREFIX) {
+            return self.toplist_detail(http, id, offset, limit).await;
+        }
+        let url = format!("https://www.kugou.com/yy/special/single/{}.html", id);
+        let html = http.execute(request(&url, Value::Null, true)).await?.body;
+
+        // 歌单信息在 .specialPage 中
+        let page = html
+            .find("specialPage")
+            .map_or(html.as_str(), |i| &html[i..]);
+        let title = img_attr(page, "class=\"pic\"", "alt").unwrap_or_default();
+        let mut result = Playlist::new(id, CODE, title);
+        result.cover = img_attr(page, "class=\"pic\"", "_src").and_then(|src| custom_cover(&src));
+        result.about = element_text(page, "class=\"more_intro\"").unwrap_or_default();
+
+        // 歌曲列表在页面脚本的 var data= 中
+        let songs = json_after(&html, "var data=").unwrap_or(Value::Null);
+        let songs = value_array(&songs, "歌曲列表")?;
+        result.total = songs.len() as u64;
+        for item in songs.iter().skip(offset as usize).take(limit as usize) {
+            let mut track = Track::new(value_string(&item["audio_id"]), CODE);
+            track.title = value_string(&item["songname"]);
+            track.artist = authors(&item["authors"]);
+            track.album = NamedRef {
+                id: value_string(&item["album_id"]),
+                name: value_string(&item["album_name"]),
+            };
+            track.duration = value_u64(&item["duration"]);
+            track.cover = custom_cover(&value_string(&item["authors"][0]["sizable_avatar"]));
+            track.hash = non_empty(value_string(&item["hash"]));
+            track.pid = Some(id.to_string());
+            track.pay_play = value_u64(&item["vip"]) != 0;
+            result.data.push(track);
+        }
+        Ok(result)
+    }
+
+    async fn play_detail(&self, http: &HttpClientState, track: &Track) -> Result<Track, String> {
+        let hash = track
+            .hash
+            .as_deref()
+            .filter(|hash| !hash.is_empty())
+            .ok_or_else(|| format!("缺少歌曲的文件哈希: {}", track.id))?;
+        let query = json!({
+            "r": "play/getdata",
+            "hash": hash,
+            "dfid": KG_DFID,
+            "appid": 1014,
+            "mid": KG_MID,
+            "platid": 4,
+            "album_id": track.album.id,
+        });
+        let json = fetch_json(
+            http,
+            request("https://wwwapi.kugou.com/yy/index.php", query, false),
+        )
+        .await?;
+        let data = &json["data"];
+
+        let mut result = Track::new(track.id.clone(), CODE);
+        result.url = non_empty(value_string(&data["play_url"]));
+        result.cover = non_empty(value_string(&data["img"]));
+        result.lyric = non_empty(value_string(&data["lyrics"]));
+        let artist = authors(&data["authors"]);
+        if !artist.is_empty() {
+            result.artist = artist;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fixtures::replay_client;
+    use tauri::async_runtime::block_on;
+
+    #[test]
+    fn parses_categories() {
+        let html = r#"<div class="pc_specail_menu"><h3>热门</h3>
+            <div class="pc_specail_menu_content">
+            <a href="javascript:getData('&amp;c=11')">流行</a>
+            <a href="javascript:getData('&c=12')">经典 &amp; 怀旧</a></div></div>
+            <div class="pc_specail_menu"><h3>心情</h3>
+            <div class="pc_specail_menu_content"><a href="javascript:getData('&c=25')">伤感</a></div></div>"#;
+        let result = parse_categories(html);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].name, "热门");
+        let items: Vec<(&str, &str)> = result[0]
+            .data
+            .iter()
+            .map(|item| (item.key.as_str(), item.value.as_str()))
+            .collect();
+        assert_eq!(items, [("流行", "11"), ("经典 & 怀旧", "12")]);
+        assert_eq!(result[1].data[0].value, "25");
+        assert!(parse_categories("<html></html>").is_empty());
+    }
+
+    #[test]
+    fn parses_square() {
+        let http = replay_client("kugou_square");
+        let page = block_on(KuGou.square(&http, "11", 0, 2)).unwrap();
+        // total 为总页数
+        assert_eq!(page.total, 50);
+        assert_eq!(page.data.len(), 2);
+        let playlist = &page.data[0];
+        assert_eq!(playlist.id, "546903");
+        assert_eq!(playlist.title, "周杰伦精选");
+        assert_eq!(
+            playlist.cover.as_deref(),
+            Some("https://imgessl.kugou.com/custom/480/20250101/20250101120000123456.jpg")
+        );
+        assert_eq!(playlist.about, "周杰伦的经典歌曲");
+    }
+
+    // 排行榜详情的 id 带有 TOP_ 前缀，排行榜信息从排行榜列表中查找
+    #[test]
+    fn parses_toplist_detail() {
+        let http = replay_client("kugou_toplist");
+        let toplist = block_on(KuGou.toplist(&http)).unwrap();
+        assert_eq!(toplist.len(), 2);
+        assert_eq!(toplist[0].id, "TOP_8888");
+        assert_eq!(toplist[0].title, "酷狗TOP500");
+        assert_eq!(
+            toplist[0].cover.as_deref(),
+            Some("http://imge.kugou.com/mcommon/480/20181019/20181019125718523.png")
+        );
+        let page = block_on(KuGou.square(&http, TOPLIST_CODE, 0, 20)).unwrap();
+        assert_eq!(page.data.len(), 2);
+
+        let playlist = block_on(KuGou.playlist_detail(&http, "TOP_8888", 0, 2)).unwrap();
+        assert_eq!(playlist.title, "酷狗TOP500");
+        assert_eq!(playlist.total, 500);
+        assert_eq!(playlist.data.len(), 2);
+        let track = &playlist.data[0];
+        assert_eq!(track.id, "32218352");
+        assert_eq!(track.title, "晴天");
+        let names: Vec<&str> = track
+            .artist
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect();
+        assert_eq!(names, ["周杰伦"]);
+        assert_eq!(track.album.id, "960399");
+        assert_eq!(track.duration, 269000);
+        assert_eq!(track.pid.as_deref(), Some("TOP_8888"));
+        // 多个歌手以、分隔，没有 audio_id 时使用文件哈希
+        let track = &playlist.data[1];
+        assert_eq!(track.id, "3C3A7C5B6D5B2E4F9A8B7C6D5E4F3A2B");
+        assert_eq!(track.artist.len(), 2);
+    }
+
+    #[test]
+    fn parses_search() {
+        let http = replay_client("kugou_search");
+        let result = block_on(KuGou.search(&http, "晴天", 0, 2)).unwrap();
+        assert_eq!(result.total, 200);
+        assert_eq!(result.data.len(), 2);
+        let track = &result.data[0];
+        assert_eq!(track.id, "32218352");
+        assert_eq!(track.title, "晴天");
+        assert_eq!(track.artist[0].id, "3520");
+        assert_eq!(track.album.name, "叶惠美");
+        assert_eq!(track.duration, 269000);
+        assert_eq!(
+            track.cover.as_deref(),
+            Some("http://imge.kugou.com/stdmusic/480/20150718/20150718173412419592.jpg")
+        );
+        assert_eq!(
+            track.hash.as_deref(),
+            Some("C5A9F7A8C4B0E03E3D6A1B5B0C2AD0E1")
+        );
+        assert!(track.pay_play);
+        // 没有 Audioid 时使用文件哈希，没有 Singers 时按 SingerName 拆分
+        let track = &result.data[1];
+        assert_eq!(track.id, "8E6B5F9A1D2C3B4A5968778695A4B3C2");
+        let names: Vec<&str> = track
+            .artist
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect();
+        assert_eq!(names, ["周杰伦", "五月天"]);
+        assert_eq!(track.cover, None);
+        assert!(!track.pay_play);
+    }
+
+    // 歌单详情从网页中解析
+    #[test]
+    fn parses_playlist_detail() {
+        let http = replay_client("kugou_playlist");
+        let playlist = block_on(KuGou.playlist_detail(&http, "546903", 0, 2)).unwrap();
+        assert_eq!(playlist.title, "周杰伦精选");
+        assert_eq!(playlist.about, "周杰伦的经典歌曲");
+        assert_eq!(
+            playlist.cover.as_deref(),
+            Some("https://imgessl.kugou.com/custom/480/20250101/20250101120000123456.jpg")
+        );
+        assert_eq!(playlist.total, 3);
+        assert_eq!(playlist.data.len(), 2);
+        let track = &playlist.data[0];
+        assert_eq!(track.id, "32218352");
+        assert_eq!(track.artist[0].name, "周杰伦");
+        assert_eq!(track.album.id, "960399");
+        assert_eq!(track.duration, 269000);
+        assert_eq!(
+            track.hash.as_deref(),
+            Some("C5A9F7A8C4B0E03E3D6A1B5B0C2AD0E1")
+        );
+        assert!(track.pay_play);
+        assert!(!playlist.data[1].pay_play);
+    }
+
+    #[test]
+    fn parses_play_detail() {
+        let http = replay_client("kugou_play");
+        let mut track = Track::new("32218352", CODE);
+        track.hash = Some("1A0D5B8C7E2F4A0B9C8D7E6F5A4B3C2D".to_string());
+        track.album.id = "960399".to_string();
+        let result = block_on(KuGou.play_detail(&http, &track)).unwrap();
+        assert!(result
+            .url
+            .unwrap()
+            .ends_with("1a0d5b8c7e2f4a0b9c8d7e6f5a4b3c2d.mp3"));
+        assert!(result.lyric.unwrap().contains("故事的小黄花"));
+        assert_eq!(result.artist[0].name, "周杰伦");
+
+        // 没有文件哈希时无法获取播放地址
+        let result = block_on(KuGou.play_detail(&http, &Track::new("1", CODE)));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,420 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+
+use crate::http_client::HttpClientState;
+use crate::music_provider::{
+    non_empty, value_array, value_string, value_u64, Category, MusicProvider, NamedRef, Playlist,
+    PlaylistPage, SearchResult, Track, ANCHOR_RADIO_PLAYLIST_TYPE,
+};
+use crate::netease_api::{self, NeteaseCrypto};
+
+const CODE: &str = "netease";
+const BASE_URL: &str = "https://music.163.com";
+// 主播电台歌单 id 的前缀，与前端 Playlist.ANCHOR_RADIO_ID_PREFIX 一致
+const ANCHOR_RADIO_ID_PREFIX: &str = "ARP_";
+// 主播电台节目 id 的前缀，与前端 NetEase.RADIO_PREFIX 一致
+const RADIO_PROGRAM_PREFIX: &str = "DJR_";
+// 歌单广场的默认分类
+const DEFAULT_CATE: &str = "全部";
+// 单曲搜索
+const SEARCH_TYPE_SONG: u32 = 1;
+
+pub struct NetEase;
+
+// offline_cache 为 true 时保存响应供离线模式使用；播放地址会过期，不保存
+async fn weapi(
+    http: &HttpClientState,
+    path: &str,
+    payload: Value,
+    offline_cache: bool,
+) -> Result<Value, String> {
+    let json = netease_api::request(
+        http,
+        path,
+        &payload,
+        NeteaseCrypto::Weapi,
+        None,
+        offline_cache,
+    )
+    .await?;
+    match json["code"].as_i64() {
+        Some(200) | None => Ok(json),
+        Some(code) => Err(format!(
+            "网易云音乐接口返回错误 {}: {}",
+            code,
+            value_string(&json["message"])
+        )),
+    }
+}
+
+fn named_refs(list: &Value) -> Vec<NamedRef> {
+    list.as_array()
+        .map(|list| {
+            list.iter()
+                .map(|item| NamedRef {
+                    id: value_string(&item["id"]),
+                    name: value_string(&item["name"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 新版接口的歌曲格式：ar、al、dt
+fn parse_song(song: &Value) -> Track {
+    let mut track = Track::new(value_string(&song["id"]), CODE);
+    track.title = value_string(&song["name"]);
+    track.artist = named_refs(&song["ar"]);
+    track.album = NamedRef {
+        id: value_string(&song["al"]["id"]),
+        name: value_string(&song["al"]["name"]),
+    };
+    track.duration = value_u64(&song["dt"]);
+    track.cover = non_empty(value_string(&song["al"]["picUrl"]));
+    track.mv = non_empty(value_string(&song["mv"])).filter(|mv| mv != "0");
+    // fee 为 1 时为 VIP 歌曲
+    track.pay_play = value_u64(&song["fee"]) == 1;
+    track
+}
+
+fn parse_playlist(item: &Value) -> Playlist {
+    let id = value_string(&item["id"]);
+    let mut playlist = Playlist::new(id.clone(), CODE, value_string(&item["name"]));
+    playlist.cover = non_empty(value_string(&item["coverImgUrl"]));
+    playlist.url = Some(format!("{}/playlist?id={}", BASE_URL, id));
+    playlist.about = value_string(&item["description"]);
+    playlist.listen_num = item.get("playCount").map(value_u64);
+    playlist
+}
+
+impl NetEase {
+    // 主播电台详情，节目作为歌曲返回，播放时使用节目对应的歌曲 id
+    async fn anchor_radio_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String> {
+        let radio_id = id.trim_start_matches(ANCHOR_RADIO_ID_PREFIX);
+        let payload = json!({
+            "radioId": radio_id,
+            "offset": offset,
+            "limit": limit,
+            "asc": false,
+            "csrf_token": "",
+        });
+        let json = weapi(http, "/dj/program/byradio", payload, true).await?;
+        let programs = value_array(&json["programs"], "节目列表")?;
+
+        // 电台信息包含在每个节目中
+        let radio = &programs.first().unwrap_or(&Value::Null)["radio"];
+        let mut result = Playlist::new(id, CODE, value_string(&radio["name"]));
+        result.cover = non_empty(value_string(&radio["picUrl"]));
+        result.url = Some(format!("{}/djradio?id={}", BASE_URL, radio_id));
+        result.about = value_string(&radio["desc"]);
+        result.playlist_type = ANCHOR_RADIO_PLAYLIST_TYPE;
+        result.total = value_u64(&json["count"]);
+        for program in programs {
+            let program_id = format!("{}{}", RADIO_PROGRAM_PREFIX, value_string(&program["id"]));
+            let mut track = Track::new(program_id, CODE);
+            track.title = value_string(&program["name"]);
+            track.artist = vec![NamedRef {
+                id: String::new(),
+                name: value_string(&program["dj"]["nickname"]),
+            }];
+            track.album = NamedRef {
+                id: id.to_string(),
+                name: result.title.clone(),
+            };
+            track.duration = value_u64(&program["duration"]);
+            track.cover =
+                non_empty(value_string(&program["coverUrl"])).or_else(|| result.cover.clone());
+            track.pid = Some(id.to_string());
+            track.songlist_id = non_empty(value_string(&program["mainSong"]["id"]));
+            result.data.push(track);
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl MusicProvider for NetEase {
+    fn code(&self) -> &'static str {
+        CODE
+    }
+
+    fn name(&self) -> &'static str {
+        "网易云音乐"
+    }
+
+    async fn categories(&self, http: &HttpClientState) -> Result<Vec<Category>, String> {
+        let json = weapi(http, "/playlist/catalogue", json!({}), true).await?;
+        let mut default = Category::new("默认");
+        default.add(DEFAULT_CATE, "");
+        let mut result = vec![default];
+
+        // categories 为 { 序号: 分类名 }，sub 中的 category 为所属分类的序号
+        if let Some(groups) = json["categories"].as_object() {
+            let mut groups: Vec<(u64, &Value)> = groups
+                .iter()
+                .map(|(index, name)| (index.parse().unwrap_or(u64::MAX), name))
+                .collect();
+            groups.sort_by_key(|(index, _)| *index);
+            for (index, name) in groups {
+                let mut category = Category::new(value_string(name));
+                for sub in json["sub"].as_array().into_iter().flatten() {
+                    if value_u64(&sub["category"]) == index {
+                        let name = value_string(&sub["name"]);
+                        category.add(name.clone(), name);
+                    }
+                }
+                result.push(category);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn square(
+        &self,
+        http: &HttpClientState,
+        cate: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<PlaylistPage, String> {
+        let resolved = if cate.trim().is_empty() {
+            DEFAULT_CATE
+        } else {
+            cate.trim()
+        };
+        let payload = json!({
+            "cat": resolved,
+            "order": "hot",
+            "offset": offset,
+            "limit": limit,
+            "total": true,
+        });
+        let json = weapi(http, "/playlist/list", payload, true).await?;
+        let data = value_array(&json["playlists"], "歌单列表")?
+            .iter()
+            .map(parse_playlist)
+            .collect();
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset,
+            limit,
+            total: value_u64(&json["total"]).div_ceil(u64::from(limit.max(1))),
+            data,
+        })
+    }
+
+    async fn toplist(&self, http: &HttpClientState) -> Result<Vec<Playlist>, String> {
+        let json = weapi(http, "/toplist", json!({}), true).await?;
+        Ok(value_array(&json["list"], "排行榜列表")?
+            .iter()
+            .map(parse_playlist)
+            .collect())
+    }
+
+    async fn search(
+        &self,
+        http: &HttpClientState,
+        keyword: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<SearchResult, String> {
+        let payload = json!({
+            "s": keyword,
+            "type": SEARCH_TYPE_SONG,
+            "offset": offset,
+            "limit": limit,
+            "total": true,
+        });
+        let json = weapi(http, "/cloudsearch/get/web", payload, true).await?;
+        let result = &json["result"];
+        // 没有结果时不返回 songs
+        let data = result["songs"]
+            .as_array()
+            .map(|songs| songs.iter().map(parse_song).collect())
+            .unwrap_or_default();
+        Ok(SearchResult {
+            platform: CODE.to_string(),
+            keyword: keyword.to_string(),
+            offset,
+            limit,
+            total: value_u64(&result["songCount"]),
+            data,
+        })
+    }
+
+    async fn playlist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String> {
+        if id.starts_with(ANCHOR_RADIO_ID_PREFIX) {
+            return self.anchor_radio_detail(http, id, offset, limit).await;
+        }
+        let payload = json!({
+            "id": id,
+            "offset": 0,
+            "total": true,
+            "limit": 1000,
+            "n": 1000,
+            "csrf_token": "",
+        });
+        let json = weapi(http, "/v3/playlist/detail", payload, true).await?;
+        let mut result = parse_playlist(&json["playlist"]);
+        let ids: Vec<u64> = value_array(&json["playlist"]["trackIds"], "歌曲列表")?
+            .iter()
+            .map(|track| value_u64(&track["id"]))
+            .collect();
+        result.total = ids.len() as u64;
+
+        // 歌单详情只返回歌曲 id，歌曲信息需要另外请求
+        let start = (offset as usize).min(ids.len());
+        let end = (start + limit as usize).min(ids.len());
+        let ids = &ids[start..end];
+        if ids.is_empty() {
+            return Ok(result);
+        }
+        let c: Vec<Value> = ids.iter().map(|id| json!({ "id": id })).collect();
+        let payload = json!({
+            "c": Value::Array(c).to_string(),
+            "ids": json!(ids).to_string(),
+        });
+        let json = weapi(http, "/v3/song/detail", payload, true).await?;
+        for song in value_array(&json["songs"], "歌曲信息")? {
+            let mut track = parse_song(song);
+            track.pid = Some(id.to_string());
+            result.data.push(track);
+        }
+        Ok(result)
+    }
+
+    async fn play_detail(&self, http: &HttpClientState, track: &Track) -> Result<Track, String> {
+        // 主播电台节目使用对应的歌曲 id 获取播放地址
+        let song_id = if track.id.starts_with(RADIO_PROGRAM_PREFIX) {
+            track
+                .songlist_id
+                .as_deref()
+                .ok_or_else(|| format!("缺少电台节目对应的歌曲 id: {}", track.id))?
+        } else {
+            track.id.as_str()
+        };
+        let payload = json!({
+            "ids": [song_id],
+            "level": "standard",
+            "encodeType": "aac",
+            "csrf_token": "",
+        });
+        let json = weapi(
+            http,
+            "/song/enhance/player/url/v1?csrf_token=",
+            payload,
+            false,
+        )
+        .await?;
+        let mut result = Track::new(track.id.clone(), CODE);
+        result.url = non_empty(value_string(&json["data"][0]["url"]));
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fixtures::replay_client;
+    use tauri::async_runtime::block_on;
+
+    #[test]
+    fn parses_search() {
+        let http = replay_client("netease_search");
+        let result = block_on(NetEase.search(&http, "晴天", 0, 2)).unwrap();
+        assert_eq!(result.total, 300);
+        assert_eq!(result.data.len(), 2);
+        let track = &result.data[0];
+        assert_eq!(track.id, "186016");
+        assert_eq!(track.title, "晴天");
+        assert_eq!(track.artist[0].name, "周杰伦");
+        assert_eq!(track.album.id, "18905");
+        assert_eq!(track.album.name, "叶惠美");
+        assert_eq!(track.duration, 269000);
+        assert_eq!(track.mv.as_deref(), Some("504177"));
+        assert!(!track.pay_play);
+        // 没有封面和 MV 时为空，fee 为 1 时是 VIP 歌曲
+        let track = &result.data[1];
+        assert_eq!(track.artist.len(), 2);
+        assert_eq!(track.cover, None);
+        assert_eq!(track.mv, None);
+        assert!(track.pay_play);
+    }
+
+    #[test]
+    fn parses_playlist_detail() {
+        let http = replay_client("netease_playlist");
+        let playlist = block_on(NetEase.playlist_detail(&http, "3778678", 0, 2)).unwrap();
+        assert_eq!(playlist.title, "热歌榜");
+        assert_eq!(
+            playlist.url.as_deref(),
+            Some("https://music.163.com/playlist?id=3778678")
+        );
+        assert_eq!(playlist.listen_num, Some(13227381760));
+        // total 为歌单的全部歌曲数，data 只包含请求的一页
+        assert_eq!(playlist.total, 3);
+        let ids: Vec<&str> = playlist
+            .data
+            .iter()
+            .map(|track| track.id.as_str())
+            .collect();
+        assert_eq!(ids, ["186016", "1901371647"]);
+        assert_eq!(playlist.data[1].artist[0].name, "陈奕迅");
+        assert_eq!(playlist.data[1].pid.as_deref(), Some("3778678"));
+    }
+
+    #[test]
+    fn parses_play_detail() {
+        let http = replay_client("netease_play");
+        let track = Track::new("186016", CODE);
+        let result = block_on(NetEase.play_detail(&http, &track)).unwrap();
+        assert_eq!(result.id, "186016");
+        assert!(result
+            .url
+            .unwrap()
+            .starts_with("http://m701.music.126.net/"));
+    }
+
+    // 主播电台的节目作为歌曲返回，播放时使用节目对应的歌曲 id
+    #[test]
+    fn parses_anchor_radio_detail() {
+        let http = replay_client("netease_anchor_radio");
+        let playlist = block_on(NetEase.playlist_detail(&http, "ARP_336355127", 0, 2)).unwrap();
+        assert_eq!(playlist.title, "晚安电台");
+        assert_eq!(playlist.playlist_type, ANCHOR_RADIO_PLAYLIST_TYPE);
+        assert_eq!(
+            playlist.url.as_deref(),
+            Some("https://music.163.com/djradio?id=336355127")
+        );
+        assert_eq!(playlist.total, 120);
+        assert_eq!(playlist.data.len(), 2);
+        let track = &playlist.data[0];
+        assert_eq!(track.id, "DJR_2071138567");
+        assert_eq!(track.title, "第120期 晚安");
+        assert_eq!(track.artist[0].name, "晚安主播");
+        assert_eq!(track.album.name, "晚安电台");
+        assert_eq!(track.duration, 1_201_000);
+        assert_eq!(track.songlist_id.as_deref(), Some("2049512696"));
+        // 节目没有封面时使用电台封面
+        assert_eq!(playlist.data[1].cover, playlist.cover);
+
+        let result = block_on(NetEase.play_detail(&http, track)).unwrap();
+        assert_eq!(result.id, "DJR_2071138567");
+        assert!(result.url.unwrap().ends_with(".mp3"));
+        // 缺少节目对应的歌曲 id 时无法获取播放地址
+        let result = block_on(NetEase.play_detail(&http, &Track::new("DJR_1", CODE)));
+        assert!(result.is_err());
+    }
+}
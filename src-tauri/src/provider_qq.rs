@@ -0,0 +1,611 @@
+use async_trait::async_trait;
+use rand::Rng;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::http_client::{HttpClientState, HttpRequest};
+use crate::music_provider::{
+    fetch_json, non_empty, value_array, value_string, value_u64, Category, CategoryItem,
+    MusicProvider, NamedRef, Playlist, PlaylistPage, SearchResult, Track,
+    NORMAL_RADIO_PLAYLIST_TYPE,
+};
+
+const CODE: &str = "qq";
+const MUSICU_URL: &str = "https://u.y.qq.com/cgi-bin/musicu.fcg";
+const REFERER: &str = "https://y.qq.com/";
+const ORIGIN: &str = "https://y.qq.com";
+
+// 以下分类 id 与前端 QQ vendor 一致
+const DEFAULT_CATE: u64 = 10000000;
+const TOPLIST_CODE: u64 = 99999999;
+const RADIO_CODE: u64 = 88888888;
+const NEW_CODE: u64 = 22222222;
+const TOPLIST_PREFIX: &str = "TOP_";
+
+// 歌单广场排序：最热、最新
+const SORT_HOT: u32 = 5;
+const SORT_NEW: u32 = 2;
+
+pub struct QQ;
+
+// query 为 JSON 对象，作为查询参数发送
+// offline_cache 为 true 时保存响应供离线模式使用；播放地址会过期，不保存
+fn get_request(url: &str, query: Value, offline_cache: bool) -> HttpRequest {
+    let mut request = HttpRequest::new("GET", url);
+    request.headers = HashMap::from([
+        ("Referer".to_string(), REFERER.to_string()),
+        ("Origin".to_string(), ORIGIN.to_string()),
+    ]);
+    // 查询参数 _ 为防止缓存的时间戳，不计入缓存键，离线时才能找到缓存
+    if let Some(map) = query.as_object().filter(|map| map.contains_key("_")) {
+        let mut map = map.clone();
+        map.remove("_");
+        request.cache_key = Some(format!("{} {}", url, Value::Object(map)));
+    }
+    request.query = match query {
+        Value::Object(map) => Some(map.into_iter().collect()),
+        _ => None,
+    };
+    request.offline_cache = offline_cache;
+    request
+}
+
+async fn get_json(
+    http: &HttpClientState,
+    url: &str,
+    query: Value,
+    offline_cache: bool,
+) -> Result<Value, String> {
+    fetch_json(http, get_request(url, query, offline_cache)).await
+}
+
+fn module_req(module: &str, method: &str, param: Value) -> Value {
+    json!({ "module": module, "method": method, "param": param })
+}
+
+fn album_cover(album_mid: &str) -> Option<String> {
+    if album_mid.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "https://y.qq.com/music/photo_new/T002R500x500M000{}.jpg?max_age=2592000",
+        album_mid
+    ))
+}
+
+fn named_refs(list: &Value, id_key: &str) -> Vec<NamedRef> {
+    list.as_array()
+        .map(|list| {
+            list.iter()
+                .map(|item| NamedRef {
+                    id: value_string(&item[id_key]),
+                    name: value_string(&item["name"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 新版接口（musicu、new_json=1 的搜索）的歌曲格式
+fn parse_song(song: &Value) -> Track {
+    let mut track = Track::new(value_string(&song["mid"]), CODE);
+    track.title = value_string(&song["name"]);
+    track.artist = named_refs(&song["singer"], "mid");
+    let album_mid = value_string(&song["album"]["mid"]);
+    track.cover = album_cover(&album_mid);
+    track.album = NamedRef {
+        id: album_mid,
+        name: value_string(&song["album"]["name"]),
+    };
+    track.duration = value_u64(&song["interval"]) * 1000;
+    track.mv = non_empty(value_string(&song["mv"]["vid"]));
+    track.pay_play = value_u64(&song["pay"]["pay_play"]) == 1;
+    track
+}
+
+// 北京时间的年、月、日、一年中的第几天和星期（0 为周日）
+fn beijing_today() -> (i64, u32, u32, u32, u32) {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+        + 8 * 3600;
+    let days = secs.div_euclid(86400);
+    // 1970-01-01 为周四
+    let weekday = (days + 4).rem_euclid(7) as u32;
+
+    // 按公历由天数推算日期
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let days_before_month = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let day_of_year = days_before_month[month as usize - 1] + day + u32::from(leap && month > 2);
+    (year, month, day, day_of_year, weekday)
+}
+
+// 排行榜的更新周期，与前端 getPerid 的算法一致
+fn toplist_period(top_id: u64) -> String {
+    let (year, month, day, day_of_year, weekday) = beijing_today();
+    // 与 1 月 1 日相差的天数除以 7 向上取整
+    let week_of_year = i64::from((day_of_year + 5) / 7);
+    match top_id {
+        // 每天更新
+        27 | 62 | 130 => format!("{}-{:02}-{:02}", year, month, day),
+        // 每周固定一天更新
+        4 | 52 | 67 => {
+            let day = if weekday < 6 {
+                (day + 1).saturating_sub(weekday).max(1)
+            } else {
+                day
+            };
+            format!("{}-{:02}-{:02}", year, month, day)
+        }
+        131 => format!("{}_{:02}", year, week_of_year - 8),
+        // 每周更新
+        _ => format!("{}_{:02}", year, week_of_year - 1),
+    }
+}
+
+impl QQ {
+    async fn toplist_page(
+        &self,
+        http: &HttpClientState,
+        cate: &str,
+    ) -> Result<PlaylistPage, String> {
+        let data = self.toplist(http).await?;
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset: 0,
+            limit: data.len() as u32,
+            total: 1,
+            data,
+        })
+    }
+
+    // 歌单电台列表
+    async fn radio_page(&self, http: &HttpClientState, cate: &str) -> Result<PlaylistPage, String> {
+        let query = json!({
+            "format": "json",
+            "inCharset": "utf8",
+            "outCharset": "utf8",
+            "notice": 0,
+            "platform": "yqq.json",
+            "needNewCode": 1,
+            "loginUin": 0,
+            "hostUin": 0,
+            "g_tk": 5381,
+            "data": json!({
+                "comm": { "ct": 24, "cv": 0 },
+                "req_1": module_req("pf.radiosvr", "GetRadiolist", json!({ "ct": 24 })),
+            })
+            .to_string(),
+        });
+        let json = get_json(http, MUSICU_URL, query, true).await?;
+        let mut data = Vec::new();
+        for group in value_array(&json["req_1"]["data"]["radio_list"], "电台列表")? {
+            for item in group["list"].as_array().into_iter().flatten() {
+                let title = format!(
+                    "{}| {}",
+                    value_string(&group["title"]),
+                    value_string(&item["title"])
+                );
+                let mut playlist = Playlist::new(value_string(&item["id"]), CODE, title);
+                playlist.cover = non_empty(value_string(&item["pic_url"]));
+                playlist.playlist_type = NORMAL_RADIO_PLAYLIST_TYPE;
+                data.push(playlist);
+            }
+        }
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset: 0,
+            limit: data.len() as u32,
+            total: 1,
+            data,
+        })
+    }
+
+    async fn toplist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+    ) -> Result<Playlist, String> {
+        let top_id: u64 = id
+            .trim_start_matches(TOPLIST_PREFIX)
+            .parse()
+            .map_err(|_| format!("无效的排行榜 id: {}", id))?;
+        let query = json!({
+            "g_tk": 5381,
+            "data": json!({
+                "comm": { "ct": 24, "cv": 0 },
+                "req_1": module_req("musicToplist.ToplistInfoServer", "GetDetail", json!({
+                    "topid": top_id,
+                    "offset": offset,
+                    "num": 100,
+                    "period": toplist_period(top_id),
+                })),
+            })
+            .to_string(),
+        });
+        let json = get_json(http, MUSICU_URL, query, true).await?;
+        let detail = &json["req_1"]["data"]["data"];
+        let mut result = Playlist::new(id, CODE, value_string(&detail["title"]));
+        result.cover = non_empty(value_string(&detail["frontPicUrl"]))
+            .or_else(|| non_empty(value_string(&detail["headPicUrl"])));
+        result.about = value_string(&detail["intro"]);
+        for song in value_array(&json["req_1"]["data"]["songInfoList"], "歌曲列表")? {
+            let mut track = parse_song(song);
+            track.pid = Some(id.to_string());
+            result.data.push(track);
+        }
+        result.total = value_u64(&detail["totalNum"]).max(result.data.len() as u64);
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl MusicProvider for QQ {
+    fn code(&self) -> &'static str {
+        CODE
+    }
+
+    fn name(&self) -> &'static str {
+        "QQ音乐"
+    }
+
+    async fn categories(&self, http: &HttpClientState) -> Result<Vec<Category>, String> {
+        let url = "https://c.y.qq.com/splcloud/fcgi-bin/fcg_get_diss_tag_conf.fcg";
+        let query = json!({ "format": "json", "inCharset": "utf8", "outCharset": "utf8" });
+        let json = get_json(http, url, query, true).await?;
+
+        let mut result: Vec<Category> = Vec::new();
+        for group in value_array(&json["data"]["categories"], "分类列表")? {
+            let name = value_string(&group["categoryGroupName"]);
+            // 同名的分类组只保留第一个
+            if result.iter().any(|category| category.name == name) {
+                continue;
+            }
+            let mut category = Category::new(name);
+            for item in group["items"].as_array().into_iter().flatten() {
+                category.add(
+                    value_string(&item["categoryName"]),
+                    value_string(&item["categoryId"]),
+                );
+            }
+            result.push(category);
+        }
+        // 在第一个分类中插入最新、排行榜、电台
+        if let Some(first) = result.first_mut() {
+            let index = first.data.len().min(1);
+            let special = [
+                ("最新", NEW_CODE),
+                ("排行榜", TOPLIST_CODE),
+                ("电台", RADIO_CODE),
+            ];
+            for (offset, (key, value)) in special.into_iter().enumerate() {
+                let item = CategoryItem {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                };
+                first.data.insert(index + offset, item);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn square(
+        &self,
+        http: &HttpClientState,
+        cate: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<PlaylistPage, String> {
+        let mut resolved = cate
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|cate| *cate > 0)
+            .unwrap_or(DEFAULT_CATE);
+        match resolved {
+            TOPLIST_CODE => return self.toplist_page(http, cate).await,
+            RADIO_CODE => return self.radio_page(http, cate).await,
+            _ => {}
+        }
+        let mut sort_id = SORT_HOT;
+        if resolved == NEW_CODE {
+            sort_id = SORT_NEW;
+            resolved = DEFAULT_CATE;
+        }
+
+        let url = "https://c.y.qq.com/splcloud/fcgi-bin/fcg_get_diss_by_tag.fcg";
+        let query = json!({
+            "format": "json",
+            "inCharset": "utf8",
+            "outCharset": "utf8",
+            "sortId": sort_id,
+            "categoryId": resolved,
+            "sin": offset,
+            "ein": (offset + limit).saturating_sub(1),
+        });
+        let json = get_json(http, url, query, true).await?;
+        let mut data = Vec::new();
+        for item in value_array(&json["data"]["list"], "歌单列表")? {
+            let mut playlist = Playlist::new(
+                value_string(&item["dissid"]),
+                CODE,
+                value_string(&item["dissname"]),
+            );
+            playlist.cover = non_empty(value_string(&item["imgurl"]));
+            playlist.about = value_string(&item["introduction"]);
+            playlist.listen_num = item.get("listennum").map(value_u64);
+            data.push(playlist);
+        }
+        Ok(PlaylistPage {
+            platform: CODE.to_string(),
+            cate: cate.to_string(),
+            offset,
+            limit,
+            total: value_u64(&json["data"]["sum"]).div_ceil(u64::from(limit.max(1))),
+            data,
+        })
+    }
+
+    async fn toplist(&self, http: &HttpClientState) -> Result<Vec<Playlist>, String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let query = json!({
+            "_": now,
+            "uin": 0,
+            "format": "json",
+            "inCharset": "utf8",
+            "outCharset": "utf8",
+            "notice": 0,
+            "platform": "yqq.json",
+            "needNewCode": 1,
+            "g_tk": 5381,
+            "data": json!({
+                "comm": { "ct": 24, "cv": 0 },
+                "req_1": module_req("musicToplist.ToplistInfoServer", "GetAll", json!({})),
+            })
+            .to_string(),
+        });
+        let json = get_json(http, MUSICU_URL, query, true).await?;
+        let mut result = Vec::new();
+        for group in value_array(&json["req_1"]["data"]["group"], "排行榜列表")? {
+            for item in group["toplist"].as_array().into_iter().flatten() {
+                let id = format!("{}{}", TOPLIST_PREFIX, value_string(&item["topId"]));
+                let mut playlist = Playlist::new(id, CODE, value_string(&item["title"]));
+                playlist.cover = non_empty(value_string(&item["frontPicUrl"]))
+                    .or_else(|| non_empty(value_string(&item["headPicUrl"])));
+                playlist.about = value_string(&item["intro"]);
+                result.push(playlist);
+            }
+        }
+        Ok(result)
+    }
+
+    async fn search(
+        &self,
+        http: &HttpClientState,
+        keyword: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<SearchResult, String> {
+        let limit = limit.max(1);
+        let url = "https://c.y.qq.com/soso/fcgi-bin/client_search_cp";
+        let query = json!({
+            "format": "json",
+            "w": keyword,
+            "p": offset / limit + 1,
+            "n": limit,
+            "cr": 1,
+            "new_json": 1,
+            "t": 0,
+        });
+        let json = get_json(http, url, query, true).await?;
+        let songs = &json["data"]["song"];
+        let data = songs["list"]
+            .as_array()
+            .map(|list| list.iter().map(parse_song).collect())
+            .unwrap_or_default();
+        Ok(SearchResult {
+            platform: CODE.to_string(),
+            keyword: keyword.to_string(),
+            offset,
+            limit,
+            total: value_u64(&songs["totalnum"]),
+            data,
+        })
+    }
+
+    async fn playlist_detail(
+        &self,
+        http: &HttpClientState,
+        id: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Playlist, String> {
+        if id.starts_with(TOPLIST_PREFIX) {
+            return self.toplist_detail(http, id, offset).await;
+        }
+        let url = "https://c.y.qq.com/qzone/fcg-bin/fcg_ucc_getcdinfo_byids_cp.fcg";
+        let query = json!({
+            "format": "json",
+            "type": 1,
+            "utf8": 1,
+            "disstid": id,
+            "loginUin": 0,
+        });
+        let json = get_json(http, url, query, true).await?;
+        let detail = &json["cdlist"][0];
+        let mut result = Playlist::new(id, CODE, value_string(&detail["dissname"]));
+        result.cover = non_empty(value_string(&detail["logo"]).replace("/300?n=1", "/600?n=1"));
+        result.about = value_string(&detail["desc"]);
+
+        let songs = value_array(&detail["songlist"], "歌曲列表")?;
+        result.total = songs.len() as u64;
+        // 旧版接口的歌曲格式
+        for song in songs.iter().skip(offset as usize).take(limit as usize) {
+            let mut track = Track::new(value_string(&song["songmid"]), CODE);
+            track.title = value_string(&song["songname"]);
+            track.artist = named_refs(&song["singer"], "mid");
+            let album_mid = value_string(&song["albummid"]);
+            track.cover = album_cover(&album_mid);
+            track.album = NamedRef {
+                id: album_mid,
+                name: value_string(&song["albumname"]),
+            };
+            track.duration = value_u64(&song["interval"]) * 1000;
+            track.mv = non_empty(value_string(&song["vid"]));
+            track.pid = Some(id.to_string());
+            track.pay_play = value_u64(&song["pay"]["payplay"]) == 1;
+            track.pay_download = value_u64(&song["pay"]["paydownload"]) == 1;
+            result.data.push(track);
+        }
+        Ok(result)
+    }
+
+    async fn play_detail(&self, http: &HttpClientState, track: &Track) -> Result<Track, String> {
+        let query = json!({
+            "format": "json",
+            "data": json!({
+                "songinfo": module_req(
+                    "music.pf_song_detail_svr",
+                    "get_song_detail_yqq",
+                    json!({ "song_mid": track.id }),
+                ),
+            })
+            .to_string(),
+        });
+        let json = get_json(http, MUSICU_URL, query, true).await?;
+        let info = &json["songinfo"]["data"]["track_info"];
+        let mid = value_string(&info["mid"]);
+        if mid.is_empty() {
+            return Err(format!("没有找到歌曲: {}", track.id));
+        }
+
+        // 通过 vkey 获取播放地址
+        let guid = rand::thread_rng().gen_range(0..10_000_000u32).to_string();
+        let data = json!({
+            "comm": { "uin": "0", "format": "json", "ct": 24, "cv": 0 },
+            "req_1": module_req("vkey.GetVkeyServer", "CgiGetVkey", json!({
+                "filename": [format!("C400{}{}.m4a", mid, mid)],
+                "guid": guid,
+                "songmid": [mid],
+                "songtype": [info["type"]],
+                "uin": "0",
+                "loginflag": 1,
+                "platform": "20",
+            })),
+        });
+        let query = json!({
+            "-": "getplaysongvkey",
+            "g_tk": 5381,
+            "loginUin": 0,
+            "hostUin": 0,
+            "format": "json",
+            "inCharset": "utf8",
+            "outCharset": "utf8",
+            "notice": 1,
+            "platform": "yqq.json",
+            "needNewCode": 0,
+            "data": data.to_string(),
+        });
+        let mut request = get_request(MUSICU_URL, query, false);
+        // guid 每次都不同，按歌曲生成缓存键，录制的响应也能回放
+        request.cache_key = Some(format!("{} vkey {}", MUSICU_URL, mid));
+        let json = fetch_json(http, request).await?;
+        let data = &json["req_1"]["data"];
+        let url_info = &data["midurlinfo"][0];
+
+        let mut result = Track::new(track.id.clone(), CODE);
+        // 没有 vkey 时无法播放（如 VIP 歌曲）
+        if !value_string(&url_info["vkey"]).is_empty() {
+            let purl = value_string(&url_info["purl"]);
+            result.url =
+                non_empty(purl).map(|purl| format!("{}{}", value_string(&data["sip"][0]), purl));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fixtures::replay_client;
+    use tauri::async_runtime::block_on;
+
+    #[test]
+    fn parses_search() {
+        let http = replay_client("qq_search");
+        let result = block_on(QQ.search(&http, "晴天", 0, 2)).unwrap();
+        assert_eq!(result.total, 600);
+        assert_eq!(result.data.len(), 2);
+        let track = &result.data[0];
+        assert_eq!(track.id, "0039MnYb0qxYhV");
+        assert_eq!(track.title, "晴天");
+        assert_eq!(track.artist[0].id, "0025NhlN2yWrP4");
+        assert_eq!(track.album.name, "叶惠美");
+        assert_eq!(track.duration, 269000);
+        assert_eq!(track.mv.as_deref(), Some("n0010BCw40b"));
+        assert!(track.cover.as_deref().unwrap().contains("000MkMni19ClKG"));
+        assert!(track.pay_play);
+        let track = &result.data[1];
+        assert_eq!(track.artist.len(), 2);
+        assert_eq!(track.cover, None);
+        assert_eq!(track.mv, None);
+        assert!(!track.pay_play);
+    }
+
+    #[test]
+    fn parses_playlist_detail() {
+        let http = replay_client("qq_playlist");
+        let playlist = block_on(QQ.playlist_detail(&http, "7256912512", 1, 2)).unwrap();
+        assert_eq!(playlist.title, "周杰伦精选");
+        assert_eq!(playlist.about, "周杰伦的经典歌曲");
+        assert_eq!(
+            playlist.cover.as_deref(),
+            Some("http://qpic.y.qq.com/music_cover/abc/600?n=1")
+        );
+        assert_eq!(playlist.total, 3);
+        let ids: Vec<&str> = playlist
+            .data
+            .iter()
+            .map(|track| track.id.as_str())
+            .collect();
+        assert_eq!(ids, ["002Zkt5S2z8JZx", "004Z8Ihr0JIu5s"]);
+        let track = &playlist.data[0];
+        assert_eq!(track.title, "稻香");
+        assert_eq!(track.album.name, "魔杰座");
+        assert_eq!(track.duration, 223000);
+        assert_eq!(track.mv, None);
+        assert!(!track.pay_play);
+        assert!(track.pay_download);
+        assert_eq!(track.pid.as_deref(), Some("7256912512"));
+    }
+
+    // 先查询歌曲信息，再通过 vkey 接口获取播放地址
+    #[test]
+    fn parses_play_detail() {
+        let http = replay_client("qq_play");
+        let track = Track::new("0039MnYb0qxYhV", CODE);
+        let result = block_on(QQ.play_detail(&http, &track)).unwrap();
+        assert_eq!(
+            result.url.as_deref(),
+            Some("http://ws.stream.qqmusic.qq.com/C4000039MnYb0qxYhV0039MnYb0qxYhV.m4a?guid=3860374&vkey=5F3A9C0E2B7D41A8&uin=0&fromtag=66")
+        );
+    }
+}
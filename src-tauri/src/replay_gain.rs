@@ -0,0 +1,304 @@
+use lofty::config::WriteOptions;
+use lofty::file::TaggedFile;
+use lofty::prelude::*;
+use lofty::tag::{ItemKey, ItemValue, Tag, TagItem};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// R128 标签以 -23 LUFS 为参考，ReplayGain 以 -18 LUFS（89 dB SPL）为参考
+const R128_TO_REPLAY_GAIN_OFFSET_DB: f64 = 5.0;
+
+// 音量均衡信息，增益统一为 dB，峰值为线性振幅（1.0 表示满刻度）
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReplayGain {
+    pub track_gain: Option<f64>,
+    pub track_peak: Option<f64>,
+    pub album_gain: Option<f64>,
+    pub album_peak: Option<f64>,
+}
+
+impl ReplayGain {
+    // 用另一个来源补全缺失的字段，已有的值优先
+    fn merge(&mut self, other: ReplayGain) {
+        self.track_gain = self.track_gain.or(other.track_gain);
+        self.track_peak = self.track_peak.or(other.track_peak);
+        self.album_gain = self.album_gain.or(other.album_gain);
+        self.album_peak = self.album_peak.or(other.album_peak);
+    }
+}
+
+// 从音频文件的所有标签中读取音量均衡信息
+// 优先级：REPLAYGAIN_* > R128_* > iTunNORM
+pub fn read_replay_gain(tagged_file: &TaggedFile) -> ReplayGain {
+    let mut replay_gain = ReplayGain::default();
+    let mut r128 = ReplayGain::default();
+    let mut itunes = ReplayGain::default();
+
+    for tag in tagged_file.tags() {
+        for item in tag.items() {
+            let key = match item_key_name(tag, item) {
+                Some(key) => key,
+                None => continue,
+            };
+            let value = match item_text(item) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            match key.as_str() {
+                "REPLAYGAIN_TRACK_GAIN" => {
+                    replay_gain.track_gain = replay_gain.track_gain.or(parse_gain(value))
+                }
+                "REPLAYGAIN_TRACK_PEAK" => {
+                    replay_gain.track_peak = replay_gain.track_peak.or(parse_peak(value))
+                }
+                "REPLAYGAIN_ALBUM_GAIN" => {
+                    replay_gain.album_gain = replay_gain.album_gain.or(parse_gain(value))
+                }
+                "REPLAYGAIN_ALBUM_PEAK" => {
+                    replay_gain.album_peak = replay_gain.album_peak.or(parse_peak(value))
+                }
+                "R128_TRACK_GAIN" => r128.track_gain = r128.track_gain.or(parse_r128(value)),
+                "R128_ALBUM_GAIN" => r128.album_gain = r128.album_gain.or(parse_r128(value)),
+                "ITUNNORM" => {
+                    if itunes.track_gain.is_none() {
+                        itunes = parse_itunnorm(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    replay_gain.merge(r128);
+    replay_gain.merge(itunes);
+    replay_gain
+}
+
+// 将音量均衡信息写回文件的主标签，空字段保持不变
+pub fn write_replay_gain(path: &Path, replay_gain: &ReplayGain) -> Result<(), String> {
+    let mut tagged_file =
+        lofty::read_from_path(path).map_err(|e| format!("无法读取音频文件: {}", e))?;
+
+    // 没有主标签时按文件格式创建一个
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| "无法创建标签".to_string())?;
+
+    let fields = [
+        (
+            ItemKey::ReplayGainTrackGain,
+            replay_gain.track_gain.map(format_gain),
+        ),
+        (
+            ItemKey::ReplayGainTrackPeak,
+            replay_gain.track_peak.map(format_peak),
+        ),
+        (
+            ItemKey::ReplayGainAlbumGain,
+            replay_gain.album_gain.map(format_gain),
+        ),
+        (
+            ItemKey::ReplayGainAlbumPeak,
+            replay_gain.album_peak.map(format_peak),
+        ),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            tag.insert_text(key, value);
+        }
+    }
+
+    tag.save_to_path(path, WriteOptions::default())
+        .map_err(|e| format!("保存标签失败: {}", e))
+}
+
+fn format_gain(gain: f64) -> String {
+    format!("{:.2} dB", gain)
+}
+
+fn format_peak(peak: f64) -> String {
+    format!("{:.6}", peak)
+}
+
+// 获取标签项的统一键名（大写，去掉 ID3 TXXX / MP4 freeform 前缀）
+fn item_key_name(tag: &Tag, item: &TagItem) -> Option<String> {
+    let raw = match item.key() {
+        ItemKey::ReplayGainTrackGain => return Some("REPLAYGAIN_TRACK_GAIN".to_string()),
+        ItemKey::ReplayGainTrackPeak => return Some("REPLAYGAIN_TRACK_PEAK".to_string()),
+        ItemKey::ReplayGainAlbumGain => return Some("REPLAYGAIN_ALBUM_GAIN".to_string()),
+        ItemKey::ReplayGainAlbumPeak => return Some("REPLAYGAIN_ALBUM_PEAK".to_string()),
+        // iTunes 会把 iTunNORM 写在 COMM 帧中，按内容格式识别
+        ItemKey::Comment if item_text(item).is_some_and(looks_like_itunnorm) => {
+            return Some("ITUNNORM".to_string())
+        }
+        ItemKey::Unknown(key) => key.clone(),
+        key => key.map_key(tag.tag_type(), true)?.to_string(),
+    };
+
+    let name = raw
+        .trim_start_matches("----:com.apple.iTunes:")
+        .trim_start_matches("TXXX:")
+        .trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.to_ascii_uppercase())
+}
+
+fn item_text(item: &TagItem) -> Option<&str> {
+    match item.value() {
+        ItemValue::Text(text) | ItemValue::Locator(text) => Some(text.trim()),
+        ItemValue::Binary(_) => None,
+    }
+}
+
+// iTunNORM 为 10 个以空格分隔的 8 位十六进制数
+fn looks_like_itunnorm(value: &str) -> bool {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    fields.len() == 10
+        && fields
+            .iter()
+            .all(|field| field.len() == 8 && field.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// 解析形如 "-6.54 dB" 的增益值
+fn parse_gain(value: &str) -> Option<f64> {
+    let number = value
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic() || c.is_whitespace());
+    number.parse::<f64>().ok().filter(|gain| gain.is_finite())
+}
+
+// 解析形如 "0.988525" 的峰值
+fn parse_peak(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|peak| peak.is_finite() && *peak >= 0.0)
+}
+
+// R128 增益为 Q7.8 定点整数（单位 1/256 dB），转换为 ReplayGain 参考电平
+fn parse_r128(value: &str) -> Option<f64> {
+    let raw = value.trim().parse::<i32>().ok()?;
+    Some(raw as f64 / 256.0 + R128_TO_REPLAY_GAIN_OFFSET_DB)
+}
+
+// 解析 iTunNORM：10 个十六进制数，前两个为左右声道的音量调整值（1/1000 W），
+// 第 7、8 个为左右声道的峰值采样（16 位满刻度为 32768）
+fn parse_itunnorm(value: &str) -> ReplayGain {
+    let fields: Vec<u32> = value
+        .split_whitespace()
+        .filter_map(|field| u32::from_str_radix(field, 16).ok())
+        .collect();
+    if fields.len() < 2 {
+        return ReplayGain::default();
+    }
+
+    let adjustment = fields[0].max(fields[1]);
+    let track_gain = if adjustment > 0 {
+        Some(-10.0 * (adjustment as f64 / 1000.0).log10())
+    } else {
+        None
+    };
+
+    let track_peak = if fields.len() >= 8 {
+        let peak = fields[6].max(fields[7]);
+        if peak > 0 {
+            Some(peak as f64 / 32768.0)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    ReplayGain {
+        track_gain,
+        track_peak,
+        album_gain: None,
+        album_peak: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("应有值");
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "期望 {}，实际 {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn parses_replay_gain_values() {
+        assert_near(parse_gain("-6.54 dB"), -6.54);
+        assert_near(parse_gain("+2.10 dB"), 2.10);
+        assert_near(parse_gain(" 1.5dB "), 1.5);
+        assert_eq!(parse_gain("dB"), None);
+        assert_near(parse_peak("0.988525"), 0.988525);
+        assert_eq!(parse_peak("-1"), None);
+        assert_eq!(parse_peak("abc"), None);
+    }
+
+    // R128 增益以 1/256 dB 为单位、以 -23 LUFS 为参考，加 5 dB 得到 ReplayGain
+    #[test]
+    fn parses_r128_gain() {
+        assert_near(parse_r128("0"), 5.0);
+        assert_near(parse_r128("-256"), 4.0);
+        assert_near(parse_r128(" -1792 "), -2.0);
+        assert_near(parse_r128("384"), 6.5);
+        assert_eq!(parse_r128("1.5"), None);
+        assert_eq!(parse_r128(""), None);
+    }
+
+    #[test]
+    fn parses_itunnorm() {
+        let value = " 00000A2C 00000A2C 00001A15 00001A15 00024CA8 00024CA8 00007FFF 00007FFF 00024CA8 00024CA8";
+        assert!(looks_like_itunnorm(value.trim()));
+        let gain = parse_itunnorm(value);
+        // 0xA2C = 2604，-10 * log10(2.604)
+        assert_near(gain.track_gain, -4.1564);
+        assert_near(gain.track_peak, 32767.0 / 32768.0);
+        assert_eq!(gain.album_gain, None);
+        assert_eq!(gain.album_peak, None);
+
+        // 左右声道不同时取较大的调整值和峰值
+        let gain = parse_itunnorm("000003E8 000007D0 0 0 0 0 00004000 00002000 0 0");
+        assert_near(gain.track_gain, -3.0103);
+        assert_near(gain.track_peak, 0.5);
+
+        // 1000 表示不需要调整
+        assert_near(parse_itunnorm("000003E8 000003E8").track_gain, 0.0);
+        assert_eq!(parse_itunnorm("000003E8 000003E8").track_peak, None);
+        assert_eq!(parse_itunnorm("00000000 00000000").track_gain, None);
+        assert_eq!(parse_itunnorm("000003E8").track_gain, None);
+        assert!(!looks_like_itunnorm("000003E8 000003E8"));
+    }
+
+    #[test]
+    fn merge_keeps_existing_values() {
+        let mut gain = ReplayGain {
+            track_gain: Some(-3.0),
+            ..Default::default()
+        };
+        gain.merge(ReplayGain {
+            track_gain: Some(1.0),
+            track_peak: Some(0.9),
+            ..Default::default()
+        });
+        assert_eq!(gain.track_gain, Some(-3.0));
+        assert_eq!(gain.track_peak, Some(0.9));
+        assert_eq!(gain.album_gain, None);
+    }
+}
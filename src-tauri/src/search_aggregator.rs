@@ -0,0 +1,399 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::mpsc;
+
+use crate::http_client::HttpClientState;
+use crate::music_provider::{MusicProvider, ProviderRegistry, SearchResult, Track};
+
+// 每个平台默认返回的结果数
+const DEFAULT_LIMIT: u32 = 30;
+// 单个平台的默认超时，超时的平台不影响其他平台的结果
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(8);
+// 时长相差不超过该值时视为同一首歌
+const DURATION_TOLERANCE_MS: u64 = 3000;
+
+// 合并后的歌曲：字段为首选来源的歌曲信息，sources 为所有平台的同一首歌
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregatedTrack {
+    #[serde(flatten)]
+    pub track: Track,
+    pub sources: Vec<Track>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregatedSearch {
+    pub keyword: String,
+    pub data: Vec<AggregatedTrack>,
+    // 各平台的结果总数
+    pub totals: HashMap<String, u64>,
+    // 失败或超时的平台及原因
+    pub errors: HashMap<String, String>,
+}
+
+// 每个平台返回结果后发送 search-progress 事件，results 为目前合并的全部结果
+#[derive(Clone, Serialize)]
+struct SearchProgress<'a> {
+    search_id: &'a str,
+    platform: &'a str,
+    error: Option<&'a str>,
+    // 还未返回结果的平台
+    pending: &'a [String],
+    results: &'a [AggregatedTrack],
+}
+
+// 去掉空白和标点、统一大小写和全角字符，用于比较标题和歌手
+pub(crate) fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            // 全角 ASCII 转半角
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            c => c,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+struct MatchKey {
+    title: String,
+    artist: String,
+    duration: u64,
+}
+
+impl MatchKey {
+    fn new(track: &Track) -> Self {
+        MatchKey {
+            title: normalize(&track.title),
+            // 各平台对合唱歌手的记录不一致，只比较第一位歌手
+            artist: track
+                .artist
+                .first()
+                .map(|artist| normalize(&artist.name))
+                .unwrap_or_default(),
+            duration: track.duration,
+        }
+    }
+
+    fn matches(&self, other: &MatchKey) -> bool {
+        // 缺少时长时只比较标题和歌手
+        let duration_matches = self.duration == 0
+            || other.duration == 0
+            || self.duration.abs_diff(other.duration) <= DURATION_TOLERANCE_MS;
+        !self.title.is_empty()
+            && self.title == other.title
+            && self.artist == other.artist
+            && duration_matches
+    }
+}
+
+struct Source {
+    // 平台在请求列表中的位置，越靠前越优先
+    priority: usize,
+    // 在该平台结果中的位置
+    rank: usize,
+    track: Track,
+}
+
+struct Group {
+    key: MatchKey,
+    sources: Vec<Source>,
+}
+
+impl Group {
+    fn rank(&self) -> (usize, usize) {
+        self.sources
+            .iter()
+            .map(|source| (source.rank, source.priority))
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+// 合并各平台的搜索结果，结果与各平台返回的先后顺序无关
+#[derive(Default)]
+struct Aggregator {
+    groups: Vec<Group>,
+}
+
+impl Aggregator {
+    fn add(&mut self, priority: usize, tracks: Vec<Track>) {
+        for (rank, track) in tracks.into_iter().enumerate() {
+            let key = MatchKey::new(&track);
+            let source = Source {
+                priority,
+                rank,
+                track,
+            };
+            match self.groups.iter_mut().find(|group| group.key.matches(&key)) {
+                Some(group) => group.sources.push(source),
+                None => self.groups.push(Group {
+                    key,
+                    sources: vec![source],
+                }),
+            }
+        }
+    }
+
+    // 按各平台结果中的最靠前位置排序，相同时按平台顺序
+    fn results(&self) -> Vec<AggregatedTrack> {
+        let mut groups: Vec<&Group> = self.groups.iter().collect();
+        groups.sort_by_key(|group| group.rank());
+        groups
+            .into_iter()
+            .map(|group| {
+                let mut sources: Vec<&Source> = group.sources.iter().collect();
+                sources.sort_by_key(|source| (source.priority, source.rank));
+                AggregatedTrack {
+                    track: sources[0].track.clone(),
+                    sources: sources.iter().map(|source| source.track.clone()).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+// 同时在多个平台搜索，每个平台返回结果（或失败、超时）后通过通道发送 (平台序号, 结果)；
+// 丢弃接收端后还没有返回的请求随之取消
+pub(crate) fn search_providers(
+    app_handle: &AppHandle,
+    providers: &[Arc<dyn MusicProvider>],
+    keyword: &str,
+    limit: u32,
+    timeout: Duration,
+) -> mpsc::UnboundedReceiver<(usize, Result<SearchResult, String>)> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    for (index, provider) in providers.iter().enumerate() {
+        let provider = provider.clone();
+        let app_handle = app_handle.clone();
+        let keyword = keyword.to_string();
+        let sender = sender.clone();
+        tauri::async_runtime::spawn(async move {
+            let http = app_handle.state::<HttpClientState>();
+            let search = tokio::time::timeout(timeout, provider.search(&http, &keyword, 0, limit));
+            let result = tokio::select! {
+                result = search => result.unwrap_or_else(|_| Err("搜索超时".to_string())),
+                _ = sender.closed() => return,
+            };
+            let _ = sender.send((index, result));
+        });
+    }
+    receiver
+}
+
+// 同时搜索多个平台并合并去重，每个平台返回后通过 search-progress 事件发送目前的结果；
+// platforms 为空时搜索所有平台；search_id 同时作为请求 id，可通过 cancel_request 取消
+#[tauri::command]
+pub async fn search_all(
+    app_handle: AppHandle,
+    registry: State<'_, ProviderRegistry>,
+    search_id: String,
+    keyword: String,
+    platforms: Option<Vec<String>>,
+    limit: Option<u32>,
+    timeout_ms: Option<u64>,
+) -> Result<AggregatedSearch, String> {
+    let keyword = keyword.trim().to_string();
+    if keyword.is_empty() {
+        return Err("搜索关键字不能为空".to_string());
+    }
+    let providers = match platforms.filter(|platforms| !platforms.is_empty()) {
+        Some(platforms) => platforms
+            .iter()
+            .map(|code| registry.get(code))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => registry.all().to_vec(),
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).max(1);
+    let timeout = timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_TIMEOUT);
+
+    let search = async {
+        let mut receiver = search_providers(&app_handle, &providers, &keyword, limit, timeout);
+
+        let mut pending: Vec<String> = providers
+            .iter()
+            .map(|provider| provider.code().to_string())
+            .collect();
+        let mut aggregator = Aggregator::default();
+        let mut totals = HashMap::new();
+        let mut errors = HashMap::new();
+        let mut results = Vec::new();
+        while let Some((priority, result)) = receiver.recv().await {
+            let platform = providers[priority].code();
+            pending.retain(|code| code != platform);
+            let error = match result {
+                Ok(result) => {
+                    totals.insert(platform.to_string(), result.total);
+                    aggregator.add(priority, result.data);
+                    results = aggregator.results();
+                    None
+                }
+                Err(e) => {
+                    eprintln!("搜索 {} 失败: {}", platform, e);
+                    errors.insert(platform.to_string(), e.clone());
+                    Some(e)
+                }
+            };
+            let _ = app_handle.emit(
+                "search-progress",
+                SearchProgress {
+                    search_id: &search_id,
+                    platform,
+                    error: error.as_deref(),
+                    pending: &pending,
+                    results: &results,
+                },
+            );
+        }
+
+        Ok(AggregatedSearch {
+            keyword: keyword.clone(),
+            data: results,
+            totals,
+            errors,
+        })
+    };
+    let http = app_handle.state::<HttpClientState>();
+    let guard = http.cancels().register(search_id.clone());
+    guard.run(search).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music_provider::NamedRef;
+
+    fn track(platform: &str, id: &str, title: &str, artists: &[&str], duration: u64) -> Track {
+        Track {
+            id: id.to_string(),
+            platform: platform.to_string(),
+            title: title.to_string(),
+            artist: artists
+                .iter()
+                .map(|name| NamedRef {
+                    id: String::new(),
+                    name: name.to_string(),
+                })
+                .collect(),
+            duration,
+            ..Default::default()
+        }
+    }
+
+    // 每组的来源，格式为 平台:id
+    fn grouped(results: &[AggregatedTrack]) -> Vec<Vec<String>> {
+        results
+            .iter()
+            .map(|result| {
+                result
+                    .sources
+                    .iter()
+                    .map(|source| format!("{}:{}", source.platform, source.id))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_text() {
+        let cases = [
+            ("Hello, World!", "helloworld"),
+            ("ＡＢＣ　１２３", "abc123"),
+            ("晴天 (Live)", "晴天live"),
+            ("  Love-Story ", "lovestory"),
+            ("Ｌｏｖｅ　Ｓｔｏｒｙ", "lovestory"),
+            ("。，！？", ""),
+            ("", ""),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(normalize(text), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn groups_by_title_first_artist_and_duration() {
+        let base = track("netease", "1", "晴天", &["周杰伦"], 269_000);
+        let cases = [
+            // 标点、大小写和全角字符不影响比较
+            (track("qq", "2", "晴 天！", &["周杰伦"], 269_000), true),
+            (
+                track("qq", "2", "Ｑｉｎｇｔｉａｎ", &["周杰伦"], 269_000),
+                false,
+            ),
+            // 时长相差不超过 3 秒
+            (track("qq", "2", "晴天", &["周杰伦"], 272_000), true),
+            (track("qq", "2", "晴天", &["周杰伦"], 266_000), true),
+            (track("qq", "2", "晴天", &["周杰伦"], 272_001), false),
+            // 缺少时长时只比较标题和歌手
+            (track("qq", "2", "晴天", &["周杰伦"], 0), true),
+            // 只比较第一位歌手
+            (track("qq", "2", "晴天", &["周杰伦", "Lara"], 269_000), true),
+            (
+                track("qq", "2", "晴天", &["Lara", "周杰伦"], 269_000),
+                false,
+            ),
+            (track("qq", "2", "晴天", &[], 269_000), false),
+        ];
+        for (other, expected) in cases {
+            let mut aggregator = Aggregator::default();
+            aggregator.add(0, vec![base.clone()]);
+            aggregator.add(1, vec![other.clone()]);
+            let groups = aggregator.results().len();
+            assert_eq!(groups == 1, expected, "{:?}", other);
+        }
+
+        // 标题为空的歌曲不合并
+        let mut aggregator = Aggregator::default();
+        aggregator.add(0, vec![track("netease", "1", "？", &["周杰伦"], 0)]);
+        aggregator.add(1, vec![track("qq", "2", "!", &["周杰伦"], 0)]);
+        assert_eq!(aggregator.results().len(), 2);
+    }
+
+    #[test]
+    fn compares_against_first_track_of_group() {
+        // 每首歌只与组内第一首比较，时长不会沿着组内的歌曲逐步偏移
+        let mut aggregator = Aggregator::default();
+        aggregator.add(0, vec![track("netease", "1", "晴天", &["周杰伦"], 100_000)]);
+        aggregator.add(1, vec![track("qq", "2", "晴天", &["周杰伦"], 103_000)]);
+        aggregator.add(2, vec![track("kugou", "3", "晴天", &["周杰伦"], 106_000)]);
+        assert_eq!(
+            grouped(&aggregator.results()),
+            [vec!["netease:1", "qq:2"], vec!["kugou:3"]]
+        );
+    }
+
+    #[test]
+    fn orders_results_independent_of_arrival() {
+        let netease = vec![
+            track("netease", "n1", "晴天", &["周杰伦"], 269_000),
+            track("netease", "n2", "七里香", &["周杰伦"], 299_000),
+        ];
+        let qq = vec![
+            track("qq", "q1", "七里香", &["周杰伦"], 298_000),
+            track("qq", "q2", "稻香", &["周杰伦"], 223_000),
+        ];
+        let expected = [
+            // 位置相同时按平台顺序
+            vec!["netease:n1"],
+            vec!["netease:n2", "qq:q1"],
+            vec!["qq:q2"],
+        ];
+
+        let mut in_order = Aggregator::default();
+        in_order.add(0, netease.clone());
+        in_order.add(1, qq.clone());
+        let mut reversed = Aggregator::default();
+        reversed.add(1, qq);
+        reversed.add(0, netease);
+        for aggregator in [in_order, reversed] {
+            let results = aggregator.results();
+            assert_eq!(grouped(&results), expected);
+            // 首选来源为平台顺序靠前的歌曲
+            assert_eq!(results[1].track.platform, "netease");
+        }
+    }
+}
@@ -1,10 +1,41 @@
 use std::error::Error;
+use std::sync::Mutex;
 use tauri::{App, Manager};
 
-pub fn setup_app(_app: &mut App) -> Result<(), Box<dyn Error>> {
+use crate::audio_cache::AudioCache;
+use crate::download_queue::{self, DownloadManager};
+use crate::http_client::HttpClientState;
+use crate::library::Library;
+use crate::loudness::LoudnessJob;
+use crate::music_provider::ProviderRegistry;
+use crate::offline::OfflineManager;
+use crate::stream_proxy::{self, StreamProxy};
+
+pub fn setup_app(app: &mut App) -> Result<(), Box<dyn Error>> {
+    // 加载本地曲库分析数据
+    let data_dir = app.path().app_data_dir()?;
+    app.manage(Mutex::new(Library::load(&data_dir)));
+    app.manage(LoudnessJob::default());
+
+    // 共享的 HTTP 客户端，读取网络设置，响应缓存保存在应用缓存目录
+    let config_dir = app.path().app_config_dir()?;
+    let cache_dir = app.path().app_cache_dir()?;
+    app.manage(HttpClientState::new(&config_dir, &data_dir, &cache_dir));
+    app.manage(ProviderRegistry::default());
+
+    // 本机音频代理，播放在线歌曲时边播放边缓存
+    app.manage(AudioCache::new(&cache_dir));
+    app.manage(StreamProxy::bind()?);
+    stream_proxy::start(app.handle());
+    app.manage(OfflineManager::load(&data_dir));
+
+    // 下载队列，继续上次未完成的下载
+    app.manage(DownloadManager::load(&data_dir));
+    download_queue::start(app.handle());
+
     // 调试环境下打开开发者工具
     #[cfg(debug_assertions)]
-    open_devtools(_app)?;
+    open_devtools(app)?;
 
     Ok(())
 }
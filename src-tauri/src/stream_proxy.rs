@@ -0,0 +1,906 @@
+use rand::Rng;
+use reqwest::header::{
+    HeaderMap, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, HOST, IF_RANGE, RANGE,
+};
+use reqwest::{Response, StatusCode, Url};
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, TcpListener as StdTcpListener};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::audio_cache::{AudioCache, AudioCacheEntry, WriteGuard};
+use crate::http_client::HttpClientState;
+use crate::http_download::{content_range, download_to, response_validator, resumes_part};
+use crate::platform::platform_for_host;
+
+const STREAM_PATH_PREFIX: &str = "/stream/";
+// 记录的音频地址数量上限，超出时删除最早记录的
+const MAX_SOURCES: usize = 64;
+// 请求头的最大长度
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+const DEFAULT_CONTENT_TYPE: &str = "audio/mpeg";
+// 代理地址带上扩展名，播放器据此判断格式
+const DEFAULT_EXTENSION: &str = "mp3";
+// 音频扩展名与文件类型
+const AUDIO_TYPES: &[(&str, &str)] = &[
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("mp4", "audio/mp4"),
+    ("aac", "audio/aac"),
+    ("flac", "audio/flac"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+];
+// 各平台的音频地址需要带上对应的 Referer
+const PLATFORM_REFERERS: &[(&str, &str)] = &[
+    ("netease", "https://music.163.com/"),
+    ("qq", "https://y.qq.com/"),
+    ("kugou", "https://www.kugou.com/"),
+];
+
+#[derive(Debug, Clone)]
+struct StreamSource {
+    platform: String,
+    id: String,
+    url: String,
+    headers: HashMap<String, String>,
+}
+
+// 本机回环地址上的音频代理：转发 Range 请求，并把读取到的音频写入缓存
+pub struct StreamProxy {
+    port: u16,
+    // 每次启动随机生成，放在代理地址中，本机其他程序无法猜到
+    token: String,
+    // 启动后取出
+    listener: Mutex<Option<StdTcpListener>>,
+    // 缓存键 -> 记录时间和音频地址
+    sources: Mutex<HashMap<String, (Instant, StreamSource)>>,
+}
+
+impl StreamProxy {
+    // 在随机端口上监听
+    pub fn bind() -> Result<Self, String> {
+        let listener = StdTcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .map_err(|e| format!("启动音频代理失败: {}", e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| format!("启动音频代理失败: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("启动音频代理失败: {}", e))?
+            .port();
+        Ok(StreamProxy {
+            port,
+            token: format!("{:032x}", rand::thread_rng().gen::<u128>()),
+            listener: Mutex::new(Some(listener)),
+            sources: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn source(&self, key: &str) -> Option<StreamSource> {
+        let sources = self.sources.lock().ok()?;
+        sources.get(key).map(|(_, source)| source.clone())
+    }
+
+    fn remember(&self, key: String, source: StreamSource) -> Result<(), String> {
+        let mut sources = self.sources.lock().map_err(|e| e.to_string())?;
+        sources.insert(key, (Instant::now(), source));
+        while sources.len() > MAX_SOURCES {
+            let oldest = sources
+                .iter()
+                .min_by_key(|(_, (added, _))| *added)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => sources.remove(&key),
+                None => break,
+            };
+        }
+        Ok(())
+    }
+
+    // 歌曲已完整缓存，之后不再需要音频地址
+    fn forget(&self, key: &str) {
+        if let Ok(mut sources) = self.sources.lock() {
+            sources.remove(key);
+        }
+    }
+
+    fn host(&self) -> String {
+        format!("{}:{}", Ipv4Addr::LOCALHOST, self.port)
+    }
+
+    fn origin(&self) -> String {
+        format!("http://{}", self.host())
+    }
+
+    fn path_prefix(&self) -> String {
+        format!("{}{}/", STREAM_PATH_PREFIX, self.token)
+    }
+
+    fn stream_url(&self, key: &str, extension: &str) -> String {
+        format!(
+            "{}{}{}.{}",
+            self.origin(),
+            self.path_prefix(),
+            key,
+            extension
+        )
+    }
+
+    // 只接受发往 127.0.0.1:端口 的请求，防止网页通过 DNS 重绑定访问代理
+    fn allows_host(&self, host: Option<&str>) -> bool {
+        host.is_some_and(|host| host.eq_ignore_ascii_case(&self.host()))
+    }
+
+    // 路径为 /stream/令牌/缓存键.扩展名，令牌不一致时返回 None
+    fn stream_key<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let name = path.strip_prefix(&self.path_prefix())?;
+        name.split(['.', '?']).next().filter(|key| !key.is_empty())
+    }
+
+    // 已完整缓存的歌曲的播放地址，不需要音频地址
+    pub fn cached_stream_url(
+        &self,
+        cache: &AudioCache,
+        platform: &str,
+        id: &str,
+    ) -> Option<String> {
+        let key = AudioCache::key_for(platform, id);
+        let entry = cache.entry(&key).filter(|entry| entry.complete)?;
+        Some(self.stream_url(&key, stream_extension(None, Some(&entry))))
+    }
+}
+
+// 请求的字节范围
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ByteRange {
+    // bytes=start- 或 bytes=start-end
+    From(u64, Option<u64>),
+    // bytes=-length，文件末尾的 length 个字节
+    Suffix(u64),
+}
+
+impl ByteRange {
+    // 只支持单个范围
+    fn parse(value: &str) -> Option<ByteRange> {
+        let range = value.trim().strip_prefix("bytes=")?.trim();
+        if range.contains(',') {
+            return None;
+        }
+        let (start, end) = range.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        if start.is_empty() {
+            return end.parse().ok().map(ByteRange::Suffix);
+        }
+        let start = start.parse().ok()?;
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse().ok()?)
+        };
+        Some(ByteRange::From(start, end))
+    }
+
+    // 按文件大小计算实际范围 [start, end)，超出文件时为空
+    fn resolve(&self, total: u64) -> Option<(u64, u64)> {
+        let (start, end) = match *self {
+            ByteRange::From(start, end) => {
+                let end = end.map_or(total, |end| end.saturating_add(1).min(total));
+                (start, end)
+            }
+            ByteRange::Suffix(length) => (total.saturating_sub(length), total),
+        };
+        (start < end).then_some((start, end))
+    }
+}
+
+struct ProxyRequest {
+    method: String,
+    path: String,
+    host: Option<String>,
+    range: Option<ByteRange>,
+    // 原始的 Range 请求头，直接转发时使用
+    range_header: Option<String>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<ProxyRequest, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let head_end = loop {
+        if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break index;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err("请求头过长".to_string());
+        }
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| format!("读取请求失败: {}", e))?;
+        if read == 0 {
+            return Err("连接已关闭".to_string());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let head = String::from_utf8_lossy(&buffer[..head_end]);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<_> = lines.filter_map(|line| line.split_once(':')).collect();
+    let header = |header_name: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(header_name))
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let range_header = header(RANGE.as_str());
+    Ok(ProxyRequest {
+        method,
+        host: header(HOST.as_str()),
+        range: range_header.as_deref().and_then(ByteRange::parse),
+        path,
+        range_header,
+    })
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("")
+}
+
+async fn write_head(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(&str, String)],
+) -> Result<(), String> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason_phrase(status));
+    // 播放器以 crossOrigin="anonymous" 加载音频
+    head.push_str("Access-Control-Allow-Origin: *\r\n");
+    head.push_str(
+        "Access-Control-Expose-Headers: Content-Length, Content-Range, Accept-Ranges\r\n",
+    );
+    head.push_str("Accept-Ranges: bytes\r\n");
+    head.push_str("Cache-Control: no-store\r\n");
+    head.push_str("Connection: close\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream
+        .write_all(head.as_bytes())
+        .await
+        .map_err(|e| format!("发送响应失败: {}", e))
+}
+
+async fn write_error(stream: &mut TcpStream, status: u16, message: &str) -> Result<(), String> {
+    let headers = [
+        ("Content-Type", "text/plain; charset=utf-8".to_string()),
+        ("Content-Length", message.len().to_string()),
+    ];
+    write_head(stream, status, &headers).await?;
+    stream
+        .write_all(message.as_bytes())
+        .await
+        .map_err(|e| format!("发送响应失败: {}", e))
+}
+
+// 把文件中从 start 开始的 length 个字节发送给播放器
+async fn send_file_range(
+    stream: &mut TcpStream,
+    path: &Path,
+    start: u64,
+    length: u64,
+) -> Result<(), String> {
+    let mut file = File::open(path)
+        .await
+        .map_err(|e| format!("读取音频缓存失败: {}", e))?;
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|e| format!("读取音频缓存失败: {}", e))?;
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut remaining = length;
+    while remaining > 0 {
+        let size = remaining.min(COPY_BUFFER_SIZE as u64) as usize;
+        let read = file
+            .read(&mut buffer[..size])
+            .await
+            .map_err(|e| format!("读取音频缓存失败: {}", e))?;
+        if read == 0 {
+            return Err("音频缓存不完整".to_string());
+        }
+        stream
+            .write_all(&buffer[..read])
+            .await
+            .map_err(|e| format!("发送响应失败: {}", e))?;
+        remaining -= read as u64;
+    }
+    Ok(())
+}
+
+// 响应头：完整响应为 200，Range 请求为 206
+fn range_head(
+    range: Option<ByteRange>,
+    start: u64,
+    end: u64,
+    total: u64,
+    content_type: &str,
+) -> (u16, Vec<(&'static str, String)>) {
+    let mut headers = vec![
+        ("Content-Type", content_type.to_string()),
+        ("Content-Length", (end - start).to_string()),
+    ];
+    if range.is_none() {
+        return (200, headers);
+    }
+    headers.push((
+        "Content-Range",
+        format!("bytes {}-{}/{}", start, end - 1, total),
+    ));
+    (206, headers)
+}
+
+async fn send_unsatisfiable(stream: &mut TcpStream, total: u64) -> Result<(), String> {
+    let headers = [
+        ("Content-Range", format!("bytes */{}", total)),
+        ("Content-Length", "0".to_string()),
+    ];
+    write_head(stream, 416, &headers).await
+}
+
+// 播放完整缓存的音频，不需要网络
+async fn serve_cached(
+    stream: &mut TcpStream,
+    request: &ProxyRequest,
+    path: &Path,
+    entry: &AudioCacheEntry,
+) -> Result<(), String> {
+    let total = entry.size;
+    let range = request.range.unwrap_or(ByteRange::From(0, None));
+    let Some((start, end)) = range.resolve(total) else {
+        return send_unsatisfiable(stream, total).await;
+    };
+    let content_type = entry
+        .content_type
+        .as_deref()
+        .unwrap_or(DEFAULT_CONTENT_TYPE);
+    let (status, headers) = range_head(request.range, start, end, total, content_type);
+    write_head(stream, status, &headers).await?;
+    if request.method == "HEAD" {
+        return Ok(());
+    }
+    send_file_range(stream, path, start, end - start).await
+}
+
+fn default_referer(source: &StreamSource) -> Option<&'static str> {
+    let host_platform = Url::parse(&source.url)
+        .ok()
+        .and_then(|url| url.host_str().map(platform_for_host));
+    PLATFORM_REFERERS
+        .iter()
+        .find(|(platform, _)| *platform == source.platform || Some(*platform) == host_platform)
+        .map(|(_, referer)| *referer)
+}
+
+// 调用方没有指定 Referer 时按平台添加
+fn upstream_headers(source: &StreamSource) -> HashMap<String, String> {
+    let mut headers = source.headers.clone();
+    let has_referer = headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("referer"));
+    if let (false, Some(referer)) = (has_referer, default_referer(source)) {
+        headers.insert("Referer".to_string(), referer.to_string());
+    }
+    headers
+}
+
+// 请求音频地址，range 为转发的 Range 请求头，if_range 为续传时已缓存部分的校验值
+async fn send_upstream(
+    http: &HttpClientState,
+    source: &StreamSource,
+    range: Option<String>,
+    if_range: Option<&str>,
+) -> Result<Response, String> {
+    let headers = upstream_headers(source);
+    let mut builder = http.download_request(&source.url, headers).await?;
+    if let Some(range) = range {
+        builder = builder.header(RANGE, range);
+        if let Some(validator) = if_range {
+            builder = builder.header(IF_RANGE, validator);
+        }
+    }
+    builder
+        .send()
+        .await
+        .map_err(|e| format!("请求音频失败: {}", e))
+}
+
+fn header_str(headers: &HeaderMap, name: impl reqwest::header::AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+// 读取下一段响应体，两次收到数据的间隔超过读取超时时失败
+async fn next_chunk(
+    http: &HttpClientState,
+    response: &mut Response,
+) -> Result<Option<impl AsRef<[u8]>>, String> {
+    let chunk = match http.settings().timeouts.read() {
+        Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+            .await
+            .map_err(|_| "读取响应超时".to_string())?,
+        None => response.chunk().await,
+    };
+    chunk.map_err(|e| format!("读取音频失败: {}", e))
+}
+
+// 直接转发，不写入缓存：请求位置在已缓存部分之后，或其他连接正在写入同一首歌
+async fn pass_through(
+    stream: &mut TcpStream,
+    http: &HttpClientState,
+    request: &ProxyRequest,
+    source: &StreamSource,
+) -> Result<(), String> {
+    let mut response = match send_upstream(http, source, request.range_header.clone(), None).await {
+        Ok(response) => response,
+        Err(e) => return write_error(stream, 502, &e).await,
+    };
+    let status = response.status().as_u16();
+    let mut headers = Vec::new();
+    for (name, header) in [
+        ("Content-Type", CONTENT_TYPE),
+        ("Content-Length", CONTENT_LENGTH),
+        ("Content-Range", CONTENT_RANGE),
+    ] {
+        if let Some(value) = header_str(response.headers(), header) {
+            headers.push((name, value));
+        }
+    }
+    write_head(stream, status, &headers).await?;
+    if request.method == "HEAD" {
+        return Ok(());
+    }
+    while let Some(chunk) = next_chunk(http, &mut response).await? {
+        stream
+            .write_all(chunk.as_ref())
+            .await
+            .map_err(|e| format!("发送响应失败: {}", e))?;
+    }
+    Ok(())
+}
+
+// 先发送已缓存的部分，再从缓存末尾继续请求音频，边播放边写入缓存
+async fn stream_and_cache(
+    stream: &mut TcpStream,
+    http: &HttpClientState,
+    cache: &AudioCache,
+    request: &ProxyRequest,
+    key: &str,
+    source: &StreamSource,
+    guard: WriteGuard,
+) -> Result<(), String> {
+    let part = cache.part_path(key);
+    // 有缓存记录时已缓存的部分才可信
+    let entry = cache.entry(key).filter(|entry| !entry.complete);
+    let mut cached = match entry {
+        Some(_) => fs::metadata(&part).await.map(|m| m.len()).unwrap_or(0),
+        None => 0,
+    };
+    let requested_start = match request.range {
+        None => 0,
+        Some(ByteRange::From(start, _)) => start,
+        Some(ByteRange::Suffix(_)) => u64::MAX,
+    };
+    // 请求位置在已缓存部分之后时无法连续写入缓存
+    if requested_start > cached {
+        drop(guard);
+        return pass_through(stream, http, request, source).await;
+    }
+
+    let range = (cached > 0).then(|| format!("bytes={}-", cached));
+    let if_range = entry.as_ref().and_then(|entry| entry.validator.as_deref());
+    let mut response = match send_upstream(http, source, range, if_range).await {
+        Ok(response) => response,
+        Err(e) => return write_error(stream, 502, &e).await,
+    };
+    // 已缓存的部分与服务端的文件不一致，丢弃后重新缓存；校验值不一致时服务端直接返回完整文件
+    let mismatched = cached > 0
+        && match response.status() {
+            StatusCode::PARTIAL_CONTENT => !entry.as_ref().is_some_and(|entry| {
+                resumes_part(
+                    response.headers(),
+                    cached,
+                    entry.total,
+                    entry.validator.as_deref(),
+                )
+            }),
+            StatusCode::RANGE_NOT_SATISFIABLE => true,
+            _ => false,
+        };
+    if mismatched {
+        cached = 0;
+        drop(response);
+        response = match send_upstream(http, source, None, None).await {
+            Ok(response) => response,
+            Err(e) => return write_error(stream, 502, &e).await,
+        };
+    }
+    if !response.status().is_success() {
+        let message = format!("音频地址返回状态码 {}", response.status().as_u16());
+        return write_error(stream, 502, &message).await;
+    }
+
+    // 服务端不支持 Range 时返回完整文件，从头缓存
+    let offset = if response.status() == StatusCode::PARTIAL_CONTENT {
+        cached
+    } else {
+        0
+    };
+    let total = if offset > 0 {
+        content_range(response.headers()).and_then(|(_, total)| total)
+    } else {
+        response.content_length()
+    };
+    // 不知道文件大小时无法响应 Range 请求，也无法判断缓存是否完整
+    let Some(total) = total else {
+        drop(response);
+        drop(guard);
+        return pass_through(stream, http, request, source).await;
+    };
+    let range = request.range.unwrap_or(ByteRange::From(0, None));
+    let Some((start, end)) = range.resolve(total) else {
+        return send_unsatisfiable(stream, total).await;
+    };
+
+    let content_type = header_str(response.headers(), CONTENT_TYPE)
+        .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+    let validator = response_validator(response.headers()).or_else(|| {
+        entry
+            .filter(|_| offset > 0)
+            .and_then(|entry| entry.validator)
+    });
+    cache.begin(
+        key,
+        AudioCacheEntry {
+            platform: source.platform.clone(),
+            id: source.id.clone(),
+            content_type: Some(content_type.clone()),
+            total: Some(total),
+            validator,
+            size: offset,
+            ..Default::default()
+        },
+    );
+    let mut file = if offset > 0 {
+        OpenOptions::new().append(true).open(&part).await
+    } else {
+        File::create(&part).await
+    }
+    .map_err(|e| format!("打开音频缓存失败: {}", e))?;
+
+    let (status, headers) = range_head(request.range, start, end, total, &content_type);
+    write_head(stream, status, &headers).await?;
+    if start < offset {
+        send_file_range(stream, &part, start, end.min(offset) - start).await?;
+    }
+
+    let mut position = offset;
+    let result = loop {
+        // 播放器已收到请求的全部内容
+        if position >= end {
+            break Ok(());
+        }
+        let chunk = match next_chunk(http, &mut response).await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+        let chunk = chunk.as_ref();
+        if let Err(e) = file.write_all(chunk).await {
+            break Err(format!("写入音频缓存失败: {}", e));
+        }
+        let chunk_start = position;
+        position += chunk.len() as u64;
+        // 发送与请求范围重叠的部分
+        let (from, to) = (start.max(chunk_start), end.min(position));
+        if from < to {
+            let data = &chunk[(from - chunk_start) as usize..(to - chunk_start) as usize];
+            // 播放器断开连接（如跳转播放位置）时停止，已缓存的部分下次继续使用
+            if stream.write_all(data).await.is_err() {
+                break Ok(());
+            }
+        }
+    };
+    if let Err(e) = file.flush().await {
+        eprintln!("写入音频缓存失败: {}", e);
+    }
+    drop(file);
+    cache.finish(key, position, position == total);
+    result
+}
+
+async fn serve(app_handle: &AppHandle, stream: &mut TcpStream) -> Result<(), String> {
+    let request = match read_request(stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            let _ = write_error(stream, 400, &e).await;
+            return Err(e);
+        }
+    };
+    let proxy = app_handle.state::<StreamProxy>();
+    if !proxy.allows_host(request.host.as_deref()) {
+        return write_error(stream, 403, "不允许的请求").await;
+    }
+    if request.method == "OPTIONS" {
+        let headers = [
+            (
+                "Access-Control-Allow-Methods",
+                "GET, HEAD, OPTIONS".to_string(),
+            ),
+            ("Access-Control-Allow-Headers", "Range".to_string()),
+            ("Content-Length", "0".to_string()),
+        ];
+        return write_head(stream, 204, &headers).await;
+    }
+    if request.method != "GET" && request.method != "HEAD" {
+        return write_error(stream, 405, "只支持 GET 请求").await;
+    }
+    let Some(key) = proxy.stream_key(&request.path).map(str::to_string) else {
+        return write_error(stream, 404, "音频不存在").await;
+    };
+
+    let cache = app_handle.state::<AudioCache>();
+    if let Some(entry) = cache.entry(&key).filter(|entry| entry.complete) {
+        proxy.forget(&key);
+        cache.touch(&key);
+        return serve_cached(stream, &request, &cache.audio_path(&key), &entry).await;
+    }
+    let source = match proxy.source(&key) {
+        Some(source) => source,
+        None => return write_error(stream, 404, "音频未缓存").await,
+    };
+    let http = app_handle.state::<HttpClientState>();
+    // 同一首歌同时只有一个连接写入缓存，其他连接直接转发
+    let guard = match request.method.as_str() {
+        "GET" => cache.try_write(&key),
+        _ => None,
+    };
+    let result = match guard {
+        Some(guard) => {
+            stream_and_cache(stream, &http, &cache, &request, &key, &source, guard).await
+        }
+        None => pass_through(stream, &http, &request, &source).await,
+    };
+    if cache.entry(&key).is_some_and(|entry| entry.complete) {
+        proxy.forget(&key);
+    }
+    result
+}
+
+// 启动音频代理，每个连接处理一个请求
+pub fn start(app_handle: &AppHandle) {
+    let listener = match app_handle.state::<StreamProxy>().listener.lock() {
+        Ok(mut listener) => listener.take(),
+        Err(_) => None,
+    };
+    let Some(listener) = listener else {
+        return;
+    };
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("启动音频代理失败: {}", e);
+                return;
+            }
+        };
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("音频代理接受连接失败: {}", e);
+                    continue;
+                }
+            };
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = serve(&app_handle, &mut stream).await {
+                    eprintln!("音频代理请求失败: {}", e);
+                }
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+}
+
+// 代理地址的扩展名：优先使用音频地址中的扩展名，其次按缓存的文件类型
+fn stream_extension(url: Option<&str>, entry: Option<&AudioCacheEntry>) -> &'static str {
+    let from_url = url.and_then(url_audio_type).map(|(extension, _)| extension);
+    let from_type = entry
+        .and_then(|entry| entry.content_type.as_deref())
+        .and_then(|content_type| {
+            AUDIO_TYPES
+                .iter()
+                .find(|(_, audio_type)| *audio_type == content_type)
+        })
+        .map(|(extension, _)| *extension);
+    from_url.or(from_type).unwrap_or(DEFAULT_EXTENSION)
+}
+
+// 按音频地址中的扩展名判断文件类型
+fn url_audio_type(url: &str) -> Option<(&'static str, &'static str)> {
+    let path = Url::parse(url).ok()?.path().to_ascii_lowercase();
+    AUDIO_TYPES
+        .iter()
+        .find(|(extension, _)| path.ends_with(&format!(".{}", extension)))
+        .copied()
+}
+
+// 下载歌曲的完整音频到缓存，用于离线播放；pinned 为 true 时超出容量也不删除
+pub(crate) async fn prefetch_audio(
+    http: &HttpClientState,
+    cache: &AudioCache,
+    platform: &str,
+    id: &str,
+    url: &str,
+    pinned: bool,
+) -> Result<(), String> {
+    let key = AudioCache::key_for(platform, id);
+    if cache.entry(&key).is_some_and(|entry| entry.complete) {
+        return Ok(());
+    }
+    let _guard = cache
+        .try_write(&key)
+        .ok_or_else(|| format!("歌曲正在缓存: {}", id))?;
+    let source = StreamSource {
+        platform: platform.to_string(),
+        id: id.to_string(),
+        url: url.to_string(),
+        headers: HashMap::new(),
+    };
+    let headers = upstream_headers(&source);
+    let result = download_to(http, url, &cache.audio_path(&key), &headers, |_, _| {}).await?;
+    let content_type =
+        url_audio_type(url).map_or(DEFAULT_CONTENT_TYPE, |(_, audio_type)| audio_type);
+    cache.insert_complete(
+        &key,
+        AudioCacheEntry {
+            platform: source.platform,
+            id: source.id,
+            content_type: Some(content_type.to_string()),
+            size: result.size,
+            pinned,
+            ..Default::default()
+        },
+    );
+    Ok(())
+}
+
+// 返回歌曲的代理播放地址；url 为空时只能播放已完整缓存的歌曲
+#[tauri::command]
+pub fn proxy_stream_url(
+    proxy: State<'_, StreamProxy>,
+    cache: State<'_, AudioCache>,
+    platform: String,
+    id: String,
+    url: Option<String>,
+    headers: Option<HashMap<String, String>>,
+) -> Result<String, String> {
+    let key = AudioCache::key_for(&platform, &id);
+    let entry = cache.entry(&key);
+    let url = url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    // 已经是代理地址（如离线模式下的播放地址）
+    let stream_prefix = format!("{}{}", proxy.origin(), proxy.path_prefix());
+    if let Some(url) = url.as_ref().filter(|url| url.starts_with(&stream_prefix)) {
+        return Ok(url.clone());
+    }
+    match &url {
+        Some(url) => {
+            let parsed = Url::parse(url).map_err(|e| format!("无效的音频地址: {}", e))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(format!("不支持的音频地址: {}", url));
+            }
+            let source = StreamSource {
+                platform,
+                id,
+                url: url.clone(),
+                headers: headers.unwrap_or_default(),
+            };
+            proxy.remember(key.clone(), source)?;
+        }
+        None if entry.as_ref().is_some_and(|entry| entry.complete) => {}
+        None => return Err("歌曲未缓存，缺少音频地址".to_string()),
+    }
+    let extension = stream_extension(url.as_deref(), entry.as_ref());
+    Ok(proxy.stream_url(&key, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn parse_byte_ranges() {
+        assert_eq!(
+            ByteRange::parse("bytes=100-"),
+            Some(ByteRange::From(100, None))
+        );
+        assert_eq!(
+            ByteRange::parse("bytes=0-99"),
+            Some(ByteRange::From(0, Some(99)))
+        );
+        assert_eq!(ByteRange::parse("bytes=-500"), Some(ByteRange::Suffix(500)));
+        assert_eq!(ByteRange::parse("bytes=0-1,5-6"), None);
+        assert_eq!(ByteRange::parse("items=0-1"), None);
+        assert_eq!(ByteRange::From(0, Some(99)).resolve(50), Some((0, 50)));
+        assert_eq!(ByteRange::Suffix(500).resolve(100), Some((0, 100)));
+        assert_eq!(ByteRange::From(100, None).resolve(100), None);
+    }
+
+    fn source(url: &str) -> StreamSource {
+        StreamSource {
+            platform: "netease".to_string(),
+            id: "1".to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn stream_url_requires_token_and_host() {
+        let proxy = StreamProxy::bind().unwrap();
+        let url = Url::parse(&proxy.stream_url("netease_1", "mp3")).unwrap();
+        assert_eq!(proxy.stream_key(url.path()), Some("netease_1"));
+        assert_eq!(proxy.stream_key("/stream/netease_1.mp3"), None);
+        let guessed = format!("/stream/{}/netease_1.mp3", "0".repeat(32));
+        assert_eq!(proxy.stream_key(&guessed), None);
+        assert_eq!(proxy.stream_key(&proxy.path_prefix()), None);
+
+        let host = format!("127.0.0.1:{}", proxy.port);
+        assert!(proxy.allows_host(Some(&host)));
+        assert!(!proxy.allows_host(Some(&format!("evil.example:{}", proxy.port))));
+        assert!(!proxy.allows_host(Some("127.0.0.1")));
+        assert!(!proxy.allows_host(None));
+        // 每次启动的令牌不同
+        assert_ne!(StreamProxy::bind().unwrap().token, proxy.token);
+    }
+
+    #[test]
+    fn sources_are_capped_and_forgotten() {
+        let proxy = StreamProxy::bind().unwrap();
+        for index in 0..MAX_SOURCES + 10 {
+            let url = format!("https://m701.music.126.net/{}.mp3", index);
+            proxy
+                .remember(format!("key_{}", index), source(&url))
+                .unwrap();
+        }
+        assert_eq!(proxy.sources.lock().unwrap().len(), MAX_SOURCES);
+        assert!(proxy.source("key_0").is_none());
+        let last = format!("key_{}", MAX_SOURCES + 9);
+        assert!(proxy.source(&last).is_some());
+        proxy.forget(&last);
+        assert!(proxy.source(&last).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::audio_decoder::decode_file;
+use crate::library::modified_secs;
+
+const WAVEFORM_CACHE_DIR: &str = "waveforms";
+// 先按固定帧数求出细粒度的峰值，再合并到请求的分辨率
+const FRAMES_PER_CHUNK: usize = 256;
+const MIN_RESOLUTION: usize = 16;
+const MAX_RESOLUTION: usize = 10000;
+
+// 波形峰值数据，每个点为该区间内的 [最小值, 最大值]（-1.0 ~ 1.0）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveformPeaks {
+    pub resolution: usize,
+    // 时长（毫秒），与 AudioMetadata 一致
+    pub duration: f64,
+    pub peaks: Vec<[f32; 2]>,
+}
+
+#[tauri::command]
+pub async fn get_waveform_peaks(
+    app_handle: AppHandle,
+    full_path: String,
+    resolution: usize,
+) -> Result<WaveformPeaks, String> {
+    let resolution = resolution.clamp(MIN_RESOLUTION, MAX_RESOLUTION);
+    let cache_dir = app_handle
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("获取缓存目录失败: {}", e))?
+        .join(WAVEFORM_CACHE_DIR);
+
+    // 解码耗时较长，放到阻塞线程中执行
+    tauri::async_runtime::spawn_blocking(move || {
+        load_or_generate(&cache_dir, Path::new(&full_path), resolution)
+    })
+    .await
+    .map_err(|e| format!("生成波形失败: {}", e))?
+}
+
+fn load_or_generate(
+    cache_dir: &Path,
+    path: &Path,
+    resolution: usize,
+) -> Result<WaveformPeaks, String> {
+    let cache_file = cache_file_path(cache_dir, &cache_key(path)?, resolution);
+
+    if let Ok(content) = fs::read_to_string(&cache_file) {
+        match serde_json::from_str::<WaveformPeaks>(&content) {
+            Ok(peaks) => return Ok(peaks),
+            Err(e) => eprintln!("波形缓存已损坏 {:?}: {}", cache_file, e),
+        }
+    }
+
+    let peaks = generate_peaks(path, resolution)?;
+
+    // 缓存写入失败不影响返回结果
+    let saved = fs::create_dir_all(cache_dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string(&peaks).map_err(|e| e.to_string()))
+        .and_then(|content| fs::write(&cache_file, content).map_err(|e| e.to_string()));
+    if let Err(e) = saved {
+        eprintln!("保存波形缓存失败 {:?}: {}", cache_file, e);
+    }
+
+    Ok(peaks)
+}
+
+// 缓存键：路径、修改时间和文件大小，文件被替换或修改后重新生成，不需要读取整个文件
+fn cache_key(path: &Path) -> Result<String, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("读取文件信息失败: {}", e))?;
+    let mut hasher = Sha256::new();
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(modified_secs(path).unwrap_or(0).to_le_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn cache_file_path(cache_dir: &Path, hash: &str, resolution: usize) -> PathBuf {
+    cache_dir.join(format!("{}-{}.json", hash, resolution))
+}
+
+fn generate_peaks(path: &Path, resolution: usize) -> Result<WaveformPeaks, String> {
+    let mut chunks: Vec<[f32; 2]> = Vec::new();
+    let mut current = [f32::MAX, f32::MIN];
+    let mut frames_in_chunk = 0;
+    let mut total_frames: u64 = 0;
+
+    let info = decode_file(path, None, |info, samples| {
+        let channels = info.channels.max(1);
+        for frame in samples.chunks_exact(channels) {
+            // 多声道混合为单声道
+            let value = frame.iter().sum::<f32>() / channels as f32;
+            current[0] = current[0].min(value);
+            current[1] = current[1].max(value);
+            frames_in_chunk += 1;
+            total_frames += 1;
+            if frames_in_chunk == FRAMES_PER_CHUNK {
+                chunks.push(current);
+                current = [f32::MAX, f32::MIN];
+                frames_in_chunk = 0;
+            }
+        }
+        ControlFlow::Continue(())
+    })?;
+    if frames_in_chunk > 0 {
+        chunks.push(current);
+    }
+
+    let duration = if info.sample_rate > 0 {
+        total_frames as f64 * 1000.0 / info.sample_rate as f64
+    } else {
+        0.0
+    };
+
+    Ok(WaveformPeaks {
+        resolution,
+        duration,
+        peaks: downsample(&chunks, resolution),
+    })
+}
+
+// 把细粒度峰值合并为指定数量的点；峰值少于点数时相邻的点重复使用同一个峰值，没有数据时为零
+fn downsample(chunks: &[[f32; 2]], resolution: usize) -> Vec<[f32; 2]> {
+    (0..resolution)
+        .map(|i| {
+            let start = i * chunks.len() / resolution;
+            let end = ((i + 1) * chunks.len() / resolution).max(start + 1);
+            chunks
+                .get(start..end.min(chunks.len()))
+                .filter(|bucket| !bucket.is_empty())
+                .map(|bucket| {
+                    bucket.iter().fold([f32::MAX, f32::MIN], |acc, peak| {
+                        [acc[0].min(peak[0]), acc[1].max(peak[1])]
+                    })
+                })
+                .map(|[min, max]| [min.clamp(-1.0, 1.0), max.clamp(-1.0, 1.0)])
+                .unwrap_or([0.0, 0.0])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsamples_to_resolution() {
+        let chunks = [[-0.1, 0.2], [-0.5, 0.1], [-0.2, 0.4], [-0.3, 0.3]];
+        assert_eq!(downsample(&chunks, 2), [[-0.5, 0.2], [-0.3, 0.4]]);
+        assert_eq!(downsample(&chunks, 1), [[-0.5, 0.4]]);
+        // 不能整除时每个点覆盖的峰值数不同
+        let five = [
+            [-0.1, 0.1],
+            [-0.2, 0.2],
+            [-0.3, 0.3],
+            [-0.4, 0.4],
+            [-0.5, 0.5],
+        ];
+        assert_eq!(downsample(&five, 2), [[-0.2, 0.2], [-0.5, 0.5]]);
+        // 超出范围的值被截断
+        assert_eq!(downsample(&[[-1.5, 2.0]], 1), [[-1.0, 1.0]]);
+    }
+
+    #[test]
+    fn short_input_repeats_chunks() {
+        let chunks = [[-0.1, 0.1], [-0.2, 0.2]];
+        assert_eq!(
+            downsample(&chunks, 4),
+            [[-0.1, 0.1], [-0.1, 0.1], [-0.2, 0.2], [-0.2, 0.2]]
+        );
+        assert_eq!(downsample(&[], 3), [[0.0, 0.0]; 3]);
+    }
+
+    #[test]
+    fn cache_key_follows_path_and_size() {
+        let dir = std::env::temp_dir().join("waveform_cache_key");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.mp3"), dir.join("b.mp3"));
+        fs::write(&a, b"audio").unwrap();
+        fs::write(&b, b"audio").unwrap();
+
+        let key = cache_key(&a).unwrap();
+        assert_eq!(cache_key(&a).unwrap(), key);
+        assert_ne!(cache_key(&b).unwrap(), key);
+        fs::write(&a, b"longer audio").unwrap();
+        assert_ne!(cache_key(&a).unwrap(), key);
+        assert!(cache_key(&dir.join("missing.mp3")).is_err());
+    }
+}
@@ -4,7 +4,7 @@ import EventBus from "../common/EventBus";
 import { Track } from "./Track";
 import { WebAudioApi } from "./WebAudioApi";
 import { fetch } from "@tauri-apps/plugin-http";
-import { convertFileSrc } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 
 let singleton = null;
 
@@ -46,6 +46,16 @@ export class Player {
     try {
       if (track.platform === "local") {
         return convertFileSrc(url);
+      }
+      //已经是本地文件的 asset 地址（如无法播放时找到的本地替代来源）
+      if (url.startsWith(convertFileSrc(""))) {
+        return url;
+      }
+      //通过本机代理边播放边缓存，代理不可用时下载完整文件
+      const { platform, id } = track;
+      const proxyUrl = await invoke("proxy_stream_url", { platform, id: String(id), url }).catch((error) => console.log(error));
+      if (proxyUrl) {
+        return proxyUrl;
       } else {
         const response = await fetch(url);
         if (!response.ok) {
@@ -4,7 +4,11 @@ import { join, appDataDir } from "@tauri-apps/api/path";
 import { readDir } from "@tauri-apps/plugin-fs";
 import { invoke } from "@tauri-apps/api/core";
 import { Track } from "../common/Track";
-import CryptoJS from "crypto-js";
+import { LocalMusic } from "../vendor/localmusic";
+import { usePlayStore } from "./playStore";
+
+//后端生成的稳定标识前缀：音频数据哈希、文件路径哈希
+const isStableTrackId = (id) => typeof id === "string" && (id.startsWith("audio-") || id.startsWith("path-"));
 
 export const useLocalMusicStore = defineStore("localMusic", {
   state: () => ({
@@ -39,9 +43,14 @@ export const useLocalMusicStore = defineStore("localMusic", {
       } catch (error) {
         console.error("扫描音频文件失败:", error);
       } finally {
+        await this.saveLibrary();
         this.isLoading = false;
       }
     },
+    //后端逐个文件只在内存中记录标识，整批处理完成后统一保存曲库
+    async saveLibrary() {
+      await invoke("save_library").catch((error) => console.error(`保存曲库失败: ${error}`));
+    },
     async getAudioMetadata(fullPath) {
       try {
         return await invoke("get_audio_metadata", { fullPath: fullPath });
@@ -65,7 +74,7 @@ export const useLocalMusicStore = defineStore("localMusic", {
               const metadata = await this.getAudioMetadata(fullPath);
               if (metadata) {
                 console.log("metadata", metadata);
-                const hash = CryptoJS.MD5(metadata.title + metadata.duration).toString();
+                const hash = metadata.track_id;
                 const coverData = metadata.cover_data ? `data:${metadata.cover_mime_type};base64,${metadata.cover_data}` : "default_cover.png";
 
                 const artistObj = metadata.artist && metadata.artist !== "未知艺术家" ? [{ id: "", name: metadata.artist }] : [];
@@ -119,7 +128,7 @@ export const useLocalMusicStore = defineStore("localMusic", {
           if (metadata) {
             // 使用文件路径的文件名作为后备标题
             const fileName = filePath.split("\\").pop() || filePath.split("/").pop();
-            const hash = CryptoJS.MD5(metadata.title + metadata.duration).toString();
+            const hash = metadata.track_id;
             const coverData = metadata.cover_data ? `data:${metadata.cover_mime_type};base64,${metadata.cover_data}` : "default_cover.png";
 
             const artistObj = metadata.artist && metadata.artist !== "未知艺术家" ? [{ id: "", name: metadata.artist }] : [];
@@ -136,10 +145,35 @@ export const useLocalMusicStore = defineStore("localMusic", {
       } catch (error) {
         console.error("处理选中的音频文件失败:", error);
       } finally {
+        await this.saveLibrary();
         this.isLoading = false;
       }
     },
 
+    //旧版本的 id 为 MD5(标题 + 时长)，修改标签后会变化，且不同文件可能重复
+    //启动时一次性迁移为后端的稳定标识，并同步更新播放队列中的同一歌曲
+    async migrateTrackIds() {
+      const legacyTracks = this.localTracks.filter((track) => !isStableTrackId(track.id));
+      if (legacyTracks.length < 1) return;
+      const { queueTracks } = usePlayStore();
+      for (const track of legacyTracks) {
+        //文件已不存在时保留原样，下次启动再尝试
+        const metadata = await this.getAudioMetadata(track.url);
+        if (!metadata) continue;
+        const legacyId = track.id;
+        const id = metadata.track_id;
+        queueTracks.filter((item) => item.platform === LocalMusic.CODE && item.id === legacyId).forEach((item) => Object.assign(item, { id }));
+        Object.assign(track, { id });
+      }
+      await this.saveLibrary();
+      //同一文件被重复添加过时，迁移后只保留一条
+      const ids = new Set();
+      this.localTracks = this.localTracks.filter((track) => {
+        if (ids.has(track.id)) return false;
+        ids.add(track.id);
+        return true;
+      });
+    },
     removeItem(index) {
       if (isNaN(index)) return;
       if (index > -1) {